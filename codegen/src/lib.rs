@@ -26,6 +26,8 @@
 // NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+#![allow(clippy::needless_return)]
+
 use std::vec::Vec;
 use std::string::String;
 use proc_macro::{self, TokenStream};
@@ -33,9 +35,11 @@ use quote::quote;
 use syn::parse_macro_input;
 use syn::DeriveInput;
 use syn::FieldsNamed;
+use syn::Field;
 use proc_macro2::Span;
 use syn::Fields;
 use syn::Data;
+use syn::DataStruct;
 use syn::Type;
 use syn::PathArguments;
 use syn::GenericArgument;
@@ -57,15 +61,277 @@ fn expand_type_name(t: &syn::Type) -> String
             }
             return String::from(&s[0..s.len() - 2]);
         }
-        _ => panic!("Invalid generic type name for ComponentPool")
+        _ => panic!("Invalid component type name")
+    }
+}
+
+fn component_type_of(f: &Field) -> String
+{
+    if let Some(attr) = f.attrs.iter().find(|a| a.path.is_ident("component"))
+    {
+        match attr.parse_args::<Type>()
+        {
+            Ok(t) => return expand_type_name(&t),
+            Err(e) => panic!("Invalid component attribute for field {:?}: {}", f.ident, e)
+        }
+    }
+    if let Type::Path(p) = &f.ty
+    {
+        let last = &p.path.segments.last().unwrap();
+        if let PathArguments::AngleBracketed(b) = &last.arguments
+        {
+            if let Some(GenericArgument::Type(t)) = b.args.first()
+            {
+                return expand_type_name(t);
+            }
+        }
+    }
+    panic!("Could not identify type of component for field {:?}, use #[component(Type)] to specify it", f.ident);
+}
+
+struct ComponentField
+{
+    name: Ident,
+    component: String,
+    ty: Type,
+    hooks: bool,
+    reflect: bool
+}
+
+impl ComponentField
+{
+    fn component_type(&self) -> Type
+    {
+        return syn::parse_str::<Type>(&self.component).unwrap();
+    }
+
+    fn manager_trait(&self) -> Type
+    {
+        let mut s = self.component.clone();
+        s.push_str("Manager");
+        return syn::parse_str::<Type>(&s).unwrap();
+    }
+}
+
+fn serde_impl(ident: &Ident, v: &[ComponentField], dynamic: &Option<Ident>) -> proc_macro2::TokenStream
+{
+    let name = ident.to_string();
+    let dynamic_type: Type = syn::parse_quote!(regecs::component::DynamicComponents);
+    let mut field_names: Vec<&Ident> = v.iter().map(|f| &f.name).collect();
+    let mut field_types: Vec<&Type> = v.iter().map(|f| &f.ty).collect();
+    if let Some(field_name) = dynamic
+    {
+        field_names.push(field_name);
+        field_types.push(&dynamic_type);
+    }
+    let mut version_tokens = Vec::new();
+    for f in v.iter().filter(|f| !is_component_pool(&f.ty))
+    {
+        let component_type = f.component_type();
+        let msg = format!("field {} of {} stores a component with a schema version greater than 1 outside a ComponentPool, \
+            migrations only apply to ComponentPool", f.name, ident);
+        version_tokens.push(quote!
+        {
+            const _: () = assert!(<#component_type as regecs::component::Versioned>::VERSION == 1, #msg);
+        });
+    }
+    let mut hooks_tokens = Vec::new();
+    let mut reload_hooks_tokens = Vec::new();
+    for f in v.iter().filter(|f| f.hooks)
+    {
+        let field_name = &f.name;
+        let component_type = f.component_type();
+        hooks_tokens.push(quote!
+        {
+            mgr.#field_name.set_hooks(regecs::component::Hooks::<#ident>::of::<#component_type>());
+        });
+        reload_hooks_tokens.push(quote!
+        {
+            self.#field_name.set_hooks(regecs::component::Hooks::<#ident>::of::<#component_type>());
+        });
+    }
+    let mut visit_tokens = Vec::new();
+    let mut load_tokens = Vec::new();
+    for f in v
+    {
+        let field_name = &f.name;
+        let name = field_name.to_string();
+        let component_type = f.component_type();
+        visit_tokens.push(quote!
+        {
+            visitor.visit(#name, <#component_type as regecs::component::Versioned>::NAME, std::mem::size_of::<#component_type>(),
+                <#component_type as regecs::component::Versioned>::VERSION, &self.#field_name)?;
+        });
+        load_tokens.push(quote!
+        {
+            loader.load(#name, <#component_type as regecs::component::Versioned>::NAME, std::mem::size_of::<#component_type>(),
+                <#component_type as regecs::component::Versioned>::VERSION, &mut self.#field_name)?;
+        });
+    }
+    return quote!
+    {
+        const _: () =
+        {
+            #(#version_tokens)*
+
+            #[derive(regecs::serde::Serialize)]
+            #[serde(crate = "regecs::serde", rename = #name)]
+            struct Ser<'a>
+            {
+                #(#field_names: &'a #field_types,)*
+            }
+
+            #[derive(regecs::serde::Deserialize)]
+            #[serde(crate = "regecs::serde", rename = #name)]
+            struct De
+            {
+                #(#field_names: #field_types,)*
+            }
+
+            impl regecs::serde::Serialize for #ident
+            {
+                fn serialize<__S: regecs::serde::Serializer>(&self, serializer: __S) -> Result<__S::Ok, __S::Error>
+                {
+                    let data = Ser
+                    {
+                        #(#field_names: &self.#field_names,)*
+                    };
+                    return regecs::serde::Serialize::serialize(&data, serializer);
+                }
+            }
+
+            impl <'de> regecs::serde::Deserialize<'de> for #ident
+            {
+                fn deserialize<__D: regecs::serde::Deserializer<'de>>(deserializer: __D) -> Result<Self, __D::Error>
+                {
+                    let data: De = regecs::serde::Deserialize::deserialize(deserializer)?;
+                    #[allow(unused_mut)]
+                    let mut mgr = #ident
+                    {
+                        #(#field_names: data.#field_names,)*
+                    };
+                    #(#hooks_tokens)*
+                    return Ok(mgr);
+                }
+            }
+        };
+
+        impl regecs::component::SerializePools for #ident
+        {
+            fn visit_pools<__V: regecs::component::PoolVisitor>(&self, visitor: &mut __V) -> Result<(), __V::Error>
+            {
+                #(#visit_tokens)*
+                return Ok(());
+            }
+
+            fn load_pools<__L: regecs::component::PoolLoader>(&mut self, loader: &mut __L) -> Result<(), __L::Error>
+            {
+                #(#load_tokens)*
+                #(#reload_hooks_tokens)*
+                return Ok(());
+            }
+        }
+    };
+}
+
+fn is_component_pool(t: &syn::Type) -> bool
+{
+    if let Type::Path(p) = t
+    {
+        return p.path.segments.last().map(|s| s.ident == "ComponentPool").unwrap_or(false);
+    }
+    return false;
+}
+
+fn is_reflect_skip(attrs: &[syn::Attribute]) -> bool
+{
+    return attrs.iter().filter(|a| a.path.is_ident("reflect")).any(|a| match a.parse_args::<Ident>()
+    {
+        Ok(arg) if arg == "skip" => true,
+        _ => panic!("Invalid reflect attribute, expected #[reflect(skip)]")
+    });
+}
+
+fn reflect_pools_impl(ident: &Ident, v: &[ComponentField]) -> proc_macro2::TokenStream
+{
+    let mut info_tokens = Vec::new();
+    let mut owners_tokens = Vec::new();
+    let mut reflect_tokens = Vec::new();
+    let mut reflect_mut_tokens = Vec::new();
+    for f in v.iter().filter(|f| f.reflect)
+    {
+        let field_name = &f.name;
+        let name = field_name.to_string();
+        let component_type = f.component_type();
+        info_tokens.push(quote!
+        {
+            regecs::reflect::PoolInfo
+            {
+                name: #name,
+                component: std::any::type_name::<#component_type>()
+            }
+        });
+        owners_tokens.push(quote!
+        {
+            #name => Some(regecs::component::Storage::iter(&self.#field_name).map(|(owner, _)| owner).collect())
+        });
+        reflect_tokens.push(quote!
+        {
+            #name => regecs::component::Storage::get(&self.#field_name, owner).map(|c| c as &dyn regecs::reflect::Reflect)
+        });
+        reflect_mut_tokens.push(quote!
+        {
+            #name => regecs::component::Storage::edit(&mut self.#field_name, owner, &mut |c| f(c))
+        });
     }
+    return quote!
+    {
+        impl regecs::reflect::ReflectPools for #ident
+        {
+            fn reflected_pools(&self) -> Vec<regecs::reflect::PoolInfo>
+            {
+                return vec![#(#info_tokens),*];
+            }
+
+            fn owners(&self, pool: &str) -> Option<Vec<regecs::object::ObjectRef>>
+            {
+                return match pool
+                {
+                    #(#owners_tokens,)*
+                    _ => None
+                };
+            }
+
+            fn reflect(&self, pool: &str, owner: regecs::object::ObjectRef) -> Option<&dyn regecs::reflect::Reflect>
+            {
+                return match pool
+                {
+                    #(#reflect_tokens,)*
+                    _ => None
+                };
+            }
+
+            fn reflect_mut(&mut self, pool: &str, owner: regecs::object::ObjectRef, f: &mut dyn FnMut(&mut dyn regecs::reflect::Reflect) -> Result<(), regecs::error::Error>) -> Option<Result<(), regecs::error::Error>>
+            {
+                return match pool
+                {
+                    #(#reflect_mut_tokens,)*
+                    _ => None
+                };
+            }
+        }
+    };
 }
 
-#[proc_macro_derive(ComponentManager)]
+#[proc_macro_derive(ComponentManager, attributes(component, hooks, prefab, dynamic, serialize, reflect))]
 pub fn component_manager(input: TokenStream) -> TokenStream
 {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    let DeriveInput { ident, attrs, data, .. } = parse_macro_input!(input);
+    let prefab = attrs.iter().any(|a| a.path.is_ident("prefab"));
+    let serialize = attrs.iter().any(|a| a.path.is_ident("serialize"));
+    let reflect = attrs.iter().any(|a| a.path.is_ident("reflect"));
     let mut v = Vec::new();
+    let mut dynamic = None;
 
     match data
     {
@@ -75,39 +341,35 @@ pub fn component_manager(input: TokenStream) -> TokenStream
             {
                 for f in &named
                 {
-                    match &f.ty
+                    if f.attrs.iter().any(|a| a.path.is_ident("dynamic"))
                     {
-                        Type::Path(p) =>
+                        if dynamic.is_some()
                         {
-                            let last = &p.path.segments.last().unwrap();
-                            if let PathArguments::AngleBracketed(b) = &last.arguments
-                            {
-                                if let Some(v1) = b.args.first()
-                                {
-                                    if let GenericArgument::Type(t) = v1
-                                    {
-                                        let name = expand_type_name(&t);
-                                        if let Some(useless) = &f.ident
-                                        {
-                                            v.push((useless.clone(), name));
-                                        }
-                                        else
-                                        {
-                                            panic!("How is it possible that you get no identifier???!!!")
-                                        }
-                                    }
-                                    else
-                                    {
-                                        panic!("Could not identify type of component for field {:?}", f.ident);
-                                    }
-                                }
-                                else
-                                {
-                                    panic!("Could not identify type of component for field {:?}", f.ident);
-                                }
-                            }
-                        },
-                        _ => panic!("Could not identify type of component for field {:?}", f.ident),
+                            panic!("Only one field can be marked #[dynamic]");
+                        }
+                        dynamic = f.ident.clone();
+                        continue;
+                    }
+                    let component = component_type_of(f);
+                    let hooks = f.attrs.iter().any(|a| a.path.is_ident("hooks"));
+                    if hooks && !is_component_pool(&f.ty)
+                    {
+                        panic!("#[hooks] is only supported on ComponentPool fields");
+                    }
+                    if let Some(useless) = &f.ident
+                    {
+                        v.push(ComponentField
+                        {
+                            name: useless.clone(),
+                            component,
+                            ty: f.ty.clone(),
+                            hooks,
+                            reflect: !is_reflect_skip(&f.attrs)
+                        });
+                    }
+                    else
+                    {
+                        panic!("How is it possible that you get no identifier???!!!")
                     }
                 }
             },
@@ -116,32 +378,166 @@ pub fn component_manager(input: TokenStream) -> TokenStream
         _ => panic!("ComponentManager cannot be implemented on non-structs")
     };
     let mut impl_base_tokens = Vec::new();
-    for (field_name, _) in &v
+    if let Some(field_name) = &dynamic
     {
         impl_base_tokens.push(
             quote!
             {
-                #field_name: ComponentPool::new()
+                #field_name: regecs::component::DynamicComponents::new()
             }
         );
+    }
+    for f in &v
+    {
+        let field_name = &f.name;
+        let field_type = &f.ty;
+        let component_type = f.component_type();
+        if f.hooks
+        {
+            impl_base_tokens.push(
+                quote!
+                {
+                    #field_name:
+                    {
+                        let mut pool: #field_type = Default::default();
+                        pool.set_hooks(regecs::component::Hooks::<#ident>::of::<#component_type>());
+                        pool
+                    }
+                }
+            );
+        }
+        else
+        {
+            impl_base_tokens.push(
+                quote!
+                {
+                    #field_name: Default::default()
+                }
+            );
+        }
     };
+    let mut clear_tokens = Vec::new();
+    let mut tick_tokens = Vec::new();
+    let mut removed_tokens = Vec::new();
+    let mut hooks_tokens = Vec::new();
+    let mut compact_tokens = Vec::new();
+    let mut reindex_tokens = Vec::new();
+    let mut capture_tokens = Vec::new();
+    for f in &v
+    {
+        let field_name = &f.name;
+        clear_tokens.push(
+            quote!
+            {
+                if regecs::component::Storage::contains(&self.#field_name, target)
+                {
+                    let hook = regecs::component::Storage::lifecycle_hooks(&self.#field_name)
+                        .and_then(|h| h.downcast_ref::<regecs::component::Hooks<#ident>>())
+                        .and_then(|h| h.on_remove);
+                    if let Some(hook) = hook
+                    {
+                        hook(self, target);
+                    }
+                    regecs::component::Storage::remove_silent(&mut self.#field_name, target);
+                }
+            }
+        );
+        tick_tokens.push(
+            quote!
+            {
+                regecs::component::Storage::set_tick(&mut self.#field_name, tick);
+            }
+        );
+        removed_tokens.push(
+            quote!
+            {
+                regecs::component::Storage::clear_removed(&mut self.#field_name, before);
+            }
+        );
+        capture_tokens.push(
+            quote!
+            {
+                if let Some(comp) = regecs::component::Storage::get(&self.#field_name, target)
+                {
+                    let comp = Clone::clone(comp);
+                    list.push(Box::new(move |components: &mut #ident, owner: regecs::object::ObjectRef|
+                    {
+                        regecs::component::Storage::insert(&mut components.#field_name, owner, Clone::clone(&comp))?;
+                        return Ok(());
+                    }));
+                }
+            }
+        );
+        compact_tokens.push(
+            quote!
+            {
+                regecs::component::Storage::compact(&mut self.#field_name);
+            }
+        );
+        reindex_tokens.push(
+            quote!
+            {
+                // Violations stay dirty in the pool, lookups report them
+                let _ = regecs::component::Storage::reindex(&mut self.#field_name);
+            }
+        );
+        hooks_tokens.push(
+            quote!
+            {
+                while let Some(ev) = regecs::component::Storage::pop_lifecycle(&mut self.#field_name)
+                {
+                    idle = false;
+                    let hooks = regecs::component::Storage::lifecycle_hooks(&self.#field_name)
+                        .and_then(|h| h.downcast_ref::<regecs::component::Hooks<#ident>>())
+                        .copied();
+                    if let Some(hooks) = hooks
+                    {
+                        match ev
+                        {
+                            regecs::component::Lifecycle::Added(owner) =>
+                            {
+                                if let Some(hook) = hooks.on_add
+                                {
+                                    hook(self, owner);
+                                }
+                            },
+                            regecs::component::Lifecycle::Removed(owner) =>
+                            {
+                                if let Some(hook) = hooks.on_remove
+                                {
+                                    hook(self, owner);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        );
+    }
     let mut impls_tokens = Vec::new();
-    for (field_name, component_type) in &v
+    for f in &v
     {
-        let mut s = component_type.clone();
-        s.push_str("Manager");
-        let new_ident = syn::parse_str::<Type>(&s).unwrap();
-        let new_ident1 = syn::parse_str::<Type>(&component_type).unwrap();
+        let field_name = &f.name;
+        let field_type = &f.ty;
+        let new_ident = f.manager_trait();
+        let new_ident1 = f.component_type();
         let mgr_impl_tokens = quote!
         {
             impl #new_ident for #ident
             {
-                fn get(&mut self, id: usize) -> &mut #new_ident1
+                type Storage = #field_type;
+
+                fn get(&self, owner: regecs::object::ObjectRef) -> Option<&#new_ident1>
+                {
+                    return regecs::component::Storage::get(&self.#field_name, owner);
+                }
+
+                fn get_mut(&mut self, owner: regecs::object::ObjectRef) -> Option<&mut #new_ident1>
                 {
-                    return self.#field_name.get(id);
+                    return regecs::component::Storage::get_mut(&mut self.#field_name, owner);
                 }
 
-                fn get_pool(&mut self) -> &mut ComponentPool<#new_ident1>
+                fn get_pool(&mut self) -> &mut #field_type
                 {
                     return &mut self.#field_name;
                 }
@@ -149,6 +545,54 @@ pub fn component_manager(input: TokenStream) -> TokenStream
         };
         impls_tokens.push(mgr_impl_tokens);
     }
+    let mut dynamic_tokens = None;
+    if let Some(field_name) = &dynamic
+    {
+        clear_tokens.push(
+            quote!
+            {
+                self.#field_name.clear(target);
+            }
+        );
+        dynamic_tokens = Some(quote!
+        {
+            fn dynamic(&self) -> Option<&regecs::component::DynamicComponents>
+            {
+                return Some(&self.#field_name);
+            }
+
+            fn dynamic_mut(&mut self) -> Option<&mut regecs::component::DynamicComponents>
+            {
+                return Some(&mut self.#field_name);
+            }
+        });
+    }
+    let mut capture_impl_tokens = None;
+    if prefab
+    {
+        capture_impl_tokens = Some(quote!
+        {
+            impl regecs::prefab::Capture for #ident
+            {
+                fn capture(&self, target: regecs::object::ObjectRef) -> Vec<regecs::prefab::PrefabComponent<Self>>
+                {
+                    let mut list: Vec<regecs::prefab::PrefabComponent<Self>> = Vec::new();
+                    #(#capture_tokens)*
+                    return list;
+                }
+            }
+        });
+    }
+    let mut serde_tokens = None;
+    if serialize
+    {
+        serde_tokens = Some(serde_impl(&ident, &v, &dynamic));
+    }
+    let mut reflect_tokens = None;
+    if reflect
+    {
+        reflect_tokens = Some(reflect_pools_impl(&ident, &v));
+    }
     let output = quote!
     {
         impl #ident
@@ -162,16 +606,107 @@ pub fn component_manager(input: TokenStream) -> TokenStream
             }
         }
 
+        impl regecs::component::ComponentManager for #ident
+        {
+            fn clear_components(&mut self, target: regecs::object::ObjectRef)
+            {
+                #(#clear_tokens)*
+                regecs::component::ComponentManager::run_hooks(self);
+            }
+
+            fn set_tick(&mut self, tick: regecs::component::Tick)
+            {
+                #(#tick_tokens)*
+            }
+
+            fn clear_removed(&mut self, before: regecs::component::Tick)
+            {
+                #(#removed_tokens)*
+            }
+
+            fn run_hooks(&mut self)
+            {
+                loop
+                {
+                    let mut idle = true;
+                    #(#hooks_tokens)*
+                    if idle
+                    {
+                        break;
+                    }
+                }
+            }
+
+            fn compact(&mut self)
+            {
+                #(#compact_tokens)*
+            }
+
+            fn reindex(&mut self)
+            {
+                #(#reindex_tokens)*
+            }
+
+            #dynamic_tokens
+        }
+
+        #capture_impl_tokens
+
+        #serde_tokens
+
+        #reflect_tokens
+
         #(#impls_tokens)*
     };
     return output.into();
 }
 
-#[proc_macro_derive(Component)]
+fn parse_component_name(ident: &Ident, attrs: &[syn::Attribute]) -> String
+{
+    let attr = match attrs.iter().find(|a| a.path.is_ident("component"))
+    {
+        Some(attr) => attr,
+        None => return ident.to_string()
+    };
+    if let Ok(syn::Meta::List(list)) = attr.parse_meta()
+    {
+        if let Some(syn::NestedMeta::Meta(syn::Meta::NameValue(nv))) = list.nested.first()
+        {
+            if let (true, syn::Lit::Str(name), 1) = (nv.path.is_ident("name"), &nv.lit, list.nested.len())
+            {
+                return name.value();
+            }
+        }
+    }
+    panic!("Invalid component attribute for component {}, expected #[component(name = \"...\")]", ident);
+}
+
+#[proc_macro_derive(Component, attributes(version, component))]
 pub fn component(input: TokenStream) -> TokenStream
 {
-    let DeriveInput { ident, vis, .. } = parse_macro_input!(input);
+    let DeriveInput { ident, vis, attrs, data, .. } = parse_macro_input!(input);
+    let version = match attrs.iter().find(|a| a.path.is_ident("version"))
+    {
+        Some(attr) => match attr.parse_args::<syn::LitInt>().and_then(|v| v.base10_parse::<u32>())
+        {
+            Ok(v) => v,
+            Err(e) => panic!("Invalid version attribute for component {}: {}", ident, e)
+        },
+        None => 1
+    };
+    let name = parse_component_name(&ident, &attrs);
 
+    let tag_tokens = match data
+    {
+        Data::Struct(DataStruct { fields: Fields::Unit, .. }) => Some(quote!
+        {
+            impl regecs::component::Tag for #ident
+            {
+                const INSTANCE: Self = #ident;
+            }
+        }),
+        _ => None
+    };
     let mut s = ident.to_string();
     s.push_str("Manager");
     let new_ident = Ident::new(&s, Span::call_site());
@@ -184,8 +719,171 @@ pub fn component(input: TokenStream) -> TokenStream
     {
         #vis trait #new_ident
         {
-            fn get(&mut self, id: usize) -> &mut #ident;
-            fn get_pool(&mut self) -> &mut ComponentPool<#ident>;
+            type Storage: regecs::component::Storage<Component = #ident>;
+
+            fn get(&self, owner: regecs::object::ObjectRef) -> Option<&#ident>;
+            fn get_mut(&mut self, owner: regecs::object::ObjectRef) -> Option<&mut #ident>;
+            fn get_pool(&mut self) -> &mut Self::Storage;
+        }
+
+        impl <M: #new_ident> regecs::bundle::Bundle<M> for #ident
+        {
+            fn insert(self, owner: regecs::object::ObjectRef, components: &mut M) -> Result<(), regecs::error::Error>
+            {
+                regecs::component::Storage::insert(<M as #new_ident>::get_pool(components), owner, self)?;
+                return Ok(());
+            }
+        }
+
+        impl regecs::component::Versioned for #ident
+        {
+            const NAME: &'static str = #name;
+            const VERSION: u32 = #version;
+        }
+
+        #tag_tokens
+    };
+    return output.into();
+}
+
+#[proc_macro_derive(Bundle)]
+pub fn bundle(input: TokenStream) -> TokenStream
+{
+    let DeriveInput { ident, generics, data, .. } = parse_macro_input!(input);
+    let fields = match data
+    {
+        Data::Struct(s) => s.fields,
+        _ => panic!("Bundle cannot be implemented on non-structs")
+    };
+    let mut bounds = Vec::new();
+    let mut insert_tokens = Vec::new();
+    for (i, f) in fields.iter().enumerate()
+    {
+        let ty = &f.ty;
+        bounds.push(quote! { #ty: regecs::bundle::Bundle<__M> });
+        let member = match &f.ident
+        {
+            Some(name) => quote! { #name },
+            None =>
+            {
+                let index = syn::Index::from(i);
+                quote! { #index }
+            }
+        };
+        insert_tokens.push(quote!
+        {
+            regecs::bundle::Bundle::insert(self.#member, owner, components)?;
+        });
+    }
+    let params = generics.params.iter();
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let predicates = where_clause.map(|w| &w.predicates);
+    let output = quote!
+    {
+        impl <#(#params,)* __M> regecs::bundle::Bundle<__M> for #ident #ty_generics
+            where #(#bounds,)* #predicates
+        {
+            fn insert(self, owner: regecs::object::ObjectRef, components: &mut __M) -> Result<(), regecs::error::Error>
+            {
+                #(#insert_tokens)*
+                return Ok(());
+            }
+        }
+    };
+    return output.into();
+}
+
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn reflect(input: TokenStream) -> TokenStream
+{
+    let DeriveInput { ident, generics, data, .. } = parse_macro_input!(input);
+    let fields = match data
+    {
+        Data::Struct(s) => s.fields,
+        _ => panic!("Reflect cannot be implemented on non-structs")
+    };
+    let mut bounds = Vec::new();
+    let mut info_tokens = Vec::new();
+    let mut field_tokens = Vec::new();
+    let mut field_mut_tokens = Vec::new();
+    for (i, f) in fields.iter().enumerate()
+    {
+        if is_reflect_skip(&f.attrs)
+        {
+            continue;
+        }
+        let ty = &f.ty;
+        let (name, member) = match &f.ident
+        {
+            Some(name) => (name.to_string(), quote! { #name }),
+            None =>
+            {
+                let index = syn::Index::from(i);
+                (i.to_string(), quote! { #index })
+            }
+        };
+        bounds.push(quote! { #ty: regecs::reflect::Reflect });
+        info_tokens.push(quote!
+        {
+            regecs::reflect::FieldInfo
+            {
+                name: #name,
+                type_name: std::any::type_name::<#ty>()
+            }
+        });
+        field_tokens.push(quote!
+        {
+            #name => Some(&self.#member)
+        });
+        field_mut_tokens.push(quote!
+        {
+            #name => Some(&mut self.#member)
+        });
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let predicates = where_clause.map(|w| &w.predicates);
+    let output = quote!
+    {
+        impl #impl_generics regecs::reflect::Reflect for #ident #ty_generics
+            where Self: 'static, #(#bounds,)* #predicates
+        {
+            fn type_name(&self) -> &'static str
+            {
+                return std::any::type_name::<Self>();
+            }
+
+            fn fields(&self) -> Vec<regecs::reflect::FieldInfo>
+            {
+                return vec![#(#info_tokens),*];
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn regecs::reflect::Reflect>
+            {
+                return match name
+                {
+                    #(#field_tokens,)*
+                    _ => None
+                };
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn regecs::reflect::Reflect>
+            {
+                return match name
+                {
+                    #(#field_mut_tokens,)*
+                    _ => None
+                };
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any
+            {
+                return self;
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn std::any::Any
+            {
+                return self;
+            }
         }
     };
     return output.into();
@@ -0,0 +1,149 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::bundle::Bundle;
+use regecs::component::ComponentPool;
+use regecs::component::SparseSetPool;
+use regecs::component::TagPool;
+use regecs::component::Storage;
+use regecs::event::EventContext;
+use regecs::event::EventResult;
+use regecs::object::Object;
+use regecs::object::ObjectRef;
+use regecs::scene::Scene;
+use regecs_codegen::Bundle;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+
+#[derive(Component, Debug, PartialEq)]
+pub struct Position(f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+pub struct Velocity(f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+pub struct Player;
+
+#[derive(ComponentManager)]
+pub struct Components
+{
+    positions: ComponentPool<Position>,
+    velocities: SparseSetPool<Velocity>,
+    players: TagPool<Player>
+}
+
+#[derive(Bundle)]
+struct Body
+{
+    position: Position,
+    velocity: Velocity
+}
+
+#[derive(Bundle)]
+struct PlayerBody(Body, Player);
+
+fn scene() -> Scene<(), Components>
+{
+    return Scene::new(Components::new());
+}
+
+#[test]
+fn derived_bundle_inserts_every_field()
+{
+    let mut components = Components::new();
+    let owner = ObjectRef::new(2, 0);
+    PlayerBody(Body { position: Position(1.0, 2.0), velocity: Velocity(3.0, 4.0) }, Player).insert(owner, &mut components).unwrap();
+    assert_eq!(components.positions.get(owner), Some(&Position(1.0, 2.0)));
+    assert_eq!(Storage::get(&components.velocities, owner), Some(&Velocity(3.0, 4.0)));
+    assert!(Storage::contains(&components.players, owner));
+}
+
+#[test]
+fn builder_spawns_entity_with_components()
+{
+    let mut scene = scene();
+    let a = scene.spawn().with(Position(1.0, 0.0)).with((Velocity(0.0, 1.0), Player)).id();
+    let b = scene.spawn().with(Body { position: Position(5.0, 5.0), velocity: Velocity(-1.0, 0.0) }).id();
+    assert_ne!(a, b);
+    assert!(scene.contains_object(a));
+    assert!(scene.contains_object(b));
+    let components = scene.components();
+    assert_eq!(components.positions.get(a), Some(&Position(1.0, 0.0)));
+    assert_eq!(Storage::get(&components.velocities, a), Some(&Velocity(0.0, 1.0)));
+    assert!(Storage::contains(&components.players, a));
+    assert_eq!(components.positions.get(b), Some(&Position(5.0, 5.0)));
+    assert!(!Storage::contains(&components.players, b));
+}
+
+#[test]
+fn builder_components_are_removed_with_entity()
+{
+    let mut scene = scene();
+    let a = scene.spawn().with((Position(1.0, 0.0), Player)).id();
+    scene.remove_object(a).unwrap();
+    let b = scene.spawn().with(Velocity(0.0, 0.0)).id();
+    assert_eq!(a.index(), b.index());
+    let components = scene.components();
+    assert_eq!(components.positions.get(a), None);
+    assert!(!Storage::contains(&components.players, b));
+    assert!(Storage::contains(&components.velocities, b));
+}
+
+struct Probe;
+
+impl Object<(), Components> for Probe
+{
+    type EventType = ();
+
+    fn event(&mut self, _: &(), _: EventContext<(), Components>) -> Option<EventResult>
+    {
+        return None;
+    }
+
+    fn init(&mut self, ptr: ObjectRef, components: &mut Components)
+    {
+        // Components of the builder are inserted before the object is initialized
+        let x = components.positions.get(ptr).unwrap().0;
+        Storage::insert(&mut components.velocities, ptr, Velocity(x, x)).unwrap();
+    }
+
+    fn remove(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+}
+
+#[test]
+fn builder_object_sees_its_components()
+{
+    let mut scene = scene();
+    let a = scene.spawn().with(Position(7.0, 0.0)).object(Probe).id();
+    assert!(scene.get_object(a).is_some());
+    assert_eq!(Storage::get(&scene.components().velocities, a), Some(&Velocity(7.0, 7.0)));
+}
@@ -0,0 +1,246 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentManager;
+use regecs::component::ComponentPool;
+use regecs::component::SparseSetPool;
+use regecs::component::TagPool;
+use regecs::component::Storage;
+use regecs::delta::Delta;
+use regecs::delta::PoolDelta;
+use regecs::object::ObjectRef;
+use regecs::scene::Scene;
+use regecs::system::System;
+use regecs::system::SystemContext;
+use bincode::Options;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+use serde::Serialize;
+use serde::Deserialize;
+
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Position(f32, f32);
+
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Name(String);
+
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Player;
+
+#[derive(ComponentManager)]
+#[serialize]
+pub struct Components
+{
+    positions: ComponentPool<Position>,
+    names: SparseSetPool<Name>,
+    players: TagPool<Player>
+}
+
+#[derive(ComponentManager)]
+#[serialize]
+pub struct Renamed
+{
+    positions: ComponentPool<Name>
+}
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+fn populate() -> Components
+{
+    let mut components = Components::new();
+    for i in 0..4
+    {
+        components.positions.insert(obj(i), Position(i as f32, 0.0)).unwrap();
+        components.names.insert(obj(i), Name(format!("object {}", i)));
+    }
+    components.players.insert(obj(0), Player).unwrap();
+    return components;
+}
+
+fn assert_same(a: &Components, b: &Components)
+{
+    assert_eq!(a.positions.iter().collect::<Vec<_>>(), b.positions.iter().collect::<Vec<_>>());
+    let mut names_a: Vec<_> = a.names.iter().collect();
+    let mut names_b: Vec<_> = b.names.iter().collect();
+    names_a.sort_by_key(|(o, _)| o.index());
+    names_b.sort_by_key(|(o, _)| o.index());
+    assert_eq!(names_a, names_b);
+    assert_eq!(a.players.iter().map(|(o, _)| o).collect::<Vec<_>>(), b.players.iter().map(|(o, _)| o).collect::<Vec<_>>());
+}
+
+fn mutate(components: &mut Components)
+{
+    components.positions.get_mut(obj(1)).unwrap().0 = 10.0;
+    components.positions.remove(obj(2));
+    components.positions.insert(obj(5), Position(5.0, 5.0)).unwrap();
+    components.names.remove(obj(3));
+    components.names.get_mut(obj(0)).unwrap().0 = String::from("player");
+    components.players.remove(obj(0));
+    components.players.insert(obj(1), Player).unwrap();
+}
+
+#[test]
+fn between_round_trip()
+{
+    let old = populate();
+    let mut new = populate();
+    mutate(&mut new);
+    let delta = Delta::between(&old, &new).unwrap();
+    let positions = &delta.pools()[0];
+    assert_eq!(positions.name, "positions");
+    assert_eq!(positions.added.len(), 1);
+    assert_eq!(positions.modified.len(), 1);
+    assert_eq!(positions.removed, vec![obj(2)]);
+    let mut target = populate();
+    delta.apply(&mut target).unwrap();
+    assert_same(&target, &new);
+}
+
+#[test]
+fn unchanged_is_empty()
+{
+    let delta = Delta::between(&populate(), &populate()).unwrap();
+    assert!(delta.is_empty());
+    let mut target = populate();
+    delta.apply(&mut target).unwrap();
+    assert_same(&target, &populate());
+}
+
+#[test]
+fn since_round_trip()
+{
+    let mut source = populate();
+    source.set_tick(2);
+    let baseline = populate();
+    mutate(&mut source);
+    let delta = Delta::since(&source, 1).unwrap();
+    let positions = &delta.pools()[0];
+    assert!(!positions.full);
+    assert_eq!(positions.added.len(), 1);
+    assert_eq!(positions.modified.len(), 1);
+    assert_eq!(positions.removed, vec![obj(2)]);
+    // Pools which do not track changes are sent in full
+    assert!(delta.pools()[1].full);
+    assert!(delta.pools()[2].full);
+    let mut target = baseline;
+    delta.apply(&mut target).unwrap();
+    assert_same(&target, &source);
+}
+
+#[test]
+fn bytes_round_trip()
+{
+    let old = populate();
+    let mut new = populate();
+    mutate(&mut new);
+    let bytes = Delta::between(&old, &new).unwrap().to_bytes().unwrap();
+    let delta = Delta::from_bytes(&bytes).unwrap();
+    let mut target = populate();
+    delta.apply(&mut target).unwrap();
+    assert_same(&target, &new);
+    assert!(Delta::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+}
+
+#[test]
+fn type_mismatch()
+{
+    let old = populate();
+    let mut new = populate();
+    mutate(&mut new);
+    let delta = Delta::between(&old, &new).unwrap();
+    let mut target = Renamed::new();
+    assert!(delta.apply(&mut target).is_err());
+}
+
+#[test]
+fn corrupt_delta_leaves_components_unchanged()
+{
+    #[derive(Serialize)]
+    struct RawDelta
+    {
+        pools: Vec<PoolDelta>
+    }
+
+    let old = populate();
+    let mut new = populate();
+    mutate(&mut new);
+    let mut pools = Delta::between(&old, &new).unwrap().pools().to_vec();
+    // Positions come first and are valid, the names pool is corrupt
+    pools[1].modified[0].1.clear();
+    let bytes = bincode::DefaultOptions::new().serialize(&RawDelta { pools }).unwrap();
+    let delta = Delta::from_bytes(&bytes).unwrap();
+    let mut target = populate();
+    assert!(delta.apply(&mut target).is_err());
+    assert_same(&target, &populate());
+}
+
+struct RemoveOnce(bool);
+
+impl System<(), Components> for RemoveOnce
+{
+    fn update(&mut self, context: SystemContext<(), Components>)
+    {
+        if !self.0
+        {
+            context.components.positions.remove(obj(2));
+            self.0 = true;
+        }
+    }
+}
+
+#[test]
+fn forgotten_removals_send_the_pool_in_full()
+{
+    let mut scene: Scene<(), Components> = Scene::new(populate());
+    scene.add_system(RemoveOnce(false));
+    let baseline = scene.tick();
+    scene.update(&mut ());
+    let delta = Delta::since(scene.components(), baseline).unwrap();
+    assert!(!delta.pools()[0].full);
+    assert_eq!(delta.pools()[0].removed, vec![obj(2)]);
+    for _ in 0..3
+    {
+        scene.update(&mut ());
+    }
+    let delta = Delta::since(scene.components(), baseline).unwrap();
+    assert!(delta.pools()[0].full);
+    let mut target = populate();
+    delta.apply(&mut target).unwrap();
+    let mut positions: Vec<_> = Storage::iter(&target.positions).collect();
+    positions.sort_by_key(|(o, _)| o.index());
+    assert_eq!(positions, Storage::iter(&scene.components().positions).collect::<Vec<_>>());
+    assert_eq!(target.positions.get(obj(2)), None);
+    // A baseline taken after the removal still gets an incremental delta
+    let delta = Delta::since(scene.components(), scene.tick()).unwrap();
+    assert!(!delta.pools()[0].full);
+}
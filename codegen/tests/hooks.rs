@@ -0,0 +1,154 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentManager;
+use regecs::component::ComponentHooks;
+use regecs::component::ComponentPool;
+use regecs::component::Storage;
+use regecs::object::ObjectRef;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+
+#[derive(Component)]
+pub struct Health(u32);
+
+#[derive(Component)]
+pub struct Marker;
+
+#[derive(Component, Default)]
+pub struct Log(Vec<String>);
+
+#[derive(ComponentManager)]
+pub struct Components
+{
+    #[hooks]
+    healths: ComponentPool<Health>,
+    #[hooks]
+    markers: ComponentPool<Marker>,
+    log: ComponentPool<Log>
+}
+
+fn log(components: &mut Components, line: String)
+{
+    let owner = ObjectRef::new(1000, 0);
+    if !components.log.contains(owner)
+    {
+        components.log.add(owner, Log::default());
+    }
+    components.log.get_mut(owner).unwrap().0.push(line);
+}
+
+impl ComponentHooks<Components> for Health
+{
+    fn on_add(components: &mut Components, owner: ObjectRef)
+    {
+        components.markers.add(owner, Marker);
+        log(components, format!("add health {}", owner));
+    }
+
+    fn on_remove(components: &mut Components, owner: ObjectRef)
+    {
+        let health = components.healths.get(owner).map(|h| h.0);
+        log(components, format!("remove health {} {:?}", owner, health));
+    }
+}
+
+impl ComponentHooks<Components> for Marker
+{
+    fn on_add(components: &mut Components, owner: ObjectRef)
+    {
+        log(components, format!("add marker {}", owner));
+    }
+}
+
+fn lines(components: &Components) -> Vec<String>
+{
+    return components.log.get(ObjectRef::new(1000, 0)).map(|l| l.0.clone()).unwrap_or_default();
+}
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+#[test]
+fn hooks_run_in_order_and_chain()
+{
+    let mut components = Components::new();
+    components.healths.add(obj(0), Health(10));
+    assert!(lines(&components).is_empty());
+    components.run_hooks();
+    assert_eq!(lines(&components), vec!["add health 0v0", "add marker 0v0"]);
+    assert!(components.markers.contains(obj(0)));
+}
+
+#[test]
+fn on_remove_sees_component_when_clearing()
+{
+    let mut components = Components::new();
+    components.healths.add(obj(0), Health(10));
+    components.run_hooks();
+    components.clear_components(obj(0));
+    assert_eq!(lines(&components)[2], "remove health 0v0 Some(10)");
+    assert_eq!(lines(&components).len(), 3);
+    assert!(!components.healths.contains(obj(0)));
+    assert!(!components.markers.contains(obj(0)));
+}
+
+#[test]
+fn on_remove_runs_after_direct_removal()
+{
+    let mut components = Components::new();
+    components.healths.add(obj(0), Health(10));
+    components.run_hooks();
+    assert_eq!(Storage::remove(&mut components.healths, obj(0)).map(|h| h.0), Some(10));
+    components.run_hooks();
+    assert_eq!(lines(&components)[2], "remove health 0v0 None");
+}
+
+#[test]
+fn cleared_hooks_are_not_run()
+{
+    let mut components = Components::new();
+    components.healths.clear_hooks();
+    components.healths.add(obj(0), Health(10));
+    components.run_hooks();
+    assert!(lines(&components).is_empty());
+}
+
+#[test]
+fn managers_with_hooks_are_send_and_sync()
+{
+    fn assert_send_sync<T: Send + Sync>()
+    {
+    }
+    assert_send_sync::<Components>();
+    assert_send_sync::<ComponentPool<Health>>();
+}
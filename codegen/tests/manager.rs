@@ -0,0 +1,159 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentManager;
+use regecs::component::ComponentPool;
+use regecs::component::HashIndex;
+use regecs::component::SparseSetPool;
+use regecs::component::TagPool;
+use regecs::component::Storage;
+use regecs::error::Error;
+use regecs::object::ObjectRef;
+use regecs::query::Query;
+use regecs::scene::Scene;
+use regecs::system::System;
+use regecs::system::SystemContext;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+
+#[derive(Component, Debug, PartialEq)]
+pub struct Position(f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+pub struct Name(String);
+
+#[derive(Component, Debug, PartialEq)]
+pub struct Player;
+
+#[derive(ComponentManager)]
+pub struct Components
+{
+    positions: ComponentPool<Position>,
+    names: SparseSetPool<Name>,
+    players: TagPool<Player>
+}
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+#[test]
+fn clear_components_empties_every_pool()
+{
+    let mut components = Components::new();
+    for i in 0..2
+    {
+        components.positions.insert(obj(i), Position(i as f32, 0.0)).unwrap();
+        components.names.insert(obj(i), Name(format!("object {}", i)));
+        components.players.insert(obj(i), Player).unwrap();
+    }
+    components.clear_components(obj(0));
+    assert!(!components.positions.contains(obj(0)));
+    assert!(!Storage::contains(&components.names, obj(0)));
+    assert!(!Storage::contains(&components.players, obj(0)));
+    assert_eq!(components.positions.get(obj(1)), Some(&Position(1.0, 0.0)));
+    assert_eq!(Storage::get(&components.names, obj(1)), Some(&Name(String::from("object 1"))));
+    assert!(Storage::contains(&components.players, obj(1)));
+}
+
+#[test]
+fn clear_components_ignores_missing_components()
+{
+    let mut components = Components::new();
+    components.names.insert(obj(3), Name(String::from("lonely")));
+    components.clear_components(obj(3));
+    components.clear_components(obj(5));
+    assert!(Storage::is_empty(&components.names));
+}
+
+#[test]
+fn manager_traits_access_pools()
+{
+    let mut components = Components::new();
+    components.positions.insert(obj(0), Position(1.0, 2.0)).unwrap();
+    PositionManager::get_mut(&mut components, obj(0)).unwrap().0 = 3.0;
+    assert_eq!(PositionManager::get(&components, obj(0)), Some(&Position(3.0, 2.0)));
+    assert_eq!(NameManager::get(&components, obj(0)), None);
+    assert_eq!(Storage::len(NameManager::get_pool(&mut components)), 0);
+}
+
+#[derive(ComponentManager)]
+pub struct Indexed
+{
+    names: ComponentPool<Name>,
+    positions: ComponentPool<Position>
+}
+
+fn indexed() -> Indexed
+{
+    let mut components = Indexed::new();
+    components.names.add_index(HashIndex::new("name", |n: &Name| n.0.clone()).unique()).unwrap();
+    return components;
+}
+
+struct Shout;
+
+impl System<(), Indexed> for Shout
+{
+    fn update(&mut self, context: SystemContext<(), Indexed>)
+    {
+        for (_, name) in Query::new(&mut context.components.names)
+        {
+            name.0.push('!');
+        }
+    }
+}
+
+#[test]
+fn scene_reindexes_after_each_system()
+{
+    let mut scene = Scene::new(indexed());
+    let a = scene.spawn().with(Name(String::from("ann"))).id();
+    scene.add_system(Shout);
+    scene.update(&mut ());
+    assert!(!scene.components().names.is_dirty());
+    assert_eq!(scene.components().names.lookup("name", &String::from("ann!")).unwrap(), &[a]);
+}
+
+#[test]
+fn spawning_a_unique_violation_fails()
+{
+    let mut scene: Scene<(), Indexed> = Scene::new(indexed());
+    let a = scene.spawn().with(Name(String::from("ann"))).id();
+    let res = scene.spawn().with(Position(1.0, 2.0)).with(Name(String::from("ann"))).try_id();
+    assert!(matches!(res, Err(Error::UniqueViolation(_, owner)) if owner == a));
+    assert_eq!(scene.object_count(), 1);
+    assert!(scene.components().positions.is_empty());
+    assert_eq!(scene.components().names.lookup("name", &String::from("ann")).unwrap(), &[a]);
+    let b = scene.spawn().with(Name(String::from("bob"))).id();
+    assert_ne!(b.index(), a.index());
+    assert_eq!(b.generation(), 1);
+}
@@ -0,0 +1,163 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentPool;
+use regecs::error::Error;
+use regecs::migration::Migrations;
+use regecs::object::ObjectRef;
+use regecs::scene::Scene;
+use regecs::snapshot::Snapshot;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+use serde::Serialize;
+use serde::Deserialize;
+
+mod v1
+{
+    use regecs::component::ComponentPool;
+    use regecs_codegen::Component;
+    use regecs_codegen::ComponentManager;
+    use serde::Serialize;
+    use serde::Deserialize;
+
+    #[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+    #[component(name = "health")]
+    pub struct Health(pub u32);
+
+    #[derive(ComponentManager)]
+    #[serialize]
+    pub struct Components
+    {
+        pub health: ComponentPool<Health>
+    }
+}
+
+/// Layout of health at schema version 2
+#[derive(Serialize, Deserialize)]
+struct HealthV2
+{
+    current: u32,
+    max: u32
+}
+
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[component(name = "health")]
+#[version(3)]
+pub struct Health
+{
+    current: u32,
+    max: u32,
+    regen: f32
+}
+
+#[derive(ComponentManager)]
+#[serialize]
+pub struct Components
+{
+    health: ComponentPool<Health>
+}
+
+fn to_v2(old: v1::Health) -> HealthV2
+{
+    return HealthV2
+    {
+        current: old.0,
+        max: 100
+    };
+}
+
+fn to_v3(old: HealthV2) -> Health
+{
+    return Health
+    {
+        current: old.current,
+        max: old.max,
+        regen: 0.5
+    };
+}
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+fn saved_v1() -> Vec<u8>
+{
+    let mut scene: Scene<(), v1::Components> = Scene::new(v1::Components::new());
+    scene.spawn().with(v1::Health(10)).id();
+    let b = scene.spawn().with(v1::Health(20)).id();
+    scene.spawn().with(v1::Health(30)).id();
+    scene.remove_object(b).unwrap();
+    return Snapshot::new().write(&scene).unwrap();
+}
+
+#[test]
+fn migrations_run_every_step_of_the_chain()
+{
+    let migrations = Migrations::new()
+        .register::<Health, v1::Health, HealthV2, _>(1, to_v2)
+        .register::<Health, HealthV2, Health, _>(2, to_v3);
+    assert!(migrations.contains("health", 1));
+    assert!(migrations.contains("health", 2));
+    assert!(!migrations.contains("health", 3));
+    let mut scene: Scene<(), Components> = Scene::new(Components::new());
+    Snapshot::new().migrations(migrations).read(&saved_v1(), &mut scene).unwrap();
+    let health = &scene.components().health;
+    assert_eq!(health.get(obj(0)), Some(&Health { current: 10, max: 100, regen: 0.5 }));
+    assert_eq!(health.get(obj(1)), None);
+    assert_eq!(health.get(obj(2)), Some(&Health { current: 30, max: 100, regen: 0.5 }));
+    assert_eq!(health.len(), 2);
+}
+
+#[test]
+fn missing_steps_are_reported()
+{
+    let migrations = Migrations::new().register::<Health, v1::Health, HealthV2, _>(1, to_v2);
+    let mut scene: Scene<(), Components> = Scene::new(Components::new());
+    let existing = scene.spawn().with(Health { current: 1, max: 1, regen: 0.0 }).id();
+    let res = Snapshot::new().migrations(migrations).read(&saved_v1(), &mut scene);
+    assert!(matches!(res, Err(Error::MissingMigration(name, 2)) if name == "health"));
+    assert_eq!(scene.components().health.get(existing), Some(&Health { current: 1, max: 1, regen: 0.0 }));
+    let res = Snapshot::new().read(&saved_v1(), &mut scene);
+    assert!(matches!(res, Err(Error::MissingMigration(name, 1)) if name == "health"));
+}
+
+#[test]
+fn newer_schema_versions_are_rejected()
+{
+    let mut scene: Scene<(), Components> = Scene::new(Components::new());
+    scene.spawn().with(Health { current: 5, max: 5, regen: 1.0 }).id();
+    let data = Snapshot::new().write(&scene).unwrap();
+    let mut old: Scene<(), v1::Components> = Scene::new(v1::Components::new());
+    old.spawn().with(v1::Health(1)).id();
+    let res = Snapshot::new().read(&data, &mut old);
+    assert!(matches!(res, Err(Error::InvalidSnapshot(reason)) if reason.contains("newer")));
+    assert_eq!(old.components().health.get(obj(0)), Some(&v1::Health(1)));
+}
@@ -0,0 +1,198 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentInfo;
+use regecs::component::ComponentPool;
+use regecs::component::DynamicComponents;
+use regecs::event::EventContext;
+use regecs::event::EventResult;
+use regecs::object::Object;
+use regecs::object::ObjectRef;
+use regecs::prefab::Prefab;
+use regecs::scene::Scene;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct Health(u32);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Script(String);
+
+/// *not clonable, never captured*
+struct Handle;
+
+#[derive(ComponentManager)]
+#[prefab]
+pub struct Components
+{
+    health: ComponentPool<Health>,
+    #[dynamic]
+    dynamic: DynamicComponents
+}
+
+#[derive(Clone)]
+struct Monster
+{
+    name: &'static str
+}
+
+impl Object<Vec<&'static str>, Components> for Monster
+{
+    type EventType = ();
+
+    fn event(&mut self, _: &(), context: EventContext<Vec<&'static str>, Components>) -> Option<EventResult>
+    {
+        context.state.push(self.name);
+        return None;
+    }
+
+    fn init(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+
+    fn remove(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+
+    fn duplicate(&self) -> Option<Self>
+    {
+        return Some(self.clone());
+    }
+}
+
+struct Unique;
+
+impl Object<Vec<&'static str>, Components> for Unique
+{
+    type EventType = ();
+
+    fn event(&mut self, _: &(), _: EventContext<Vec<&'static str>, Components>) -> Option<EventResult>
+    {
+        return None;
+    }
+
+    fn init(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+
+    fn remove(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+}
+
+fn scene() -> Scene<Vec<&'static str>, Components>
+{
+    let mut components = Components::new();
+    components.dynamic.register(ComponentInfo::of_clone::<Script>("script")).unwrap();
+    components.dynamic.register(ComponentInfo::of::<Handle>("handle")).unwrap();
+    return Scene::new(components);
+}
+
+fn spawn_monster(scene: &mut Scene<Vec<&'static str>, Components>) -> ObjectRef
+{
+    let obj = scene.spawn().with(Health(10)).object(Monster { name: "orc" }).id();
+    let dynamic = &mut scene.components_mut().dynamic;
+    dynamic.pool_by_name_mut("script").unwrap().insert(obj, Script(String::from("patrol"))).unwrap();
+    dynamic.pool_by_name_mut("handle").unwrap().insert(obj, Handle).unwrap();
+    return obj;
+}
+
+#[test]
+fn capture_copies_components_and_object()
+{
+    let mut scene = scene();
+    let obj = spawn_monster(&mut scene);
+    let prefab = scene.capture(obj).unwrap();
+    scene.components_mut().health.get_mut(obj).unwrap().0 = 1;
+    let copy = scene.instantiate(&prefab).id();
+    assert_eq!(scene.components().health.get(copy), Some(&Health(10)));
+    assert!(scene.get_object(copy).is_some());
+    let mut seen = Vec::new();
+    scene.send_event(copy, ()).unwrap();
+    scene.process_events(&mut seen);
+    assert_eq!(seen, vec!["orc"]);
+}
+
+#[test]
+fn capture_copies_clonable_dynamic_components()
+{
+    let mut scene = scene();
+    let obj = spawn_monster(&mut scene);
+    let prefab = scene.capture(obj).unwrap();
+    assert_eq!(prefab.len(), 2);
+    let copy = scene.instantiate(&prefab).id();
+    let dynamic = &scene.components().dynamic;
+    assert_eq!(dynamic.pool_by_name("script").unwrap().get::<Script>(copy), Some(&Script(String::from("patrol"))));
+    assert!(!dynamic.pool_by_name("handle").unwrap().contains(copy));
+    assert_eq!(dynamic.pool_by_name("script").unwrap().len(), 2);
+}
+
+#[test]
+fn clone_object_spawns_a_copy()
+{
+    let mut scene = scene();
+    let obj = spawn_monster(&mut scene);
+    let child = scene.spawn_object(Unique);
+    scene.set_parent(child, obj).unwrap();
+    let copy = scene.clone_object(obj).unwrap();
+    assert_ne!(copy, obj);
+    assert_eq!(scene.parent(copy), None);
+    assert_eq!(scene.children(copy).count(), 0);
+    assert_eq!(scene.components().health.get(copy), Some(&Health(10)));
+    assert!(scene.get_object(copy).is_some());
+    scene.remove_object(obj).unwrap();
+    assert!(scene.contains_object(copy));
+    assert_eq!(scene.components().dynamic.pool_by_name("script").unwrap().owners(), &[copy]);
+}
+
+#[test]
+fn objects_without_duplicate_are_not_captured()
+{
+    let mut scene = scene();
+    let obj = scene.spawn().with(Health(3)).object(Unique).id();
+    let copy = scene.clone_object(obj).unwrap();
+    assert!(scene.get_object(copy).is_none());
+    assert_eq!(scene.components().health.get(copy), Some(&Health(3)));
+    scene.remove_object(obj).unwrap();
+    assert!(scene.clone_object(obj).is_err());
+}
+
+#[test]
+fn prefab_factory_creates_objects()
+{
+    let mut scene = scene();
+    let prefab = Prefab::new().with(Health(5)).object(|| Monster { name: "goblin" });
+    let a = scene.instantiate(&prefab).id();
+    let b = scene.instantiate(&prefab).with(Health(6)).id();
+    assert!(scene.get_object(a).is_some());
+    assert_eq!(scene.components().health.get(a), Some(&Health(5)));
+    assert_eq!(scene.components().health.get(b), Some(&Health(6)));
+}
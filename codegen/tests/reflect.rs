@@ -0,0 +1,174 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentPool;
+use regecs::component::HashIndex;
+use regecs::error::Error;
+use regecs::object::ObjectRef;
+use regecs::reflect::FieldInfo;
+use regecs::reflect::Reflect;
+use regecs::reflect::ReflectPools;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+use regecs_codegen::Reflect;
+
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub struct Vector(f32, f32);
+
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+pub struct Transform
+{
+    position: Vector,
+    target: Option<ObjectRef>,
+    path: Vec<Vector>,
+    #[reflect(skip)]
+    cache: u32
+}
+
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+pub struct Name(String);
+
+#[derive(ComponentManager)]
+#[reflect]
+pub struct Components
+{
+    transforms: ComponentPool<Transform>,
+    names: ComponentPool<Name>
+}
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+fn transform() -> Transform
+{
+    return Transform
+    {
+        position: Vector(1.0, 2.0),
+        target: None,
+        path: vec![Vector(3.0, 4.0)],
+        cache: 0
+    };
+}
+
+#[test]
+fn fields_are_listed_in_declaration_order()
+{
+    let t = transform();
+    let value: &dyn Reflect = &t;
+    let names: Vec<_> = value.fields().iter().map(|f| f.name).collect();
+    assert_eq!(names, vec!["position", "target", "path"]);
+    assert_eq!(value.path("position").unwrap().fields()[1], FieldInfo { name: "1", type_name: "f32" });
+    assert!(value.field("cache").is_none());
+}
+
+#[test]
+fn paths_address_nested_fields()
+{
+    let mut t = transform();
+    let value: &mut dyn Reflect = &mut t;
+    assert_eq!(value.get::<f32>("position.1").unwrap(), &2.0);
+    assert_eq!(value.get::<f32>("path.0.0").unwrap(), &3.0);
+    assert_eq!(value.set("position.0", 5.0f32).unwrap(), 1.0);
+    *value.get_mut::<f32>("path.0.1").unwrap() = 6.0;
+    assert!(matches!(value.path("path.1"), Err(Error::UnknownField(path)) if path == "path.1"));
+    assert!(matches!(value.path("position.x"), Err(Error::UnknownField(_))));
+    assert!(matches!(value.get::<u32>("position.0"), Err(Error::FieldTypeMismatch(_, _))));
+    assert_eq!(t.position, Vector(5.0, 2.0));
+    assert_eq!(t.path, vec![Vector(3.0, 6.0)]);
+}
+
+#[test]
+fn leaves_round_trip_through_text()
+{
+    let mut t = transform();
+    let value: &mut dyn Reflect = &mut t;
+    value.set_text("position.0", "-1.5").unwrap();
+    assert_eq!(value.text("position.0").unwrap(), "-1.5");
+    assert!(matches!(value.set_text("position.0", "abc"), Err(Error::InvalidFieldValue(_))));
+    assert!(matches!(value.text("position"), Err(Error::InvalidFieldValue(_))));
+    assert_eq!(value.text("position.0").unwrap(), "-1.5");
+    let mut name = Name(String::from("orc"));
+    let value: &mut dyn Reflect = &mut name;
+    value.set_text("0", "goblin").unwrap();
+    assert_eq!(value.text("0").unwrap(), "goblin");
+}
+
+#[test]
+fn object_refs_round_trip_through_text()
+{
+    let mut t = transform();
+    let value: &mut dyn Reflect = &mut t;
+    assert_eq!(value.text("target").unwrap(), "none");
+    value.set_text("target", "12v3").unwrap();
+    assert_eq!(value.get::<Option<ObjectRef>>("target").unwrap(), &Some(ObjectRef::new(12, 3)));
+    assert_eq!(value.text("target").unwrap(), "12v3");
+    assert_eq!(value.text("target").unwrap(), ObjectRef::new(12, 3).to_string());
+    for invalid in ["12", "v3", "12v", "-1v3", "12v3v4"]
+    {
+        assert!(value.set_text("target", invalid).is_err(), "{} accepted", invalid);
+    }
+    value.set_text("target", "none").unwrap();
+    assert_eq!(t.target, None);
+}
+
+#[test]
+fn pools_are_reflected_by_name()
+{
+    let mut components = Components::new();
+    components.transforms.add(obj(0), transform());
+    components.names.add(obj(0), Name(String::from("orc")));
+    components.names.add(obj(1), Name(String::from("goblin")));
+    let pools: Vec<_> = components.reflected_pools().iter().map(|p| p.name).collect();
+    assert_eq!(pools, vec!["transforms", "names"]);
+    assert_eq!(components.owners("names"), Some(vec![obj(0), obj(1)]));
+    assert_eq!(components.owners("unknown"), None);
+    let all: Vec<_> = components.reflect_all(obj(0)).iter().map(|(name, _)| *name).collect();
+    assert_eq!(all, vec!["transforms", "names"]);
+    components.set_text("transforms", obj(0), "position.1", "8").unwrap().unwrap();
+    assert_eq!(components.transforms.get(obj(0)).unwrap().position, Vector(1.0, 8.0));
+    assert!(components.set_text("transforms", obj(1), "position.1", "8").is_none());
+    assert!(matches!(components.set_text("names", obj(1), "1", "x"), Some(Err(Error::UnknownField(_)))));
+}
+
+#[test]
+fn reflected_edits_update_indexes()
+{
+    let mut components = Components::new();
+    components.names.add_index(HashIndex::new("name", |n: &Name| n.0.clone()).unique()).unwrap();
+    components.names.add(obj(0), Name(String::from("orc")));
+    components.names.add(obj(1), Name(String::from("goblin")));
+    components.set_text("names", obj(0), "0", "troll").unwrap().unwrap();
+    assert_eq!(components.names.lookup("name", &String::from("troll")).unwrap(), &[obj(0)]);
+    assert!(components.names.lookup("name", &String::from("orc")).unwrap().is_empty());
+    let res = components.set_text("names", obj(0), "0", "goblin").unwrap();
+    assert!(matches!(res, Err(Error::UniqueViolation(name, other)) if name == "name" && other == obj(1)));
+}
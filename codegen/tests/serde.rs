@@ -0,0 +1,161 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentInfo;
+use regecs::component::ComponentPool;
+use regecs::component::DynamicComponents;
+use regecs::component::Tick;
+use regecs::error::Error;
+use regecs::object::ObjectRef;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+use serde::Serialize;
+use serde::Deserialize;
+
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Health(u32);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Script(String);
+
+struct Handle(u32);
+
+#[derive(ComponentManager)]
+#[serialize]
+pub struct Components
+{
+    health: ComponentPool<Health>,
+    #[dynamic]
+    dynamic: DynamicComponents
+}
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+fn register(dynamic: &mut DynamicComponents) -> Result<(), Error>
+{
+    dynamic.register(ComponentInfo::of::<Script>("script").serializable::<Script>())?;
+    dynamic.register(ComponentInfo::of::<Handle>("handle"))?;
+    return Ok(());
+}
+
+#[test]
+fn manager_round_trips_dynamic_components()
+{
+    let mut components = Components::new();
+    register(&mut components.dynamic).unwrap();
+    components.health.add(obj(0), Health(10));
+    components.dynamic.pool_by_name_mut("script").unwrap().insert(obj(0), Script(String::from("patrol"))).unwrap();
+    components.dynamic.pool_by_name_mut("script").unwrap().insert(obj(2), Script(String::from("idle"))).unwrap();
+    components.dynamic.pool_by_name_mut("handle").unwrap().insert(obj(0), Handle(7)).unwrap();
+    assert_eq!(components.dynamic.pool_by_name("handle").unwrap().get::<Handle>(obj(0)).unwrap().0, 7);
+    let data = bincode::serialize(&components).unwrap();
+    let mut loaded: Components = bincode::deserialize(&data).unwrap();
+    assert_eq!(loaded.health.get(obj(0)), Some(&Health(10)));
+    assert_eq!(loaded.dynamic.unclaimed().collect::<Vec<_>>(), vec!["script"]);
+    // Components waiting for their type survive another round trip
+    let data = bincode::serialize(&loaded).unwrap();
+    loaded = bincode::deserialize(&data).unwrap();
+    register(&mut loaded.dynamic).unwrap();
+    assert_eq!(loaded.dynamic.unclaimed().count(), 0);
+    let scripts = loaded.dynamic.pool_by_name("script").unwrap();
+    assert_eq!(scripts.get::<Script>(obj(0)), Some(&Script(String::from("patrol"))));
+    assert_eq!(scripts.get::<Script>(obj(2)), Some(&Script(String::from("idle"))));
+    assert!(loaded.dynamic.pool_by_name("handle").unwrap().is_empty());
+}
+
+#[test]
+fn waiting_components_require_a_codec()
+{
+    let mut components = Components::new();
+    register(&mut components.dynamic).unwrap();
+    components.dynamic.pool_by_name_mut("script").unwrap().insert(obj(0), Script(String::from("patrol"))).unwrap();
+    let data = bincode::serialize(&components).unwrap();
+    let mut loaded: Components = bincode::deserialize(&data).unwrap();
+    let res = loaded.dynamic.register(ComponentInfo::of::<Script>("script"));
+    assert!(matches!(res, Err(Error::InvalidComponentData(name, _)) if name == "script"));
+    assert!(loaded.dynamic.pool_by_name("script").is_none());
+    let res = loaded.dynamic.register(ComponentInfo::of::<bool>("script").serializable::<bool>());
+    assert!(matches!(res, Err(Error::InvalidComponentData(_, _))));
+    assert_eq!(loaded.dynamic.unclaimed().collect::<Vec<_>>(), vec!["script"]);
+}
+
+#[derive(Serialize, Deserialize)]
+struct Slot
+{
+    generation: u32,
+    owner: Option<ObjectRef>,
+    added: Tick,
+    changed: Tick,
+    comp: Option<u32>
+}
+
+#[derive(Serialize, Deserialize)]
+struct PoolData
+{
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    base_generation: u32,
+    tick: Tick
+}
+
+fn tampered(free: Vec<u32>) -> Result<ComponentPool<u32>, bincode::Error>
+{
+    let mut pool = ComponentPool::new();
+    pool.add(obj(0), 1);
+    let b = pool.add(obj(1), 2);
+    pool.add(obj(2), 3);
+    pool.remove(b);
+    let mut data: PoolData = bincode::deserialize(&bincode::serialize(&pool).unwrap()).unwrap();
+    assert_eq!(data.free, vec![1]);
+    data.free = free;
+    return bincode::deserialize(&bincode::serialize(&data).unwrap());
+}
+
+fn rejected(free: Vec<u32>) -> String
+{
+    return match tampered(free)
+    {
+        Ok(_) => panic!("corrupted free list accepted"),
+        Err(e) => e.to_string()
+    };
+}
+
+#[test]
+fn pool_rejects_corrupted_free_list()
+{
+    let pool = tampered(vec![1]).unwrap();
+    assert_eq!(pool.get(obj(2)), Some(&3));
+    assert!(rejected(vec![1, 1]).contains("listed twice"));
+    assert!(rejected(vec![3]).contains("out of range"));
+    assert!(rejected(vec![0]).contains("is live"));
+}
@@ -0,0 +1,428 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use std::convert::TryInto;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use regecs::component::ComponentInfo;
+use regecs::component::ComponentPool;
+use regecs::component::DynamicComponents;
+use regecs::component::HashIndex;
+use regecs::component::Tick;
+use regecs::event::EventContext;
+use regecs::event::EventResult;
+use regecs::object::Object;
+use regecs::object::ObjectRef;
+use regecs::scene::Scene;
+use regecs::snapshot::Snapshot;
+use regecs::snapshot::SnapshotReader;
+use regecs::snapshot::SectionKind;
+use regecs::snapshot::FORMAT_VERSION;
+use regecs::snapshot::type_id_of;
+use regecs_codegen::Component;
+use regecs_codegen::ComponentManager;
+use serde::Serialize;
+use serde::Deserialize;
+
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[component(name = "health")]
+pub struct Health(u32);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Script(String);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Ping(u32);
+
+#[derive(ComponentManager)]
+#[serialize]
+pub struct Components
+{
+    health: ComponentPool<Health>,
+    #[dynamic]
+    dynamic: DynamicComponents
+}
+
+mod renamed
+{
+    use regecs::component::ComponentPool;
+    use regecs_codegen::Component;
+    use regecs_codegen::ComponentManager;
+    use serde::Serialize;
+    use serde::Deserialize;
+
+    #[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+    #[component(name = "health")]
+    pub struct HitPoints(pub u32);
+
+    #[derive(ComponentManager)]
+    #[serialize]
+    pub struct Components
+    {
+        pub health: ComponentPool<HitPoints>
+    }
+}
+
+struct Monster
+{
+    removed: Arc<AtomicUsize>
+}
+
+impl Object<(), Components> for Monster
+{
+    type EventType = Ping;
+
+    fn event(&mut self, _: &Ping, _: EventContext<(), Components>) -> Option<EventResult>
+    {
+        return None;
+    }
+
+    fn init(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+
+    fn remove(&mut self, ptr: ObjectRef, components: &mut Components)
+    {
+        // Components are still attached when the object is notified
+        assert!(components.health.get(ptr).is_some());
+        self.removed.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry
+{
+    generation: u32,
+    alive: bool,
+    parent: Option<ObjectRef>,
+    children: Vec<ObjectRef>
+}
+
+#[derive(Serialize, Deserialize)]
+struct Table
+{
+    tick: Tick,
+    update_start: Tick,
+    slots: Vec<Entry>
+}
+
+fn components() -> Components
+{
+    let mut components = Components::new();
+    components.dynamic.register(ComponentInfo::of::<Script>("script").serializable::<Script>()).unwrap();
+    components.dynamic.register(ComponentInfo::of::<u64>("handle")).unwrap();
+    return components;
+}
+
+fn snapshot() -> Snapshot
+{
+    return Snapshot::new().event::<Ping>("ping");
+}
+
+/// Returns a scene with a parent, a child and a removed object, and a snapshot of it
+fn saved_scene(removed: &Arc<AtomicUsize>) -> (Scene<(), Components>, Vec<u8>)
+{
+    let mut scene = Scene::new(components());
+    let a = scene.spawn().with(Health(10)).object(Monster { removed: removed.clone() }).id();
+    let b = scene.spawn().with(Health(20)).id();
+    let c = scene.spawn().with(Health(30)).id();
+    scene.set_parent(b, a).unwrap();
+    scene.remove_object(c).unwrap();
+    scene.components_mut().dynamic.pool_by_name_mut("script").unwrap().insert(a, Script(String::from("patrol"))).unwrap();
+    scene.components_mut().dynamic.pool_by_name_mut("handle").unwrap().insert(a, 7u64).unwrap();
+    scene.send_event(a, Ping(1)).unwrap();
+    let data = snapshot().write(&scene).unwrap();
+    return (scene, data);
+}
+
+/// Replaces the payload of the named section and recomputes all checksums
+fn patch_section<F: FnOnce(&[u8]) -> Vec<u8>>(data: &[u8], name: &str, f: F) -> Vec<u8>
+{
+    let reader = SnapshotReader::new(data).unwrap();
+    let mut f = Some(f);
+    let mut out = Vec::new();
+    out.extend_from_slice(&data[..6]);
+    out.extend_from_slice(&(reader.sections().len() as u32).to_le_bytes());
+    let mut payloads = Vec::new();
+    for section in reader.sections()
+    {
+        let mut payload = reader.payload(section).unwrap().to_vec();
+        if section.name == name
+        {
+            payload = (f.take().unwrap())(&payload);
+        }
+        out.push(match section.kind
+        {
+            SectionKind::Objects => 0,
+            SectionKind::Events => 1,
+            SectionKind::Pool => 2,
+            SectionKind::Dynamic => 3
+        });
+        for s in [&section.name, &section.type_name]
+        {
+            out.extend_from_slice(&(s.len() as u16).to_le_bytes());
+            out.extend_from_slice(s.as_bytes());
+        }
+        out.extend_from_slice(&section.type_id.to_le_bytes());
+        out.extend_from_slice(&section.component_size.to_le_bytes());
+        out.extend_from_slice(&section.schema_version.to_le_bytes());
+        out.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        out.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        payloads.push(payload);
+    }
+    for payload in payloads
+    {
+        out.extend_from_slice(&payload);
+    }
+    let checksum = crc32fast::hash(&out);
+    out.extend_from_slice(&checksum.to_le_bytes());
+    return out;
+}
+
+fn patch_table<F: FnOnce(&mut Table)>(data: &[u8], f: F) -> Vec<u8>
+{
+    return patch_section(data, "objects", |payload|
+    {
+        let mut table: Table = bincode::deserialize(payload).unwrap();
+        f(&mut table);
+        return bincode::serialize(&table).unwrap();
+    });
+}
+
+fn error_of(data: &[u8], scene: &mut Scene<(), Components>) -> String
+{
+    return match snapshot().read(data, scene)
+    {
+        Ok(()) => panic!("invalid snapshot accepted"),
+        Err(e) => e.to_string()
+    };
+}
+
+#[test]
+fn header_describes_sections()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (_, data) = saved_scene(&removed);
+    assert_eq!(&data[..4], b"RGSN");
+    assert_eq!(u16::from_le_bytes(data[4..6].try_into().unwrap()), FORMAT_VERSION);
+    let reader = SnapshotReader::new(&data).unwrap();
+    assert_eq!(reader.version(), FORMAT_VERSION);
+    let sections: Vec<_> = reader.sections().iter().map(|s| (s.kind, &*s.name)).collect();
+    assert_eq!(sections, vec![
+        (SectionKind::Objects, "objects"),
+        (SectionKind::Events, "events"),
+        (SectionKind::Dynamic, "dynamic"),
+        (SectionKind::Pool, "health")
+    ]);
+    let pool = &reader.sections()[3];
+    assert_eq!(pool.type_name, "health");
+    assert_eq!(pool.type_id, type_id_of("health"));
+    assert_eq!(pool.component_size as usize, std::mem::size_of::<Health>());
+    assert_eq!(pool.schema_version, 1);
+}
+
+#[test]
+fn snapshot_round_trips_scene()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (scene, data) = saved_scene(&removed);
+    let mut other: Scene<(), Components> = Scene::new(components());
+    snapshot().read(&data, &mut other).unwrap();
+    assert_eq!(other.object_count(), 2);
+    assert_eq!(other.objects().collect::<Vec<_>>(), scene.objects().collect::<Vec<_>>());
+    let a = ObjectRef::new(0, 0);
+    let b = ObjectRef::new(1, 0);
+    assert_eq!(other.parent(b), Some(a));
+    assert_eq!(other.components().health.get(b), Some(&Health(20)));
+    assert_eq!(other.pending_events(), 1);
+    let dynamic = &other.components().dynamic;
+    assert_eq!(dynamic.pool_by_name("script").unwrap().get::<Script>(a), Some(&Script(String::from("patrol"))));
+    // Types without a codec are not saved
+    assert!(dynamic.pool_by_name("handle").unwrap().is_empty());
+}
+
+#[test]
+fn dynamic_components_are_restored()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (mut scene, data) = saved_scene(&removed);
+    let a = ObjectRef::new(0, 0);
+    let b = ObjectRef::new(1, 0);
+    let scripts = scene.components_mut().dynamic.pool_by_name_mut("script").unwrap();
+    scripts.remove(a);
+    scripts.insert(b, Script(String::from("idle"))).unwrap();
+    snapshot().read(&data, &mut scene).unwrap();
+    let dynamic = &scene.components().dynamic;
+    assert_eq!(dynamic.pool_by_name("script").unwrap().owners(), &[a]);
+    // Pools of types without a codec are left untouched
+    assert_eq!(dynamic.pool_by_name("handle").unwrap().get::<u64>(a), Some(&7));
+}
+
+#[test]
+fn replaced_objects_are_notified()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (mut scene, data) = saved_scene(&removed);
+    snapshot().read(&data, &mut scene).unwrap();
+    assert_eq!(removed.load(Ordering::SeqCst), 1);
+    assert!(scene.get_object(ObjectRef::new(0, 0)).is_none());
+    // The object table is skipped, objects are kept
+    let mut scene = saved_scene(&removed).0;
+    snapshot().read_with(&data, &mut scene, |s| s.kind != SectionKind::Objects).unwrap();
+    assert_eq!(removed.load(Ordering::SeqCst), 1);
+    assert!(scene.get_object(ObjectRef::new(0, 0)).is_some());
+}
+
+#[test]
+fn corrupted_snapshots_are_rejected()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (mut scene, data) = saved_scene(&removed);
+    let mut flipped = data.clone();
+    flipped[data.len() / 2] ^= 0xFF;
+    assert!(error_of(&flipped, &mut scene).contains("checksum mismatch"));
+    assert!(error_of(&data[..data.len() - 1], &mut scene).contains("checksum mismatch"));
+    assert!(error_of(&data[..8], &mut scene).contains("truncated snapshot"));
+    let mut bumped = data[..data.len() - 4].to_vec();
+    bumped[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    let checksum = crc32fast::hash(&bumped);
+    bumped.extend_from_slice(&checksum.to_le_bytes());
+    assert!(error_of(&bumped, &mut scene).contains("unsupported format version"));
+    assert_eq!(removed.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn invalid_hierarchies_are_rejected()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (mut scene, data) = saved_scene(&removed);
+    let stale = patch_table(&data, |t| t.slots[0].children[0] = ObjectRef::new(1, 1));
+    assert!(error_of(&stale, &mut scene).contains("invalid child"));
+    let out_of_range = patch_table(&data, |t|
+    {
+        t.slots[0].children.clear();
+        t.slots[1].parent = Some(ObjectRef::new(9, 0));
+    });
+    assert!(error_of(&out_of_range, &mut scene).contains("invalid parent"));
+    let missing = patch_table(&data, |t| t.slots[0].children.push(ObjectRef::new(9, 0)));
+    assert!(error_of(&missing, &mut scene).contains("invalid child"));
+    let dead = patch_table(&data, |t| t.slots[2].children.push(ObjectRef::new(1, 0)));
+    assert!(error_of(&dead, &mut scene).contains("removed object"));
+    let duplicate = patch_table(&data, |t| t.slots[0].children.push(ObjectRef::new(1, 0)));
+    assert!(error_of(&duplicate, &mut scene).contains("duplicate children"));
+    let cycle = patch_table(&data, |t|
+    {
+        t.slots[0].parent = Some(ObjectRef::new(1, 0));
+        t.slots[1].children.push(ObjectRef::new(0, 0));
+    });
+    assert!(error_of(&cycle, &mut scene).contains("cyclic hierarchy"));
+    let valid = patch_table(&data, |_| ());
+    snapshot().read(&valid, &mut scene).unwrap();
+}
+
+#[test]
+fn failed_reads_leave_the_scene_unchanged()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (mut scene, data) = saved_scene(&removed);
+    let a = ObjectRef::new(0, 0);
+    scene.components_mut().health.get_mut(a).unwrap().0 = 1;
+    scene.components_mut().dynamic.pool_by_name_mut("script").unwrap().remove(a);
+    scene.process_events(&mut ());
+    let corrupt = patch_section(&data, "health", |payload| payload[..payload.len() / 2].to_vec());
+    assert!(snapshot().read(&corrupt, &mut scene).is_err());
+    assert_eq!(removed.load(Ordering::SeqCst), 0);
+    assert!(scene.get_object(a).is_some());
+    assert_eq!(scene.object_count(), 2);
+    assert_eq!(scene.pending_events(), 0);
+    assert_eq!(scene.components().health.get(a), Some(&Health(1)));
+    assert!(scene.components().dynamic.pool_by_name("script").unwrap().is_empty());
+}
+
+#[test]
+fn pools_are_keyed_on_stable_names()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (_, data) = saved_scene(&removed);
+    let mut scene: Scene<(), renamed::Components> = Scene::new(renamed::Components::new());
+    Snapshot::new().event::<Ping>("ping").read(&data, &mut scene).unwrap();
+    assert_eq!(scene.components().health.get(ObjectRef::new(1, 0)), Some(&renamed::HitPoints(20)));
+    // Events are keyed on their registered name as well
+    let res = Snapshot::new().event::<Ping>("pong").read(&data, &mut scene);
+    assert!(res.is_err());
+}
+
+#[test]
+fn restored_pools_keep_their_indexes()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (mut scene, data) = saved_scene(&removed);
+    let health = &mut scene.components_mut().health;
+    health.add_index(HashIndex::new("value", |h: &Health| h.0).unique()).unwrap();
+    health.get_mut(ObjectRef::new(0, 0)).unwrap().0 = 15;
+    assert!(health.lookup("value", &10u32).is_err());
+    snapshot().read(&data, &mut scene).unwrap();
+    let health = &scene.components().health;
+    assert_eq!(health.lookup("value", &10u32).unwrap(), &[ObjectRef::new(0, 0)]);
+    assert_eq!(health.lookup("value", &20u32).unwrap(), &[ObjectRef::new(1, 0)]);
+    assert!(health.lookup("value", &15u32).unwrap().is_empty());
+}
+
+#[test]
+fn components_of_objects_missing_from_the_table_are_cleared()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (mut scene, data) = saved_scene(&removed);
+    let d = scene.spawn().with(Health(40)).id();
+    scene.components_mut().dynamic.pool_by_name_mut("script").unwrap().insert(d, Script(String::from("guard"))).unwrap();
+    // Only the object table is restored, d did not exist when the snapshot was taken
+    snapshot().read_with(&data, &mut scene, |s| s.kind == SectionKind::Objects).unwrap();
+    assert!(!scene.contains_object(d));
+    assert_eq!(scene.components().health.get(d), None);
+    assert!(!scene.components().dynamic.pool_by_name("script").unwrap().contains(d));
+    assert_eq!(scene.components().health.get(ObjectRef::new(1, 0)), Some(&Health(20)));
+}
+
+#[test]
+fn components_of_objects_missing_from_the_scene_are_cleared()
+{
+    let removed = Arc::new(AtomicUsize::new(0));
+    let (_, data) = saved_scene(&removed);
+    let mut scene: Scene<(), Components> = Scene::new(components());
+    let a = scene.spawn().with(Health(1)).id();
+    // Only the pools are restored, b does not exist in this scene
+    snapshot().read_with(&data, &mut scene, |s| s.kind != SectionKind::Objects).unwrap();
+    assert_eq!(scene.components().health.get(a), Some(&Health(10)));
+    assert_eq!(scene.components().health.get(ObjectRef::new(1, 0)), None);
+    assert_eq!(scene.components().health.len(), 1);
+    assert_eq!(scene.components().dynamic.pool_by_name("script").unwrap().owners(), &[a]);
+}
@@ -0,0 +1,697 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! REGECS archetype storage
+//!
+//! An alternative to per-type component pools: objects with the same set of components share a table
+//! with one column per component type, so joins iterate packed columns side by side. Rows are moved
+//! between tables when components are added or removed.
+//!
+//! Like a derived component manager, the archetype manager stamps additions and mutable accesses with the
+//! current tick, remembers removals until [clear_removed](crate::component::ComponentManager::clear_removed)
+//! and runs [lifecycle hooks](crate::component::Hooks) registered per component type.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::component::ComponentManager;
+use crate::component::Hooks;
+use crate::component::Lifecycle;
+use crate::component::Tick;
+
+trait Column
+{
+    fn empty(&self) -> Box<dyn Column>;
+    fn swap_remove(&mut self, row: usize);
+    fn move_row(&mut self, row: usize, dst: &mut dyn Column);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl <T: 'static> Column for Vec<T>
+{
+    fn empty(&self) -> Box<dyn Column>
+    {
+        return Box::new(Vec::<T>::new());
+    }
+
+    fn swap_remove(&mut self, row: usize)
+    {
+        Vec::swap_remove(self, row);
+    }
+
+    fn move_row(&mut self, row: usize, dst: &mut dyn Column)
+    {
+        let v = Vec::swap_remove(self, row);
+        dst.as_any_mut().downcast_mut::<Vec<T>>().unwrap().push(v);
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        return self;
+    }
+}
+
+/// A table of objects sharing the same set of component types
+pub struct Archetype
+{
+    types: Vec<TypeId>,
+    columns: Vec<Box<dyn Column>>,
+    ticks: Vec<Vec<(Tick, Tick)>>,
+    owners: Vec<ObjectRef>
+}
+
+impl Archetype
+{
+    fn column_index(&self, t: TypeId) -> Option<usize>
+    {
+        return self.types.binary_search(&t).ok();
+    }
+
+    fn column<T: 'static>(&self) -> Option<&Vec<T>>
+    {
+        let index = self.column_index(TypeId::of::<T>())?;
+        return self.columns[index].as_any().downcast_ref::<Vec<T>>();
+    }
+
+    fn column_mut<T: 'static>(&mut self) -> Option<&mut Vec<T>>
+    {
+        let index = self.column_index(TypeId::of::<T>())?;
+        return self.columns[index].as_any_mut().downcast_mut::<Vec<T>>();
+    }
+
+    /// Returns the (added, changed) ticks of the given column
+    fn ticks_mut<T: 'static>(&mut self) -> Option<&mut Vec<(Tick, Tick)>>
+    {
+        let index = self.column_index(TypeId::of::<T>())?;
+        return Some(&mut self.ticks[index]);
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Location
+{
+    owner: ObjectRef,
+    archetype: usize,
+    row: usize
+}
+
+fn pair_mut<T>(v: &mut [T], a: usize, b: usize) -> (&mut T, &mut T)
+{
+    if a < b
+    {
+        let (l, r) = v.split_at_mut(b);
+        return (&mut l[a], &mut r[0]);
+    }
+    let (l, r) = v.split_at_mut(a);
+    return (&mut r[0], &mut l[b]);
+}
+
+/// Archetype based component manager
+///
+/// *can be used in place of a derived component manager to compare both storage modes*
+pub struct ArchetypeManager
+{
+    archetypes: Vec<Archetype>,
+    index: HashMap<Vec<TypeId>, usize>,
+    locations: Vec<Option<Location>>,
+    tick: Tick,
+    removed: Vec<(TypeId, ObjectRef, Tick)>,
+    hooks: HashMap<TypeId, Hooks<ArchetypeManager>>,
+    lifecycle: VecDeque<(TypeId, Lifecycle)>
+}
+
+impl Default for ArchetypeManager
+{
+    fn default() -> Self
+    {
+        return ArchetypeManager::new();
+    }
+}
+
+impl ArchetypeManager
+{
+    pub fn new() -> ArchetypeManager
+    {
+        let mut index = HashMap::new();
+        index.insert(Vec::new(), 0);
+        return ArchetypeManager
+        {
+            archetypes: vec![Archetype
+            {
+                types: Vec::new(),
+                columns: Vec::new(),
+                ticks: Vec::new(),
+                owners: Vec::new()
+            }],
+            index,
+            locations: Vec::new(),
+            tick: 0,
+            removed: Vec::new(),
+            hooks: HashMap::new(),
+            lifecycle: VecDeque::new()
+        };
+    }
+
+    /// Registers lifecycle hooks for the given type of component, replacing any previously registered hooks
+    pub fn set_hooks<T: 'static>(&mut self, hooks: Hooks<ArchetypeManager>)
+    {
+        self.hooks.insert(TypeId::of::<T>(), hooks);
+    }
+
+    /// Unregisters lifecycle hooks for the given type of component, pending notifications are discarded
+    pub fn clear_hooks<T: 'static>(&mut self)
+    {
+        self.hooks.remove(&TypeId::of::<T>());
+        self.lifecycle.retain(|(t, _)| *t != TypeId::of::<T>());
+    }
+
+    fn notify(&mut self, t: TypeId, ev: Lifecycle)
+    {
+        if self.hooks.contains_key(&t)
+        {
+            self.lifecycle.push_back((t, ev));
+        }
+    }
+
+    fn location(&self, owner: ObjectRef) -> Option<Location>
+    {
+        return (*self.locations.get(owner.index())?).filter(|l| l.owner == owner);
+    }
+
+    fn set_location(&mut self, owner: ObjectRef, archetype: usize, row: usize)
+    {
+        if self.locations.len() <= owner.index()
+        {
+            self.locations.resize(owner.index() + 1, None);
+        }
+        self.locations[owner.index()] = Some(Location
+        {
+            owner,
+            archetype,
+            row
+        });
+    }
+
+    /// Finds or creates the archetype obtained by adding (or removing) a type to an existing archetype
+    fn derive_archetype(&mut self, from: usize, t: TypeId, column: Option<Box<dyn Column>>) -> usize
+    {
+        let mut types = self.archetypes[from].types.clone();
+        match types.binary_search(&t)
+        {
+            Ok(i) => { types.remove(i); },
+            Err(i) => types.insert(i, t)
+        };
+        if let Some(index) = self.index.get(&types)
+        {
+            return *index;
+        }
+        let src = &self.archetypes[from];
+        let count = types.len();
+        let mut pairs: Vec<(TypeId, Box<dyn Column>)> = src.types.iter().copied()
+            .zip(src.columns.iter().map(|c| c.empty()))
+            .filter(|(id, _)| *id != t)
+            .collect();
+        if let Some(column) = column
+        {
+            pairs.push((t, column));
+        }
+        pairs.sort_by_key(|(id, _)| *id);
+        let index = self.archetypes.len();
+        self.archetypes.push(Archetype
+        {
+            types: types.clone(),
+            columns: pairs.into_iter().map(|(_, c)| c).collect(),
+            ticks: vec![Vec::new(); count],
+            owners: Vec::new()
+        });
+        self.index.insert(types, index);
+        return index;
+    }
+
+    /// Moves an object row to another archetype, dropping components the destination has no column for
+    ///
+    /// *the column of the skipped type must already have been removed by the caller*
+    fn move_row(&mut self, loc: Location, dst: usize, skip: Option<TypeId>)
+    {
+        let (src, dest) = pair_mut(&mut self.archetypes, loc.archetype, dst);
+        for (i, t) in src.types.iter().enumerate()
+        {
+            if Some(*t) == skip
+            {
+                continue;
+            }
+            let ticks = src.ticks[i].swap_remove(loc.row);
+            match dest.column_index(*t)
+            {
+                Some(j) =>
+                {
+                    src.columns[i].move_row(loc.row, &mut *dest.columns[j]);
+                    dest.ticks[j].push(ticks);
+                },
+                None => src.columns[i].swap_remove(loc.row)
+            }
+        }
+        self.remove_owner_row(loc);
+        let row = self.archetypes[dst].owners.len();
+        self.archetypes[dst].owners.push(loc.owner);
+        self.set_location(loc.owner, dst, row);
+    }
+
+    /// Removes the owner entry of a row whose columns were already swap-removed
+    fn remove_owner_row(&mut self, loc: Location)
+    {
+        let arch = &mut self.archetypes[loc.archetype];
+        arch.owners.swap_remove(loc.row);
+        if let Some(moved) = arch.owners.get(loc.row).copied()
+        {
+            self.set_location(moved, loc.archetype, loc.row);
+        }
+    }
+
+    /// Removes all components of the given object without running hooks, removals are still recorded
+    fn discard(&mut self, target: ObjectRef)
+    {
+        if let Some(loc) = self.location(target)
+        {
+            let arch = &mut self.archetypes[loc.archetype];
+            for (column, ticks) in arch.columns.iter_mut().zip(arch.ticks.iter_mut())
+            {
+                column.swap_remove(loc.row);
+                ticks.swap_remove(loc.row);
+            }
+            for t in &arch.types
+            {
+                self.removed.push((*t, target, self.tick));
+            }
+            self.remove_owner_row(loc);
+            self.locations[target.index()] = None;
+        }
+    }
+
+    /// Attaches a component to the given object, moving it to the matching archetype
+    ///
+    /// *returns the previous component if the object already owned one of the same type; components left
+    /// behind by a previous object at the same index are discarded*
+    pub fn insert<T: 'static>(&mut self, owner: ObjectRef, comp: T) -> Option<T>
+    {
+        let stale = self.locations.get(owner.index()).copied().flatten().map(|l| l.owner).filter(|o| *o != owner);
+        if let Some(stale) = stale
+        {
+            self.discard(stale);
+        }
+        let loc = match self.location(owner)
+        {
+            Some(loc) => loc,
+            None =>
+            {
+                let row = self.archetypes[0].owners.len();
+                self.archetypes[0].owners.push(owner);
+                self.set_location(owner, 0, row);
+                Location
+                {
+                    owner,
+                    archetype: 0,
+                    row
+                }
+            }
+        };
+        let tick = self.tick;
+        let arch = &mut self.archetypes[loc.archetype];
+        if let Some(column) = arch.column_mut::<T>()
+        {
+            let old = std::mem::replace(&mut column[loc.row], comp);
+            arch.ticks_mut::<T>().unwrap()[loc.row].1 = tick;
+            return Some(old);
+        }
+        let dst = self.derive_archetype(loc.archetype, TypeId::of::<T>(), Some(Box::new(Vec::<T>::new())));
+        self.move_row(loc, dst, None);
+        self.archetypes[dst].column_mut::<T>().unwrap().push(comp);
+        self.archetypes[dst].ticks_mut::<T>().unwrap().push((tick, tick));
+        self.notify(TypeId::of::<T>(), Lifecycle::Added(owner));
+        return None;
+    }
+
+    /// Removes a component from the given object, moving it to the matching archetype
+    ///
+    /// *an object whose last component is removed goes back to the empty archetype*
+    pub fn remove<T: 'static>(&mut self, owner: ObjectRef) -> Option<T>
+    {
+        let loc = self.location(owner)?;
+        let arch = &mut self.archetypes[loc.archetype];
+        let comp = arch.column_mut::<T>()?.swap_remove(loc.row);
+        arch.ticks_mut::<T>().unwrap().swap_remove(loc.row);
+        let dst = self.derive_archetype(loc.archetype, TypeId::of::<T>(), None);
+        self.move_row(loc, dst, Some(TypeId::of::<T>()));
+        self.removed.push((TypeId::of::<T>(), owner, self.tick));
+        self.notify(TypeId::of::<T>(), Lifecycle::Removed(owner));
+        return Some(comp);
+    }
+
+    /// Returns true if the given object owns a component of the given type
+    pub fn contains<T: 'static>(&self, owner: ObjectRef) -> bool
+    {
+        return self.get::<T>(owner).is_some();
+    }
+
+    pub fn get<T: 'static>(&self, owner: ObjectRef) -> Option<&T>
+    {
+        let loc = self.location(owner)?;
+        return self.archetypes[loc.archetype].column::<T>()?.get(loc.row);
+    }
+
+    /// Returns the component of the given type attached to the given object
+    ///
+    /// *the component is marked as changed*
+    pub fn get_mut<T: 'static>(&mut self, owner: ObjectRef) -> Option<&mut T>
+    {
+        let loc = self.location(owner)?;
+        let tick = self.tick;
+        let arch = &mut self.archetypes[loc.archetype];
+        arch.ticks_mut::<T>()?[loc.row].1 = tick;
+        return arch.column_mut::<T>()?.get_mut(loc.row);
+    }
+
+    /// Returns the current tick
+    pub fn tick(&self) -> Tick
+    {
+        return self.tick;
+    }
+
+    /// Returns the tick at which the component of the given type was added to the given object
+    pub fn added_tick<T: 'static>(&self, owner: ObjectRef) -> Option<Tick>
+    {
+        let loc = self.location(owner)?;
+        let arch = &self.archetypes[loc.archetype];
+        return Some(arch.ticks[arch.column_index(TypeId::of::<T>())?][loc.row].0);
+    }
+
+    /// Returns the tick at which the component of the given type attached to the given object was last
+    /// mutably accessed
+    pub fn changed_tick<T: 'static>(&self, owner: ObjectRef) -> Option<Tick>
+    {
+        let loc = self.location(owner)?;
+        let arch = &self.archetypes[loc.archetype];
+        return Some(arch.ticks[arch.column_index(TypeId::of::<T>())?][loc.row].1);
+    }
+
+    /// Iterates over all objects whose component of the given type was removed after the given tick
+    pub fn removed_since<T: 'static>(&self, tick: Tick) -> impl Iterator<Item = ObjectRef> + '_
+    {
+        return self.removed.iter()
+            .filter(move |(t, _, at)| *t == TypeId::of::<T>() && *at > tick)
+            .map(|(_, o, _)| *o);
+    }
+
+    /// Returns the number of tables currently allocated
+    pub fn archetype_count(&self) -> usize
+    {
+        return self.archetypes.len();
+    }
+
+    /// Returns the index of the table holding the given object, None if the object has never owned a component
+    ///
+    /// *the empty archetype has index 0*
+    pub fn archetype_of(&self, owner: ObjectRef) -> Option<usize>
+    {
+        return self.location(owner).map(|l| l.archetype);
+    }
+
+    /// Iterates over all components of the given type with their owning object
+    pub fn iter<T: 'static>(&self) -> impl Iterator<Item = (ObjectRef, &T)>
+    {
+        return self.archetypes.iter()
+            .filter_map(|a| Some(a.owners.iter().copied().zip(a.column::<T>()?.iter())))
+            .flatten();
+    }
+
+    /// Iterates over all objects owning the queried set of components, table by table
+    ///
+    /// *panics if a component type is requested mutably more than once*
+    pub fn query<'a, Q: ArchetypeFetch<'a>>(&'a mut self) -> ArchetypeQuery<'a, Q>
+    {
+        let mut types = Vec::new();
+        Q::collect_types(&mut types);
+        for (i, (t, mutable)) in types.iter().enumerate()
+        {
+            if types[i + 1..].iter().any(|(t1, m1)| t1 == t && (*mutable || *m1))
+            {
+                panic!("Component type requested mutably more than once in the same query");
+            }
+        }
+        let mut tables = Vec::new();
+        let tick = self.tick;
+        for arch in &mut self.archetypes
+        {
+            if arch.owners.is_empty() || !types.iter().all(|(t, _)| arch.column_index(*t).is_some())
+            {
+                continue;
+            }
+            // Each queried column is distinct so the pointers never alias
+            let state = unsafe { Q::prepare(arch, tick) };
+            tables.push((state, arch.owners.as_ptr(), arch.owners.len()));
+        }
+        return ArchetypeQuery
+        {
+            tables,
+            table: 0,
+            row: 0,
+            useless: PhantomData
+        };
+    }
+}
+
+impl ComponentManager for ArchetypeManager
+{
+    fn clear_components(&mut self, target: ObjectRef)
+    {
+        let types = match self.location(target)
+        {
+            Some(loc) => self.archetypes[loc.archetype].types.clone(),
+            None => return
+        };
+        for t in types
+        {
+            if let Some(hook) = self.hooks.get(&t).and_then(|h| h.on_remove)
+            {
+                hook(self, target);
+            }
+        }
+        self.discard(target);
+        self.run_hooks();
+    }
+
+    fn set_tick(&mut self, tick: Tick)
+    {
+        self.tick = tick;
+    }
+
+    fn clear_removed(&mut self, before: Tick)
+    {
+        self.removed.retain(|(_, _, t)| *t >= before);
+    }
+
+    fn run_hooks(&mut self)
+    {
+        while let Some((t, ev)) = self.lifecycle.pop_front()
+        {
+            let hooks = match self.hooks.get(&t)
+            {
+                Some(hooks) => *hooks,
+                None => continue
+            };
+            match ev
+            {
+                Lifecycle::Added(owner) =>
+                {
+                    if let Some(hook) = hooks.on_add
+                    {
+                        hook(self, owner);
+                    }
+                },
+                Lifecycle::Removed(owner) =>
+                {
+                    if let Some(hook) = hooks.on_remove
+                    {
+                        hook(self, owner);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A term of an archetype query
+pub trait ArchetypeFetch<'a>
+{
+    /// Item produced by this term for each matching object
+    type Item;
+
+    /// Per-table state, usually raw column pointers
+    type State: Copy;
+
+    /// Appends the component types required by this term and whether they are accessed mutably
+    fn collect_types(out: &mut Vec<(TypeId, bool)>);
+
+    /// Prepares the state for a table containing all required types, mutable accesses are stamped with
+    /// the given tick
+    ///
+    /// # Safety
+    ///
+    /// The table must contain all types required by this term.
+    unsafe fn prepare(arch: &mut Archetype, tick: Tick) -> Self::State;
+
+    /// Fetches the item at the given row
+    ///
+    /// # Safety
+    ///
+    /// The row must be in bounds and fetched at most once.
+    unsafe fn fetch(state: Self::State, row: usize) -> Self::Item;
+}
+
+impl <'a, T: 'static> ArchetypeFetch<'a> for &'a T
+{
+    type Item = &'a T;
+    type State = *const T;
+
+    fn collect_types(out: &mut Vec<(TypeId, bool)>)
+    {
+        out.push((TypeId::of::<T>(), false));
+    }
+
+    unsafe fn prepare(arch: &mut Archetype, _: Tick) -> Self::State
+    {
+        return arch.column::<T>().unwrap().as_ptr();
+    }
+
+    unsafe fn fetch(state: Self::State, row: usize) -> Self::Item
+    {
+        return &*state.add(row);
+    }
+}
+
+impl <'a, T: 'static> ArchetypeFetch<'a> for &'a mut T
+{
+    type Item = &'a mut T;
+    type State = (*mut T, *mut (Tick, Tick), Tick);
+
+    fn collect_types(out: &mut Vec<(TypeId, bool)>)
+    {
+        out.push((TypeId::of::<T>(), true));
+    }
+
+    unsafe fn prepare(arch: &mut Archetype, tick: Tick) -> Self::State
+    {
+        let ticks = arch.ticks_mut::<T>().unwrap().as_mut_ptr();
+        return (arch.column_mut::<T>().unwrap().as_mut_ptr(), ticks, tick);
+    }
+
+    unsafe fn fetch(state: Self::State, row: usize) -> Self::Item
+    {
+        let (column, ticks, tick) = state;
+        (*ticks.add(row)).1 = tick;
+        return &mut *column.add(row);
+    }
+}
+
+macro_rules! impl_archetype_fetch_tuple
+{
+    ($($name: ident $index: tt),*) =>
+    {
+        impl <'a, $($name: ArchetypeFetch<'a>),*> ArchetypeFetch<'a> for ($($name,)*)
+        {
+            type Item = ($($name::Item,)*);
+            type State = ($($name::State,)*);
+
+            fn collect_types(out: &mut Vec<(TypeId, bool)>)
+            {
+                $($name::collect_types(out);)*
+            }
+
+            unsafe fn prepare(arch: &mut Archetype, tick: Tick) -> Self::State
+            {
+                return ($($name::prepare(arch, tick),)*);
+            }
+
+            unsafe fn fetch(state: Self::State, row: usize) -> Self::Item
+            {
+                return ($($name::fetch(state.$index, row),)*);
+            }
+        }
+    };
+}
+
+impl_archetype_fetch_tuple!(A 0);
+impl_archetype_fetch_tuple!(A 0, B 1);
+impl_archetype_fetch_tuple!(A 0, B 1, C 2);
+impl_archetype_fetch_tuple!(A 0, B 1, C 2, D 3);
+impl_archetype_fetch_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_archetype_fetch_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_archetype_fetch_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_archetype_fetch_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Iterator over all objects matching an archetype query
+pub struct ArchetypeQuery<'a, Q: ArchetypeFetch<'a>>
+{
+    tables: Vec<(Q::State, *const ObjectRef, usize)>,
+    table: usize,
+    row: usize,
+    useless: PhantomData<&'a mut ArchetypeManager>
+}
+
+impl <'a, Q: ArchetypeFetch<'a>> Iterator for ArchetypeQuery<'a, Q>
+{
+    type Item = (ObjectRef, Q::Item);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        while let Some((state, owners, len)) = self.tables.get(self.table).copied()
+        {
+            if self.row < len
+            {
+                let row = self.row;
+                self.row += 1;
+                // The manager is mutably borrowed for 'a and each row is visited once
+                return unsafe { Some((*owners.add(row), Q::fetch(state, row))) };
+            }
+            self.table += 1;
+            self.row = 0;
+        }
+        return None;
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! REGECS component bundles
+
+use crate::object::ObjectRef;
+use crate::error::Error;
+
+/// Represents a group of components which are inserted together for a single object
+///
+/// *implemented for every component through #[derive(Component)], for tuples of bundles
+/// and for structs through #[derive(Bundle)]*
+pub trait Bundle<TComponentManager>
+{
+    /// Inserts all components of this bundle for the given owner
+    ///
+    /// *stops at the first component rejected by its storage, components inserted before it are kept*
+    fn insert(self, owner: ObjectRef, components: &mut TComponentManager) -> Result<(), Error>;
+}
+
+impl <TComponentManager> Bundle<TComponentManager> for ()
+{
+    fn insert(self, _: ObjectRef, _: &mut TComponentManager) -> Result<(), Error>
+    {
+        return Ok(());
+    }
+}
+
+macro_rules! impl_tuple_bundle
+{
+    ($($name: ident),*) =>
+    {
+        impl <TComponentManager, $($name: Bundle<TComponentManager>),*> Bundle<TComponentManager> for ($($name,)*)
+        {
+            #[allow(non_snake_case)]
+            fn insert(self, owner: ObjectRef, components: &mut TComponentManager) -> Result<(), Error>
+            {
+                let ($($name,)*) = self;
+                $($name.insert(owner, components)?;)*
+                return Ok(());
+            }
+        }
+    };
+}
+
+impl_tuple_bundle!(A);
+impl_tuple_bundle!(A, B);
+impl_tuple_bundle!(A, B, C);
+impl_tuple_bundle!(A, B, C, D);
+impl_tuple_bundle!(A, B, C, D, E);
+impl_tuple_bundle!(A, B, C, D, E, F);
+impl_tuple_bundle!(A, B, C, D, E, F, G);
+impl_tuple_bundle!(A, B, C, D, E, F, G, H);
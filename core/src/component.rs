@@ -28,14 +28,455 @@
 
 //! REGECS component layer
 
+mod sparse;
+mod dense;
+mod map;
+mod tag;
+mod chunked;
+mod dynamic;
+mod index;
+
+use std::any::Any;
+use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::vec::Vec;
 
 use crate::object::ObjectRef;
 
+pub use sparse::SparseSetPool;
+pub use dense::DenseVecPool;
+pub use map::HashMapPool;
+pub use map::BTreePool;
+pub use tag::Tag;
+pub use tag::TagPool;
+pub use chunked::ChunkedPool;
+pub use dynamic::DropFn;
+pub use dynamic::CloneFn;
+pub use dynamic::EncodeFn;
+pub use dynamic::DecodeFn;
+pub use dynamic::ComponentInfo;
+pub use dynamic::DynamicPool;
+pub use dynamic::TypedPool;
+pub use dynamic::ComponentId;
+pub use dynamic::DynamicComponents;
+pub use index::KeyMap;
+pub use index::KeyFn;
+pub use index::Index;
+pub use index::HashIndex;
+pub use index::OrderedIndex;
+
+use index::AnyIndex;
+use crate::error::Error;
+
+/// Scene tick counter used for change detection
+///
+/// *tick 0 is never used by a scene, so a system starting with a last run tick of 0 sees every change;
+/// ticks are 64 bits wide so that they never wrap around*
+pub type Tick = u64;
+
+/// Storage interface implemented by all component pools
+///
+/// *each component is owned by an object and an object owns at most one component per storage*
+///
+/// # Safety
+///
+/// [Queries](crate::query::Query) keep the components returned by [get_mut_ptr](Storage::get_mut_ptr)
+/// alive while accessing other owners, so implementors must guarantee that:
+/// - `iter` and `iter_mut` yield each owner at most once
+/// - pointers returned by `get_mut_ptr` for distinct owners never overlap
+/// - `get_mut_ptr` neither accesses nor creates references to the components of other owners,
+///   so pointers returned earlier stay valid
+/// - components are not stored inline in the storage value, unless they are zero-sized
+pub unsafe trait Storage
+{
+    /// Type of component stored
+    type Component;
+
+    /// Attaches a component to the given object
+    ///
+    /// *returns the previous component if the object already owned one in this storage, or an error if
+    /// the storage rejects the component, which is then dropped and the previous component kept*
+    fn insert(&mut self, owner: ObjectRef, comp: Self::Component) -> Result<Option<Self::Component>, Error>;
+
+    /// Removes the component owned by the given object
+    fn remove(&mut self, owner: ObjectRef) -> Option<Self::Component>;
+
+    /// Returns the component owned by the given object
+    fn get(&self, owner: ObjectRef) -> Option<&Self::Component>;
+
+    /// Returns the component owned by the given object
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut Self::Component>;
+
+    /// Mutates the component owned by the given object through the given function
+    ///
+    /// *returns None if the object owns no component; storages with secondary indexes update them
+    /// once the function returns*
+    fn edit(&mut self, owner: ObjectRef, f: &mut dyn FnMut(&mut Self::Component) -> Result<(), Error>) -> Option<Result<(), Error>>
+    {
+        return self.get_mut(owner).map(f);
+    }
+
+    /// Returns a pointer to the component owned by the given object, used by queries to hold
+    /// several components of the same storage at once
+    ///
+    /// *the component is accessed as if through get_mut*
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut Self::Component>;
+
+    /// Returns true if the given object owns a component in this storage
+    fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return self.get(owner).is_some();
+    }
+
+    /// Iterates over all components with their owning object
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &Self::Component)>;
+
+    /// Iterates mutably over all components with their owning object
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut Self::Component)>;
+
+    /// Returns the number of components in this storage
+    fn len(&self) -> usize;
+
+    /// Returns true if this storage holds no component
+    fn is_empty(&self) -> bool
+    {
+        return self.len() == 0;
+    }
+
+    /// Sets the current tick used to stamp additions, changes and removals
+    ///
+    /// *no-op for storages which do not track changes*
+    fn set_tick(&mut self, _tick: Tick)
+    {
+    }
+
+    /// Forgets removals which happened before the given tick
+    ///
+    /// *no-op for storages which do not track changes*
+    fn clear_removed(&mut self, _before: Tick)
+    {
+    }
+
+    /// Removes the component owned by the given object without queuing a lifecycle notification
+    ///
+    /// *used by component managers which run the on_remove hook themselves, before the removal*
+    fn remove_silent(&mut self, owner: ObjectRef) -> Option<Self::Component>
+    {
+        return self.remove(owner);
+    }
+
+    /// Returns the lifecycle hooks registered on this storage, as a type-erased [Hooks](Hooks)
+    ///
+    /// *None for storages which do not support hooks*
+    fn lifecycle_hooks(&self) -> Option<&dyn Any>
+    {
+        return None;
+    }
+
+    /// Pops the next lifecycle notification waiting for its hook to run
+    fn pop_lifecycle(&mut self) -> Option<Lifecycle>
+    {
+        return None;
+    }
+
+    /// Packs live components to remove holes left by removals
+    ///
+    /// *no-op for storages which are always dense*
+    fn compact(&mut self)
+    {
+    }
+
+    /// Re-indexes the components mutated since the last call
+    ///
+    /// *no-op for storages without secondary indexes; components breaking a unique index stay out of
+    /// indexes and the first violation is returned*
+    fn reindex(&mut self) -> Result<(), Error>
+    {
+        return Ok(());
+    }
+
+    /// Replaces all components of this storage by the ones of the given storage, used to restore snapshots
+    ///
+    /// *storages with secondary indexes or lifecycle hooks keep them, new components are indexed*
+    fn restore(&mut self, other: Self)
+        where Self: Sized
+    {
+        *self = other;
+    }
+}
+
+/// Lifecycle notification recorded by a storage which has hooks registered
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Lifecycle
+{
+    /// A component was attached to the given object
+    Added(ObjectRef),
+
+    /// A component was removed from the given object
+    Removed(ObjectRef)
+}
+
+/// Hook function, receives the component manager and the owning object
+pub type Hook<TComponentManager> = fn(&mut TComponentManager, ObjectRef);
+
+/// Set of lifecycle hooks for a given type of component
+///
+/// *hooks are run by ComponentManager::run_hooks, which a scene calls after each system,
+/// event handler, object spawn and object removal*
+///
+/// *when the components of an object are cleared through its manager, as done by Scene::remove_object,
+/// on_remove runs before the component is removed so that the hook can still read it; a component
+/// removed directly from its pool is handed to the caller and on_remove runs later, once it is gone*
+pub struct Hooks<TComponentManager>
+{
+    pub on_add: Option<Hook<TComponentManager>>,
+    pub on_remove: Option<Hook<TComponentManager>>
+}
+
+#[allow(clippy::non_canonical_clone_impl)]
+impl <TComponentManager> Clone for Hooks<TComponentManager>
+{
+    fn clone(&self) -> Self
+    {
+        return *self;
+    }
+}
+
+impl <TComponentManager> Copy for Hooks<TComponentManager> {}
+
+impl <TComponentManager> Default for Hooks<TComponentManager>
+{
+    fn default() -> Self
+    {
+        return Hooks::new();
+    }
+}
+
+impl <TComponentManager> Hooks<TComponentManager>
+{
+    pub fn new() -> Hooks<TComponentManager>
+    {
+        return Hooks
+        {
+            on_add: None,
+            on_remove: None
+        };
+    }
+
+    /// Returns the hooks declared by the given component type
+    pub fn of<TComponent: ComponentHooks<TComponentManager>>() -> Hooks<TComponentManager>
+    {
+        return Hooks
+        {
+            on_add: Some(TComponent::on_add),
+            on_remove: Some(TComponent::on_remove)
+        };
+    }
+
+    pub fn on_add(mut self, hook: Hook<TComponentManager>) -> Self
+    {
+        self.on_add = Some(hook);
+        return self;
+    }
+
+    pub fn on_remove(mut self, hook: Hook<TComponentManager>) -> Self
+    {
+        self.on_remove = Some(hook);
+        return self;
+    }
+}
+
+/// Lifecycle hooks declared by a component type
+///
+/// *activated with the `#[hooks]` attribute on a [ComponentPool](ComponentPool) field of a derived
+/// component manager or with [ArchetypeManager::set_hooks](crate::archetype::ArchetypeManager::set_hooks),
+/// other storages do not support hooks*
+pub trait ComponentHooks<TComponentManager>
+{
+    /// Called after a component of this type has been attached to the given object
+    fn on_add(_components: &mut TComponentManager, _owner: ObjectRef)
+    {
+    }
+
+    /// Called when a component of this type is removed from the given object
+    ///
+    /// *see [Hooks](Hooks) for when the component is still attached*
+    fn on_remove(_components: &mut TComponentManager, _owner: ObjectRef)
+    {
+    }
+}
+
+/// Stable name and schema version of a component type, recorded in snapshots and deltas so that
+/// older saves can be matched and migrated
+///
+/// *implemented by `#[derive(Component)]`, the name is set with `#[component(name = "...")]` and defaults
+/// to the name of the type, the version is set with `#[version(N)]` and defaults to 1*
+pub trait Versioned
+{
+    /// Name of the component type, it must not change between builds unlike `std::any::type_name`
+    const NAME: &'static str;
+
+    const VERSION: u32;
+}
+
+/// Change detection interface implemented by storages which track component ticks
+pub trait Tracked: Storage
+{
+    /// Returns the current tick of this storage
+    fn tick(&self) -> Tick;
+
+    /// Returns the tick at which the component of the given object was added
+    fn added_tick(&self, owner: ObjectRef) -> Option<Tick>;
+
+    /// Returns the tick at which the component of the given object was last mutably accessed
+    fn changed_tick(&self, owner: ObjectRef) -> Option<Tick>;
+
+    /// Iterates over all objects whose component was removed after the given tick
+    fn removed_since(&self, tick: Tick) -> impl Iterator<Item = ObjectRef>;
+}
+
+/// Opaque handle to a component stored in a [ComponentPool](ComponentPool)
+///
+/// *a handle is made of a slot index and a generation; once the component is removed,
+/// the handle is considered stale and is rejected by the pool even if the slot is reused*
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComponentRef
+{
+    index: u32,
+    generation: u32
+}
+
+impl ComponentRef
+{
+    /// Returns the slot index of this handle
+    pub fn index(&self) -> usize
+    {
+        return self.index as usize;
+    }
+
+    /// Returns the generation of this handle
+    pub fn generation(&self) -> u32
+    {
+        return self.generation;
+    }
+}
+
+/// Table mapping old handles to new handles, returned by [ComponentPool::compact](ComponentPool::compact)
+pub struct Remap
+{
+    table: Vec<Option<(ComponentRef, ComponentRef)>>
+}
+
+impl Remap
+{
+    /// Returns the new handle of the component pointed by the given old handle, None if the old handle was stale
+    pub fn get(&self, old: ComponentRef) -> Option<ComponentRef>
+    {
+        let (from, to) = (*self.table.get(old.index())?)?;
+        if from != old
+        {
+            return None;
+        }
+        return Some(to);
+    }
+
+    /// Iterates over all components which were moved, as (old handle, new handle)
+    pub fn moved(&self) -> impl Iterator<Item = (ComponentRef, ComponentRef)> + '_
+    {
+        return self.table.iter().filter_map(|v| *v).filter(|(from, to)| from != to);
+    }
+}
+
+/// Key used to lookup a component in a [ComponentPool](ComponentPool)
+///
+/// *components can be looked up either by handle or by owning object*
+pub trait ComponentKey: Copy
+{
+    /// Resolves this key to a live slot index in the given pool
+    fn resolve<TComponent>(self, pool: &ComponentPool<TComponent>) -> Option<usize>;
+}
+
+impl ComponentKey for ComponentRef
+{
+    fn resolve<TComponent>(self, pool: &ComponentPool<TComponent>) -> Option<usize>
+    {
+        let slot = pool.slots.get(self.index as usize)?;
+        if slot.generation != self.generation || slot.comp.is_none()
+        {
+            return None;
+        }
+        return Some(self.index as usize);
+    }
+}
+
+impl ComponentKey for ObjectRef
+{
+    fn resolve<TComponent>(self, pool: &ComponentPool<TComponent>) -> Option<usize>
+    {
+        let index = (*pool.lookup.get(self.index())?)? as usize;
+        if pool.slots[index].owner != Some(self)
+        {
+            return None;
+        }
+        return Some(index);
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Slot<TComponent>
+{
+    generation: u32,
+    owner: Option<ObjectRef>,
+    added: Tick,
+    changed: Tick,
+    comp: Option<TComponent>
+}
+
 /// Represents an allocation pool for a given type of component
+///
+/// *removed slots are recycled through a free list, handles are only shifted by [compact](ComponentPool::compact)*
+///
+/// Each component is owned by an object and an object owns at most one component per pool.
+/// In addition to the [Storage](Storage) interface, components can be looked up by [ComponentRef](ComponentRef).
+///
+/// The pool tracks changes: additions, mutable accesses and removals are stamped with the current tick.
+///
+/// Lifecycle hooks can be registered with [set_hooks](ComponentPool::set_hooks); additions and removals
+/// are then queued until the component manager runs its hooks.
+///
+/// Secondary [indexes](Index) can be declared with [add_index](ComponentPool::add_index). They follow
+/// additions, removals and [modify](ComponentPool::modify); owners of components mutably accessed in any
+/// other way (`get_mut`, `iter_mut`, queries) are marked dirty, and [lookup](ComponentPool::lookup) fails
+/// until they are re-indexed by [reindex](ComponentPool::reindex), which a scene runs after each system,
+/// event handler and spawn through [ComponentManager::reindex](ComponentManager::reindex). Insertions breaking
+/// a unique index are rejected; mutated components breaking one are never indexed, they stay dirty until they
+/// are fixed or removed.
+///
+/// *indexes are not serialized: a deserialized pool has none until they are declared again, while a pool
+/// restored from a snapshot keeps the indexes it declared and re-indexes the loaded components*
 pub struct ComponentPool<TComponent: Sized>
 {
-    comps: Vec<TComponent>
+    slots: Vec<Slot<TComponent>>,
+    lookup: Vec<Option<u32>>,
+    free: Vec<u32>,
+    base_generation: u32,
+    len: usize,
+    tick: Tick,
+    removed: Vec<(ObjectRef, Tick)>,
+    forgotten: Tick,
+    hooks: Option<Box<dyn Any + Send + Sync>>,
+    lifecycle: VecDeque<Lifecycle>,
+    indexes: Vec<Box<dyn AnyIndex<TComponent> + Send + Sync>>,
+    dirty: HashSet<ObjectRef>
+}
+
+impl <TComponent: Sized> Default for ComponentPool<TComponent>
+{
+    fn default() -> Self
+    {
+        return ComponentPool::new();
+    }
 }
 
 impl <TComponent: Sized> ComponentPool<TComponent>
@@ -44,31 +485,1026 @@ impl <TComponent: Sized> ComponentPool<TComponent>
     {
         return ComponentPool
         {
-            comps: Vec::new()
+            slots: Vec::new(),
+            lookup: Vec::new(),
+            free: Vec::new(),
+            base_generation: 0,
+            len: 0,
+            tick: 1,
+            removed: Vec::new(),
+            forgotten: 0,
+            hooks: None,
+            lifecycle: VecDeque::new(),
+            indexes: Vec::new(),
+            dirty: HashSet::new()
+        };
+    }
+
+    /// Registers lifecycle hooks on this pool, replacing any previously registered hooks
+    ///
+    /// *the component manager type must be the one owning this pool, otherwise hooks are never run*
+    pub fn set_hooks<TComponentManager: 'static>(&mut self, hooks: Hooks<TComponentManager>)
+    {
+        self.hooks = Some(Box::new(hooks));
+    }
+
+    /// Unregisters lifecycle hooks, pending notifications are discarded
+    pub fn clear_hooks(&mut self)
+    {
+        self.hooks = None;
+        self.lifecycle.clear();
+    }
+
+    /// Attaches a new component to the given object and returns a handle to it
+    ///
+    /// *if the object already owns a component in this pool, it is replaced and its handle is kept;
+    /// a component left behind by a previous object at the same index is removed*
+    ///
+    /// # Panics
+    ///
+    /// Panics if the component breaks a unique index, see [try_add](ComponentPool::try_add).
+    pub fn add(&mut self, owner: ObjectRef, comp: TComponent) -> ComponentRef
+    {
+        return match self.try_add(owner, comp)
+        {
+            Ok(id) => id,
+            Err(e) => panic!("{}", e)
+        };
+    }
+
+    /// Attaches a new component to the given object and returns a handle to it
+    ///
+    /// *returns an error if the component breaks a unique index, the component is then not added*
+    pub fn try_add(&mut self, owner: ObjectRef, comp: TComponent) -> Result<ComponentRef, Error>
+    {
+        self.discard_stale(owner);
+        for index in &self.indexes
+        {
+            index.check(owner, &comp)?;
+        }
+        for index in &mut self.indexes
+        {
+            index.insert(owner, &comp);
+        }
+        // The previous component of the owner, if any, is replaced by a clean one
+        self.dirty.remove(&owner);
+        return Ok(self.add_unchecked(owner, comp));
+    }
+
+    /// Attaches a component to the given object, returns the previous component of the object if any
+    ///
+    /// *returns an error if the component breaks a unique index, the previous component is then kept*
+    pub fn try_insert(&mut self, owner: ObjectRef, comp: TComponent) -> Result<Option<TComponent>, Error>
+    {
+        self.discard_stale(owner);
+        for index in &self.indexes
+        {
+            index.check(owner, &comp)?;
+        }
+        let (old, res) = self.put(owner, comp);
+        return res.map(|_| old);
+    }
+
+    /// Attaches a component to the given object and indexes it, returns the previous component of the object
+    /// and the unique index violation which kept the new component out of indexes if any
+    fn put(&mut self, owner: ObjectRef, comp: TComponent) -> (Option<TComponent>, Result<(), Error>)
+    {
+        self.discard_stale(owner);
+        let old = owner.resolve(self).and_then(|index| self.slots[index].comp.take());
+        let index = self.add_unchecked(owner, comp).index as usize;
+        let comp = self.slots[index].comp.as_ref().unwrap();
+        return (old, Self::index_owner(&mut self.indexes, &mut self.dirty, owner, comp));
+    }
+
+    /// Removes a component left behind by a previous object at the same index as the given owner
+    fn discard_stale(&mut self, owner: ObjectRef)
+    {
+        let stale = self.lookup.get(owner.index()).copied().flatten()
+            .and_then(|index| self.slots[index as usize].owner)
+            .filter(|o| *o != owner);
+        if let Some(stale) = stale
+        {
+            self.remove(stale);
+        }
+    }
+
+    fn mark_dirty(&mut self, owner: ObjectRef)
+    {
+        if !self.indexes.is_empty()
+        {
+            self.dirty.insert(owner);
+        }
+    }
+
+    fn add_unchecked(&mut self, owner: ObjectRef, comp: TComponent) -> ComponentRef
+    {
+        if let Some(index) = owner.resolve(self)
+        {
+            let slot = &mut self.slots[index];
+            slot.comp = Some(comp);
+            slot.changed = self.tick;
+            return ComponentRef
+            {
+                index: index as u32,
+                generation: slot.generation
+            };
+        }
+        self.len += 1;
+        let index = match self.free.pop()
+        {
+            Some(index) => index,
+            None =>
+            {
+                self.slots.push(Slot
+                {
+                    generation: self.base_generation,
+                    owner: None,
+                    added: 0,
+                    changed: 0,
+                    comp: None
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.owner = Some(owner);
+        slot.added = self.tick;
+        slot.changed = self.tick;
+        slot.comp = Some(comp);
+        if self.lookup.len() <= owner.index()
+        {
+            self.lookup.resize(owner.index() + 1, None);
+        }
+        self.lookup[owner.index()] = Some(index);
+        if self.hooks.is_some()
+        {
+            self.lifecycle.push_back(Lifecycle::Added(owner));
+        }
+        return ComponentRef
+        {
+            index,
+            generation: slot.generation
         };
     }
 
-    pub fn add(&mut self, comp: TComponent) -> usize
+    /// Returns true if the given key points to a live component
+    pub fn contains<TKey: ComponentKey>(&self, key: TKey) -> bool
+    {
+        return key.resolve(self).is_some();
+    }
+
+    /// Returns the handle of the component owned by the given object
+    pub fn find(&self, owner: ObjectRef) -> Option<ComponentRef>
+    {
+        let index = owner.resolve(self)?;
+        return Some(ComponentRef
+        {
+            index: index as u32,
+            generation: self.slots[index].generation
+        });
+    }
+
+    /// Returns the object owning the component pointed by the given handle
+    pub fn owner(&self, id: ComponentRef) -> Option<ObjectRef>
     {
-        let id = self.comps.len();
-        self.comps.push(comp);
-        return id;
+        let index = id.resolve(self)?;
+        return self.slots[index].owner;
     }
 
-    pub fn get(&mut self, id: usize) -> &mut TComponent
+    /// Returns the component pointed by the given key, None if the key is stale
+    pub fn get<TKey: ComponentKey>(&self, key: TKey) -> Option<&TComponent>
     {
-        return &mut self.comps[id];
+        let index = key.resolve(self)?;
+        return self.slots[index].comp.as_ref();
     }
 
-    pub fn remove(&mut self, id: usize)
+    /// Returns the component pointed by the given key, None if the key is stale
+    ///
+    /// *the component is marked as changed, and as dirty if the pool has indexes*
+    pub fn get_mut<TKey: ComponentKey>(&mut self, key: TKey) -> Option<&mut TComponent>
     {
-        self.comps.remove(id);
+        let index = key.resolve(self)?;
+        if let Some(owner) = self.slots[index].owner
+        {
+            self.mark_dirty(owner);
+        }
+        let slot = &mut self.slots[index];
+        slot.changed = self.tick;
+        return slot.comp.as_mut();
+    }
+
+    /// Removes the component pointed by the given key
+    ///
+    /// *returns None if the key is stale*
+    pub fn remove<TKey: ComponentKey>(&mut self, key: TKey) -> Option<TComponent>
+    {
+        let index = key.resolve(self)?;
+        let slot = &mut self.slots[index];
+        let comp = slot.comp.take();
+        if let Some(owner) = slot.owner.take()
+        {
+            for index in &mut self.indexes
+            {
+                index.remove(owner);
+            }
+            self.dirty.remove(&owner);
+            self.lookup[owner.index()] = None;
+            self.removed.push((owner, self.tick));
+            if self.hooks.is_some()
+            {
+                self.lifecycle.push_back(Lifecycle::Removed(owner));
+            }
+        }
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index as u32);
+        self.len -= 1;
+        return comp;
+    }
+
+    /// Mutates the component pointed by the given key and updates indexes
+    ///
+    /// *returns None if the key is stale; if the new value breaks a unique index, it is kept but left
+    /// out of all indexes and marked dirty, and an error is returned*
+    pub fn modify<TKey: ComponentKey, R, F: FnOnce(&mut TComponent) -> R>(&mut self, key: TKey, f: F) -> Option<Result<R, Error>>
+    {
+        let index = key.resolve(self)?;
+        let slot = &mut self.slots[index];
+        slot.changed = self.tick;
+        let (owner, comp) = (slot.owner?, slot.comp.as_mut()?);
+        let res = f(comp);
+        return Some(Self::index_owner(&mut self.indexes, &mut self.dirty, owner, comp).map(|_| res));
+    }
+
+    /// Re-indexes the component of the given owner in all indexes, or in none if it breaks a unique index
+    fn index_owner(indexes: &mut [Box<dyn AnyIndex<TComponent> + Send + Sync>], dirty: &mut HashSet<ObjectRef>, owner: ObjectRef, comp: &TComponent) -> Result<(), Error>
+    {
+        for index in indexes.iter_mut()
+        {
+            index.remove(owner);
+        }
+        for index in indexes.iter()
+        {
+            if let Err(e) = index.check(owner, comp)
+            {
+                dirty.insert(owner);
+                return Err(e);
+            }
+        }
+        for index in indexes.iter_mut()
+        {
+            index.insert(owner, comp);
+        }
+        dirty.remove(&owner);
+        return Ok(());
     }
+
+    /// Declares a secondary index and indexes all components currently in the pool
+    ///
+    /// *returns an error if an index with the same name exists or if current components break
+    /// the unique constraint of the index*
+    pub fn add_index<TMap>(&mut self, mut index: Index<TComponent, TMap>) -> Result<(), Error>
+        where TComponent: 'static,
+              TMap: 'static + KeyMap + Send + Sync,
+              TMap::Key: Send + Sync
+    {
+        if self.indexes.iter().any(|i| i.name() == index.name())
+        {
+            return Err(Error::DuplicateIndex(String::from(index.name())));
+        }
+        for (owner, comp) in self.iter_owned()
+        {
+            index.check(owner, comp)?;
+            index.insert(owner, comp);
+        }
+        self.indexes.push(Box::new(index));
+        return Ok(());
+    }
+
+    /// Removes the secondary index with the given name, returns false if there is none
+    pub fn remove_index(&mut self, name: &str) -> bool
+    {
+        let len = self.indexes.len();
+        self.indexes.retain(|i| i.name() != name);
+        if self.indexes.is_empty()
+        {
+            self.dirty.clear();
+        }
+        return self.indexes.len() != len;
+    }
+
+    /// Returns the secondary index with the given name, None if there is none, if it is not of type TIndex
+    /// or if some owners are dirty
+    pub fn index<TIndex: 'static>(&self, name: &str) -> Option<&TIndex>
+    {
+        if !self.dirty.is_empty()
+        {
+            return None;
+        }
+        return self.indexes.iter().find(|i| i.name() == name).and_then(|i| i.as_any().downcast_ref());
+    }
+
+    /// Returns the objects whose component has the given key in the named index
+    ///
+    /// *returns an error if there is no such index, if its key is not of type TIndexKey or if some owners
+    /// are dirty and must be re-indexed first*
+    pub fn lookup<TIndexKey: 'static>(&self, name: &str, key: &TIndexKey) -> Result<&[ObjectRef], Error>
+    {
+        let index = self.indexes.iter().find(|i| i.name() == name)
+            .ok_or_else(|| Error::UnknownIndex(String::from(name)))?;
+        if !self.dirty.is_empty()
+        {
+            return Err(Error::StaleIndex(String::from(name)));
+        }
+        return index.lookup(key).ok_or_else(|| Error::UnknownIndex(String::from(name)));
+    }
+
+    /// Returns true if some owners must be re-indexed before indexes can be used
+    pub fn is_dirty(&self) -> bool
+    {
+        return !self.dirty.is_empty();
+    }
+
+    /// Re-indexes the components of all dirty owners
+    ///
+    /// *components breaking a unique index are left out of all indexes and stay dirty, the first
+    /// violation is returned*
+    pub fn reindex(&mut self) -> Result<(), Error>
+    {
+        let mut err = None;
+        let dirty: Vec<ObjectRef> = self.dirty.iter().copied().collect();
+        for owner in dirty
+        {
+            let slots = &self.slots;
+            match owner.resolve(self).and_then(|index| slots[index].comp.as_ref())
+            {
+                Some(comp) =>
+                {
+                    if let Err(e) = Self::index_owner(&mut self.indexes, &mut self.dirty, owner, comp)
+                    {
+                        err = err.or(Some(e));
+                    }
+                },
+                None =>
+                {
+                    self.dirty.remove(&owner);
+                }
+            }
+        }
+        return match err
+        {
+            Some(e) => Err(e),
+            None => Ok(())
+        };
+    }
+
+    /// Returns the number of live components in this pool
+    pub fn len(&self) -> usize
+    {
+        return self.len;
+    }
+
+    /// Returns true if this pool holds no component
+    pub fn is_empty(&self) -> bool
+    {
+        return self.len == 0;
+    }
+
+    /// Converts all components of this pool, keeping handles, owners and ticks
+    ///
+    /// *hooks, pending lifecycle notifications, the removal log and indexes are discarded, lookups on the
+    /// converted pool return [UnknownIndex](Error::UnknownIndex) until indexes are declared again*
+    #[cfg(feature = "snapshot")]
+    pub(crate) fn convert<TOther, F: FnMut(TComponent) -> TOther>(self, mut f: F) -> ComponentPool<TOther>
+    {
+        return ComponentPool
+        {
+            slots: self.slots.into_iter().map(|s| Slot
+            {
+                generation: s.generation,
+                owner: s.owner,
+                added: s.added,
+                changed: s.changed,
+                comp: s.comp.map(&mut f)
+            }).collect(),
+            lookup: self.lookup,
+            free: self.free,
+            base_generation: self.base_generation,
+            len: self.len,
+            tick: self.tick,
+            removed: Vec::new(),
+            forgotten: self.tick,
+            hooks: None,
+            lifecycle: VecDeque::new(),
+            indexes: Vec::new(),
+            dirty: HashSet::new()
+        };
+    }
+
+    /// Packs all live components at the start of the pool, keeping their order, and releases unused slots
+    ///
+    /// *owner mappings are updated so ObjectRef lookups keep working; handles of moved components become
+    /// stale and must be translated through the returned table*
+    pub fn compact(&mut self) -> Remap
+    {
+        let mut table = Vec::with_capacity(self.slots.len());
+        let mut dst = 0;
+        for src in 0..self.slots.len()
+        {
+            let generation = self.slots[src].generation;
+            if self.slots[src].comp.is_none()
+            {
+                table.push(None);
+                continue;
+            }
+            if src != dst
+            {
+                let slot = &mut self.slots[src];
+                let comp = slot.comp.take();
+                let owner = slot.owner.take();
+                let (added, changed) = (slot.added, slot.changed);
+                // Old handles to the source slot must not match whatever moves in later
+                slot.generation = generation.wrapping_add(1);
+                let slot = &mut self.slots[dst];
+                slot.comp = comp;
+                slot.owner = owner;
+                slot.added = added;
+                slot.changed = changed;
+                if let Some(owner) = owner
+                {
+                    self.lookup[owner.index()] = Some(dst as u32);
+                }
+            }
+            table.push(Some((
+                ComponentRef { index: src as u32, generation },
+                ComponentRef { index: dst as u32, generation: self.slots[dst].generation }
+            )));
+            dst += 1;
+        }
+        // Slots pushed later must not match handles to the released slots
+        for slot in &self.slots[dst..]
+        {
+            self.base_generation = self.base_generation.max(slot.generation);
+        }
+        self.slots.truncate(dst);
+        self.slots.shrink_to_fit();
+        self.free.clear();
+        self.free.shrink_to_fit();
+        return Remap
+        {
+            table
+        };
+    }
+
+    /// Iterates over all live components with their handle
+    pub fn iter(&self) -> impl Iterator<Item = (ComponentRef, &TComponent)>
+    {
+        return self.slots.iter().enumerate().filter_map(|(i, s)|
+        {
+            let generation = s.generation;
+            return s.comp.as_ref().map(|c| (ComponentRef { index: i as u32, generation }, c));
+        });
+    }
+
+    /// Iterates mutably over all live components with their handle
+    ///
+    /// *all visited components are marked as changed, and as dirty if the pool has indexes*
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (ComponentRef, &mut TComponent)>
+    {
+        let tick = self.tick;
+        // Only owners of pools with indexes have to be tracked
+        let indexed = !self.indexes.is_empty();
+        let mut dirty = Some(&mut self.dirty).filter(|_| indexed);
+        return self.slots.iter_mut().enumerate().filter_map(move |(i, s)|
+        {
+            let generation = s.generation;
+            let comp = s.comp.as_mut()?;
+            s.changed = tick;
+            if let (Some(dirty), Some(owner)) = (dirty.as_mut(), s.owner)
+            {
+                dirty.insert(owner);
+            }
+            return Some((ComponentRef { index: i as u32, generation }, comp));
+        });
+    }
+
+    /// Iterates over all live components with their owning object
+    pub fn iter_owned(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.slots.iter().filter_map(|s| Some((s.owner?, s.comp.as_ref()?)));
+    }
+
+    /// Iterates mutably over all live components with their owning object
+    ///
+    /// *all visited components are marked as changed, and as dirty if the pool has indexes*
+    pub fn iter_owned_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        let tick = self.tick;
+        // Only owners of pools with indexes have to be tracked
+        let indexed = !self.indexes.is_empty();
+        let mut dirty = Some(&mut self.dirty).filter(|_| indexed);
+        return self.slots.iter_mut().filter_map(move |s|
+        {
+            let owner = s.owner?;
+            let comp = s.comp.as_mut()?;
+            s.changed = tick;
+            if let Some(dirty) = dirty.as_mut()
+            {
+                dirty.insert(owner);
+            }
+            return Some((owner, comp));
+        });
+    }
+
+    /// Iterates over all components added after the given tick
+    pub fn iter_added(&self, since: Tick) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.slots.iter()
+            .filter(move |s| s.added > since)
+            .filter_map(|s| Some((s.owner?, s.comp.as_ref()?)));
+    }
+
+    /// Iterates over all components changed after the given tick, including new components
+    pub fn iter_changed(&self, since: Tick) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.slots.iter()
+            .filter(move |s| s.changed > since)
+            .filter_map(|s| Some((s.owner?, s.comp.as_ref()?)));
+    }
+}
+
+unsafe impl <TComponent: Sized> Storage for ComponentPool<TComponent>
+{
+    type Component = TComponent;
+
+    fn insert(&mut self, owner: ObjectRef, comp: TComponent) -> Result<Option<TComponent>, Error>
+    {
+        return ComponentPool::try_insert(self, owner, comp);
+    }
+
+    fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        return ComponentPool::remove(self, owner);
+    }
+
+    fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        return ComponentPool::get(self, owner);
+    }
+
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        return ComponentPool::get_mut(self, owner);
+    }
+
+    fn edit(&mut self, owner: ObjectRef, f: &mut dyn FnMut(&mut TComponent) -> Result<(), Error>) -> Option<Result<(), Error>>
+    {
+        return ComponentPool::modify(self, owner, f).map(|res| res.and_then(|r| r));
+    }
+
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut TComponent>
+    {
+        let index = (*self.lookup.get(owner.index())?)? as usize;
+        if index >= self.slots.len()
+        {
+            return None;
+        }
+        // Only the slot of the owner is accessed, as components of other owners may be borrowed
+        unsafe
+        {
+            let slot = self.slots.as_mut_ptr().add(index);
+            if (*slot).owner != Some(owner)
+            {
+                return None;
+            }
+            (*slot).changed = self.tick;
+            if !self.indexes.is_empty()
+            {
+                self.dirty.insert(owner);
+            }
+            return (*slot).comp.as_mut().map(|comp| comp as *mut TComponent);
+        }
+    }
+
+    fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return ComponentPool::contains(self, owner);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.iter_owned();
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        return self.iter_owned_mut();
+    }
+
+    fn len(&self) -> usize
+    {
+        return self.len;
+    }
+
+    fn set_tick(&mut self, tick: Tick)
+    {
+        self.tick = tick;
+    }
+
+    fn clear_removed(&mut self, before: Tick)
+    {
+        let mut forgotten = self.forgotten;
+        self.removed.retain(|(_, t)|
+        {
+            if *t < before
+            {
+                forgotten = forgotten.max(*t);
+            }
+            return *t >= before;
+        });
+        self.forgotten = forgotten;
+    }
+
+    fn remove_silent(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        let len = self.lifecycle.len();
+        let comp = ComponentPool::remove(self, owner);
+        self.lifecycle.truncate(len);
+        return comp;
+    }
+
+    fn lifecycle_hooks(&self) -> Option<&dyn Any>
+    {
+        return self.hooks.as_deref().map(|hooks| hooks as &dyn Any);
+    }
+
+    fn pop_lifecycle(&mut self) -> Option<Lifecycle>
+    {
+        return self.lifecycle.pop_front();
+    }
+
+    fn restore(&mut self, other: Self)
+    {
+        let indexes = std::mem::take(&mut self.indexes);
+        let hooks = self.hooks.take();
+        *self = other;
+        self.indexes = indexes;
+        self.hooks = hooks;
+        for index in &mut self.indexes
+        {
+            index.clear();
+        }
+        if !self.indexes.is_empty()
+        {
+            self.dirty = self.iter_owned().map(|(owner, _)| owner).collect();
+            // Components breaking a unique index stay dirty, lookups report it
+            let _ = self.reindex();
+        }
+    }
+
+    fn compact(&mut self)
+    {
+        ComponentPool::compact(self);
+    }
+
+    fn reindex(&mut self) -> Result<(), Error>
+    {
+        return ComponentPool::reindex(self);
+    }
+}
+
+impl <TComponent: Sized> Tracked for ComponentPool<TComponent>
+{
+    fn tick(&self) -> Tick
+    {
+        return self.tick;
+    }
+
+    fn added_tick(&self, owner: ObjectRef) -> Option<Tick>
+    {
+        let index = owner.resolve(self)?;
+        return Some(self.slots[index].added);
+    }
+
+    fn changed_tick(&self, owner: ObjectRef) -> Option<Tick>
+    {
+        let index = owner.resolve(self)?;
+        return Some(self.slots[index].changed);
+    }
+
+    fn removed_since(&self, tick: Tick) -> impl Iterator<Item = ObjectRef>
+    {
+        return self.removed.iter().filter(move |(_, t)| *t > tick).map(|(o, _)| *o);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct PoolRef<'a, TComponent>
+{
+    slots: &'a [Slot<TComponent>],
+    free: &'a [u32],
+    base_generation: u32,
+    tick: Tick
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct PoolData<TComponent>
+{
+    slots: Vec<Slot<TComponent>>,
+    free: Vec<u32>,
+    base_generation: u32,
+    tick: Tick
+}
+
+/// *hooks, pending lifecycle notifications and the removal log are not serialized*
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize> serde::Serialize for ComponentPool<TComponent>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        let data = PoolRef
+        {
+            slots: &self.slots,
+            free: &self.free,
+            base_generation: self.base_generation,
+            tick: self.tick
+        };
+        return data.serialize(serializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <'de, TComponent: serde::Deserialize<'de>> serde::Deserialize<'de> for ComponentPool<TComponent>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        use serde::de::Error;
+        let data = PoolData::deserialize(deserializer)?;
+        let mut pool = ComponentPool::new();
+        for (i, slot) in data.slots.iter().enumerate()
+        {
+            match (slot.owner, &slot.comp)
+            {
+                (Some(owner), Some(_)) =>
+                {
+                    if pool.lookup.len() <= owner.index()
+                    {
+                        pool.lookup.resize(owner.index() + 1, None);
+                    }
+                    if pool.lookup[owner.index()].is_some()
+                    {
+                        return Err(D::Error::custom(format!("duplicate owner {}", owner)));
+                    }
+                    pool.lookup[owner.index()] = Some(i as u32);
+                    pool.len += 1;
+                },
+                (None, None) => (),
+                _ => return Err(D::Error::custom(format!("inconsistent slot {}", i)))
+            }
+        }
+        let mut freed = vec![false; data.slots.len()];
+        for index in data.free.iter().map(|i| *i as usize)
+        {
+            match data.slots.get(index)
+            {
+                None => return Err(D::Error::custom(format!("free slot {} is out of range", index))),
+                Some(slot) if slot.comp.is_some() => return Err(D::Error::custom(format!("free slot {} is live", index))),
+                _ if freed[index] => return Err(D::Error::custom(format!("free slot {} is listed twice", index))),
+                _ => freed[index] = true
+            }
+        }
+        pool.slots = data.slots;
+        pool.free = data.free;
+        pool.base_generation = data.base_generation;
+        pool.tick = data.tick;
+        // The removal log is not serialized, removals up to the saved tick are unknown
+        pool.forgotten = data.tick;
+        return Ok(pool);
+    }
+}
+
+/// Components changed or removed in a storage since a given tick
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default)]
+pub struct Changes
+{
+    /// Objects whose component was added since the tick
+    pub added: Vec<ObjectRef>,
+
+    /// Objects whose component was changed, but not added, since the tick
+    pub changed: Vec<ObjectRef>,
+
+    /// Objects whose component was removed since the tick
+    pub removed: Vec<ObjectRef>
+}
+
+/// Storages able to serialize their components one by one
+#[cfg(feature = "serde")]
+pub trait SerializeEntries: Storage
+{
+    /// Serializes the component owned by the given object, fails if there is none
+    fn serialize_entry<S: serde::Serializer>(&self, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>;
+
+    /// Deserializes a component and attaches it to the given object, replacing any previous one
+    fn deserialize_entry<'de, D: serde::Deserializer<'de>>(&mut self, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>;
+
+    /// Returns the changes which happened after the given tick, None if this storage does not track changes
+    /// or has forgotten some of the removals which happened after the tick
+    fn changes_since(&self, _tick: Tick) -> Option<Changes>
+    {
+        return None;
+    }
+}
+
+#[cfg(feature = "serde")]
+fn serialize_entry<TStorage, S>(storage: &TStorage, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>
+    where TStorage: Storage,
+          TStorage::Component: serde::Serialize,
+          S: serde::Serializer
+{
+    use serde::ser::Error;
+    use serde::Serialize;
+    return match storage.get(owner)
+    {
+        Some(comp) => comp.serialize(serializer),
+        None => Err(S::Error::custom(format!("no component for {}", owner)))
+    };
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_entry<'de, TStorage, D>(storage: &mut TStorage, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>
+    where TStorage: Storage,
+          TStorage::Component: serde::Deserialize<'de>,
+          D: serde::Deserializer<'de>
+{
+    use serde::de::Error;
+    use serde::Deserialize;
+    let comp = TStorage::Component::deserialize(deserializer)?;
+    storage.insert(owner, comp).map_err(D::Error::custom)?;
+    return Ok(());
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize + serde::de::DeserializeOwned> SerializeEntries for ComponentPool<TComponent>
+{
+    fn serialize_entry<S: serde::Serializer>(&self, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return serialize_entry(self, owner, serializer);
+    }
+
+    fn deserialize_entry<'de, D: serde::Deserializer<'de>>(&mut self, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>
+    {
+        return deserialize_entry(self, owner, deserializer);
+    }
+
+    fn changes_since(&self, tick: Tick) -> Option<Changes>
+    {
+        if self.forgotten > tick
+        {
+            return None;
+        }
+        let mut changes = Changes::default();
+        for slot in &self.slots
+        {
+            if let (Some(owner), Some(_)) = (slot.owner, &slot.comp)
+            {
+                if slot.added > tick
+                {
+                    changes.added.push(owner);
+                }
+                else if slot.changed > tick
+                {
+                    changes.changed.push(owner);
+                }
+            }
+        }
+        changes.removed = self.removed_since(tick).collect();
+        return Some(changes);
+    }
+}
+
+/// Visitor over the pools of a component manager, used to serialize pools one by one
+#[cfg(feature = "serde")]
+pub trait PoolVisitor
+{
+    type Error;
+
+    /// Visits a pool given its field name, the [stable name](Versioned) of its component type,
+    /// the size of a component and the schema version of the component type
+    fn visit<TStorage: SerializeEntries + serde::Serialize>(&mut self, name: &'static str, component: &'static str, size: usize, version: u32, pool: &TStorage) -> Result<(), Self::Error>;
+}
+
+/// Loader of the pools of a component manager, used to deserialize pools one by one
+#[cfg(feature = "serde")]
+pub trait PoolLoader
+{
+    type Error;
+
+    /// Loads a pool given its field name, the [stable name](Versioned) of its component type,
+    /// the size of a component and the schema version of the component type
+    ///
+    /// *the pool is left untouched if the loader has no data for it*
+    fn load<TStorage: 'static + SerializeEntries + serde::de::DeserializeOwned + Default>(&mut self, name: &'static str, component: &'static str, size: usize, version: u32, pool: &mut TStorage) -> Result<(), Self::Error>;
+}
+
+/// Component managers whose pools can be serialized independently
+///
+/// *implemented by `#[derive(ComponentManager)]` when the struct is marked with `#[serialize]`*
+#[cfg(feature = "serde")]
+pub trait SerializePools
+{
+    /// Visits all pools in declaration order
+    fn visit_pools<TVisitor: PoolVisitor>(&self, visitor: &mut TVisitor) -> Result<(), TVisitor::Error>;
+
+    /// Loads all pools in declaration order, lifecycle hooks are registered again afterwards
+    fn load_pools<TLoader: PoolLoader>(&mut self, loader: &mut TLoader) -> Result<(), TLoader::Error>;
+}
+
+/// Serializes any storage as a list of (owner, component) entries
+#[cfg(feature = "serde")]
+fn serialize_entries<TStorage, S>(storage: &TStorage, serializer: S) -> Result<S::Ok, S::Error>
+    where TStorage: Storage,
+          TStorage::Component: serde::Serialize,
+          S: serde::Serializer
+{
+    use serde::ser::SerializeSeq;
+    let mut seq = serializer.serialize_seq(Some(storage.len()))?;
+    for entry in storage.iter()
+    {
+        seq.serialize_element(&entry)?;
+    }
+    return seq.end();
+}
+
+/// Deserializes any storage from a list of (owner, component) entries
+#[cfg(feature = "serde")]
+fn deserialize_entries<'de, TStorage, D>(deserializer: D) -> Result<TStorage, D::Error>
+    where TStorage: Storage + Default,
+          TStorage::Component: serde::Deserialize<'de>,
+          D: serde::Deserializer<'de>
+{
+    use serde::de::Error;
+    use serde::Deserialize;
+    let entries: Vec<(ObjectRef, TStorage::Component)> = Vec::deserialize(deserializer)?;
+    let mut storage = TStorage::default();
+    for (owner, comp) in entries
+    {
+        if storage.insert(owner, comp).map_err(D::Error::custom)?.is_some()
+        {
+            return Err(D::Error::custom(format!("duplicate owner {}", owner)));
+        }
+    }
+    return Ok(storage);
 }
 
 /// Base trait to represent the container of all component pools
+///
+/// *implemented by `#[derive(ComponentManager)]`*
 pub trait ComponentManager
 {
     /// Clears all components attached to the given entity
     fn clear_components(&mut self, target: ObjectRef);
+
+    /// Sets the current tick of all pools
+    fn set_tick(&mut self, _tick: Tick)
+    {
+    }
+
+    /// Forgets removals which happened before the given tick in all pools
+    fn clear_removed(&mut self, _before: Tick)
+    {
+    }
+
+    /// Runs lifecycle hooks for all pending notifications, until no hook produces new ones
+    fn run_hooks(&mut self)
+    {
+    }
+
+    /// Compacts all pools
+    ///
+    /// *ObjectRef lookups are preserved, component handles of moved components become stale*
+    fn compact(&mut self)
+    {
+    }
+
+    /// Re-indexes the components mutated since the last call in all pools, a scene calls it after
+    /// running hooks
+    ///
+    /// *components breaking a unique index stay out of indexes, lookups report the violation*
+    fn reindex(&mut self)
+    {
+    }
+
+    /// Returns the registry of runtime-defined components, None if this manager has none
+    fn dynamic(&self) -> Option<&DynamicComponents>
+    {
+        return None;
+    }
+
+    /// Returns the registry of runtime-defined components, None if this manager has none
+    fn dynamic_mut(&mut self) -> Option<&mut DynamicComponents>
+    {
+        return None;
+    }
 }
@@ -0,0 +1,300 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::component::Storage;
+use crate::error::Error;
+
+type Chunk<TComponent> = Box<[Option<(ObjectRef, TComponent)>]>;
+
+/// Chunked allocation pool for a given type of component
+///
+/// *components are stored in fixed-size blocks of CHUNK_SIZE slots which are never reallocated,
+/// so a component never moves in memory until it is removed; freed slots are recycled*
+pub struct ChunkedPool<TComponent: Sized, const CHUNK_SIZE: usize = 64>
+{
+    chunks: Vec<Chunk<TComponent>>,
+    lookup: Vec<Option<u32>>,
+    free: Vec<u32>,
+    len: usize
+}
+
+impl <TComponent: Sized, const CHUNK_SIZE: usize> Default for ChunkedPool<TComponent, CHUNK_SIZE>
+{
+    fn default() -> Self
+    {
+        return ChunkedPool::new();
+    }
+}
+
+impl <TComponent: Sized, const CHUNK_SIZE: usize> ChunkedPool<TComponent, CHUNK_SIZE>
+{
+    const NON_EMPTY_CHUNK: () = assert!(CHUNK_SIZE > 0, "Chunk size must not be zero");
+
+    pub fn new() -> ChunkedPool<TComponent, CHUNK_SIZE>
+    {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::NON_EMPTY_CHUNK;
+        return ChunkedPool
+        {
+            chunks: Vec::new(),
+            lookup: Vec::new(),
+            free: Vec::new(),
+            len: 0
+        };
+    }
+
+    fn slot(&self, index: usize) -> &Option<(ObjectRef, TComponent)>
+    {
+        return &self.chunks[index / CHUNK_SIZE][index % CHUNK_SIZE];
+    }
+
+    fn slot_mut(&mut self, index: usize) -> &mut Option<(ObjectRef, TComponent)>
+    {
+        return &mut self.chunks[index / CHUNK_SIZE][index % CHUNK_SIZE];
+    }
+
+    fn slot_index(&self, owner: ObjectRef) -> Option<usize>
+    {
+        let index = (*self.lookup.get(owner.index())?)? as usize;
+        match self.slot(index)
+        {
+            Some((o, _)) if *o == owner => return Some(index),
+            _ => return None
+        }
+    }
+
+    fn alloc(&mut self) -> usize
+    {
+        if let Some(index) = self.free.pop()
+        {
+            return index as usize;
+        }
+        let base = self.chunks.len() * CHUNK_SIZE;
+        self.chunks.push((0..CHUNK_SIZE).map(|_| None).collect());
+        // Hand out the lowest slot first so new chunks fill in order
+        self.free.extend((base + 1..base + CHUNK_SIZE).rev().map(|i| i as u32));
+        return base;
+    }
+
+    /// Attaches a component to the given object
+    ///
+    /// *returns the previous component if the object already owned one in this pool, the component
+    /// is then replaced in place; a component left behind by a previous object at the same index is discarded*
+    pub fn insert(&mut self, owner: ObjectRef, comp: TComponent) -> Option<TComponent>
+    {
+        if let Some(index) = self.lookup.get(owner.index()).copied().flatten()
+        {
+            let (o, old) = self.slot_mut(index as usize).replace((owner, comp)).unwrap();
+            if o == owner
+            {
+                return Some(old);
+            }
+            return None;
+        }
+        let index = self.alloc();
+        *self.slot_mut(index) = Some((owner, comp));
+        if self.lookup.len() <= owner.index()
+        {
+            self.lookup.resize(owner.index() + 1, None);
+        }
+        self.lookup[owner.index()] = Some(index as u32);
+        self.len += 1;
+        return None;
+    }
+
+    /// Returns true if the given object owns a component in this pool
+    pub fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return self.slot_index(owner).is_some();
+    }
+
+    /// Returns the component owned by the given object
+    pub fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        let index = self.slot_index(owner)?;
+        return self.slot(index).as_ref().map(|(_, c)| c);
+    }
+
+    /// Returns the component owned by the given object
+    pub fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        let index = self.slot_index(owner)?;
+        return self.slot_mut(index).as_mut().map(|(_, c)| c);
+    }
+
+    /// Removes the component owned by the given object
+    pub fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        let index = self.slot_index(owner)?;
+        let (_, comp) = self.slot_mut(index).take()?;
+        self.lookup[owner.index()] = None;
+        self.free.push(index as u32);
+        self.len -= 1;
+        return Some(comp);
+    }
+
+    /// Returns the number of components in this pool
+    pub fn len(&self) -> usize
+    {
+        return self.len;
+    }
+
+    /// Returns true if this pool holds no component
+    pub fn is_empty(&self) -> bool
+    {
+        return self.len == 0;
+    }
+
+    /// Returns the number of chunks allocated by this pool
+    pub fn chunk_count(&self) -> usize
+    {
+        return self.chunks.len();
+    }
+
+    /// Returns the number of components this pool can hold without allocating a new chunk
+    pub fn capacity(&self) -> usize
+    {
+        return self.chunks.len() * CHUNK_SIZE;
+    }
+
+    /// Iterates over all components with their owning object, walking chunks in allocation order
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.chunks.iter()
+            .flat_map(|c| c.iter())
+            .filter_map(|s| s.as_ref().map(|(o, c)| (*o, c)));
+    }
+
+    /// Iterates mutably over all components with their owning object, walking chunks in allocation order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        return self.chunks.iter_mut()
+            .flat_map(|c| c.iter_mut())
+            .filter_map(|s| s.as_mut().map(|(o, c)| (*o, c)));
+    }
+}
+
+unsafe impl <TComponent: Sized, const CHUNK_SIZE: usize> Storage for ChunkedPool<TComponent, CHUNK_SIZE>
+{
+    type Component = TComponent;
+
+    fn insert(&mut self, owner: ObjectRef, comp: TComponent) -> Result<Option<TComponent>, Error>
+    {
+        return Ok(ChunkedPool::insert(self, owner, comp));
+    }
+
+    fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        return ChunkedPool::remove(self, owner);
+    }
+
+    fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        return ChunkedPool::get(self, owner);
+    }
+
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        return ChunkedPool::get_mut(self, owner);
+    }
+
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut TComponent>
+    {
+        let index = (*self.lookup.get(owner.index())?)? as usize;
+        if index >= self.capacity()
+        {
+            return None;
+        }
+        // Only the slot of the owner is accessed, as components of other owners may be borrowed
+        unsafe
+        {
+            let chunk = self.chunks.as_mut_ptr().add(index / CHUNK_SIZE);
+            let slot = (std::ptr::addr_of_mut!(**chunk) as *mut Option<(ObjectRef, TComponent)>).add(index % CHUNK_SIZE);
+            return match &mut *slot
+            {
+                Some((o, c)) if *o == owner => Some(c),
+                _ => None
+            };
+        }
+    }
+
+    fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return ChunkedPool::contains(self, owner);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return ChunkedPool::iter(self);
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        return ChunkedPool::iter_mut(self);
+    }
+
+    fn len(&self) -> usize
+    {
+        return self.len;
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize, const CHUNK_SIZE: usize> serde::Serialize for ChunkedPool<TComponent, CHUNK_SIZE>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entries(self, serializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <'de, TComponent: serde::Deserialize<'de>, const CHUNK_SIZE: usize> serde::Deserialize<'de> for ChunkedPool<TComponent, CHUNK_SIZE>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        return crate::component::deserialize_entries(deserializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize + serde::de::DeserializeOwned, const CHUNK_SIZE: usize> crate::component::SerializeEntries for ChunkedPool<TComponent, CHUNK_SIZE>
+{
+    fn serialize_entry<S: serde::Serializer>(&self, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entry(self, owner, serializer);
+    }
+
+    fn deserialize_entry<'de, D: serde::Deserializer<'de>>(&mut self, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>
+    {
+        return crate::component::deserialize_entry(self, owner, deserializer);
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::component::Storage;
+use crate::error::Error;
+
+/// Dense vector allocation pool for a given type of component
+///
+/// *components are stored directly at the index of their owning object, suited to components
+/// attached to almost every object; memory grows with the highest object index*
+pub struct DenseVecPool<TComponent: Sized>
+{
+    comps: Vec<Option<(ObjectRef, TComponent)>>,
+    len: usize
+}
+
+impl <TComponent: Sized> Default for DenseVecPool<TComponent>
+{
+    fn default() -> Self
+    {
+        return DenseVecPool::new();
+    }
+}
+
+impl <TComponent: Sized> DenseVecPool<TComponent>
+{
+    pub fn new() -> DenseVecPool<TComponent>
+    {
+        return DenseVecPool
+        {
+            comps: Vec::new(),
+            len: 0
+        };
+    }
+
+    fn entry(&self, owner: ObjectRef) -> Option<&(ObjectRef, TComponent)>
+    {
+        return self.comps.get(owner.index())?.as_ref().filter(|(o, _)| *o == owner);
+    }
+}
+
+unsafe impl <TComponent: Sized> Storage for DenseVecPool<TComponent>
+{
+    type Component = TComponent;
+
+    fn insert(&mut self, owner: ObjectRef, comp: TComponent) -> Result<Option<TComponent>, Error>
+    {
+        if self.comps.len() <= owner.index()
+        {
+            self.comps.resize_with(owner.index() + 1, || None);
+        }
+        return match self.comps[owner.index()].replace((owner, comp))
+        {
+            Some((o, c)) if o == owner => Ok(Some(c)),
+            // A component left behind by a previous object at the same index is discarded
+            Some(_) => Ok(None),
+            None =>
+            {
+                self.len += 1;
+                Ok(None)
+            }
+        };
+    }
+
+    fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        self.entry(owner)?;
+        self.len -= 1;
+        return self.comps[owner.index()].take().map(|(_, c)| c);
+    }
+
+    fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        return self.entry(owner).map(|(_, c)| c);
+    }
+
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        return self.comps.get_mut(owner.index())?.as_mut()
+            .filter(|(o, _)| *o == owner)
+            .map(|(_, c)| c);
+    }
+
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut TComponent>
+    {
+        if owner.index() >= self.comps.len()
+        {
+            return None;
+        }
+        // Only the entry of the owner is accessed, as components of other owners may be borrowed
+        unsafe
+        {
+            return match &mut *self.comps.as_mut_ptr().add(owner.index())
+            {
+                Some((o, c)) if *o == owner => Some(c),
+                _ => None
+            };
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.comps.iter().filter_map(|v| v.as_ref().map(|(o, c)| (*o, c)));
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        return self.comps.iter_mut().filter_map(|v| v.as_mut().map(|(o, c)| (*o, c)));
+    }
+
+    fn len(&self) -> usize
+    {
+        return self.len;
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize> serde::Serialize for DenseVecPool<TComponent>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entries(self, serializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <'de, TComponent: serde::Deserialize<'de>> serde::Deserialize<'de> for DenseVecPool<TComponent>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        return crate::component::deserialize_entries(deserializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize + serde::de::DeserializeOwned> crate::component::SerializeEntries for DenseVecPool<TComponent>
+{
+    fn serialize_entry<S: serde::Serializer>(&self, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entry(self, owner, serializer);
+    }
+
+    fn deserialize_entry<'de, D: serde::Deserializer<'de>>(&mut self, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>
+    {
+        return crate::component::deserialize_entry(self, owner, deserializer);
+    }
+}
@@ -0,0 +1,911 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::alloc::Layout;
+use std::any::TypeId;
+use std::any::type_name;
+use std::collections::HashMap;
+use std::ptr::NonNull;
+use std::marker::PhantomData;
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::error::Error;
+use crate::component::Storage;
+
+/// Function dropping a component in place
+pub type DropFn = unsafe fn(*mut u8);
+
+/// Function cloning the component at the first address into the uninitialized memory at the second address
+pub type CloneFn = unsafe fn(*const u8, *mut u8);
+
+unsafe fn drop_impl<T>(ptr: *mut u8)
+{
+    std::ptr::drop_in_place(ptr as *mut T);
+}
+
+/// Function encoding the component at the given address
+pub type EncodeFn = unsafe fn(*const u8) -> Result<Vec<u8>, String>;
+
+/// Function decoding a component into the uninitialized memory at the given address
+pub type DecodeFn = unsafe fn(&[u8], *mut u8) -> Result<(), String>;
+
+unsafe fn clone_impl<T: Clone>(src: *const u8, dst: *mut u8)
+{
+    std::ptr::write(dst as *mut T, (*(src as *const T)).clone());
+}
+
+#[cfg(feature = "snapshot")]
+unsafe fn encode_impl<T: serde::Serialize>(src: *const u8) -> Result<Vec<u8>, String>
+{
+    return bincode::serialize(&*(src as *const T)).map_err(|e| e.to_string());
+}
+
+#[cfg(feature = "snapshot")]
+unsafe fn decode_impl<T: serde::de::DeserializeOwned>(data: &[u8], dst: *mut u8) -> Result<(), String>
+{
+    let comp: T = bincode::deserialize(data).map_err(|e| e.to_string())?;
+    std::ptr::write(dst as *mut T, comp);
+    return Ok(());
+}
+
+/// Describes a dynamic component type: name, memory layout, drop, clone and codec functions
+#[derive(Clone, Debug)]
+pub struct ComponentInfo
+{
+    name: String,
+    type_id: Option<TypeId>,
+    layout: Layout,
+    drop: Option<DropFn>,
+    clone: Option<CloneFn>,
+    codec: Option<(EncodeFn, DecodeFn)>
+}
+
+impl ComponentInfo
+{
+    /// Describes the Rust type T
+    pub fn of<T: 'static>(name: &str) -> ComponentInfo
+    {
+        return ComponentInfo
+        {
+            name: String::from(name),
+            type_id: Some(TypeId::of::<T>()),
+            layout: Layout::new::<T>(),
+            drop: Some(drop_impl::<T> as DropFn).filter(|_| std::mem::needs_drop::<T>()),
+            clone: None,
+            codec: None
+        };
+    }
+
+    /// Describes the Rust type T, components can be cloned
+    pub fn of_clone<T: 'static + Clone>(name: &str) -> ComponentInfo
+    {
+        let mut info = ComponentInfo::of::<T>(name);
+        info.clone = Some(clone_impl::<T>);
+        return info;
+    }
+
+    /// Describes a foreign type which only exists at runtime (script, editor...)
+    ///
+    /// # Safety
+    ///
+    /// The drop and clone functions must be valid for any component of the given layout stored in a pool.
+    pub unsafe fn new(name: &str, layout: Layout, drop: Option<DropFn>, clone: Option<CloneFn>) -> ComponentInfo
+    {
+        return ComponentInfo
+        {
+            name: String::from(name),
+            type_id: None,
+            layout: layout.pad_to_align(),
+            drop,
+            clone,
+            codec: None
+        };
+    }
+
+    /// Lets components of the described Rust type T be saved with the component manager
+    ///
+    /// *types without a codec are transient, their components are never saved*
+    ///
+    /// *panics if T is not the described type*
+    #[cfg(feature = "snapshot")]
+    pub fn serializable<T: 'static + serde::Serialize + serde::de::DeserializeOwned>(mut self) -> ComponentInfo
+    {
+        assert!(self.type_id == Some(TypeId::of::<T>()), "{} is not the type of component {}", type_name::<T>(), self.name);
+        self.codec = Some((encode_impl::<T>, decode_impl::<T>));
+        return self;
+    }
+
+    /// Returns the registered name of this type
+    pub fn name(&self) -> &str
+    {
+        return &self.name;
+    }
+
+    /// Returns the Rust type identifier, None for foreign types
+    pub fn type_id(&self) -> Option<TypeId>
+    {
+        return self.type_id;
+    }
+
+    /// Returns the memory layout of a single component
+    pub fn layout(&self) -> Layout
+    {
+        return self.layout;
+    }
+
+    /// Returns true if components of this type can be cloned
+    pub fn is_clone(&self) -> bool
+    {
+        return self.clone.is_some();
+    }
+
+    /// Returns true if components of this type are saved with the component manager
+    pub fn is_serializable(&self) -> bool
+    {
+        return self.codec.is_some();
+    }
+}
+
+/// Type-erased allocation pool for a dynamic component type
+///
+/// *components are packed in a single buffer for iteration; a sparse index keyed by object makes
+/// lookup, insertion and removal O(1), removal moves the last component into the hole*
+pub struct DynamicPool
+{
+    info: ComponentInfo,
+    data: NonNull<u8>,
+    capacity: usize,
+    sparse: Vec<Option<u32>>,
+    owners: Vec<ObjectRef>
+}
+
+impl DynamicPool
+{
+    pub fn new(info: ComponentInfo) -> DynamicPool
+    {
+        // Zero-sized components never allocate, an aligned dangling address is enough
+        let capacity = match info.layout.size()
+        {
+            0 => usize::MAX,
+            _ => 0
+        };
+        return DynamicPool
+        {
+            data: NonNull::new(info.layout.align() as *mut u8).unwrap(),
+            info,
+            capacity,
+            sparse: Vec::new(),
+            owners: Vec::new()
+        };
+    }
+
+    /// Returns the type description of this pool
+    pub fn info(&self) -> &ComponentInfo
+    {
+        return &self.info;
+    }
+
+    fn ptr_at(&self, index: usize) -> *mut u8
+    {
+        // The buffer always holds capacity elements, index is checked by callers
+        return unsafe { self.data.as_ptr().add(index * self.info.layout.size()) };
+    }
+
+    fn buffer_layout(&self, capacity: usize) -> Option<Layout>
+    {
+        let size = self.info.layout.size().checked_mul(capacity)?;
+        return Layout::from_size_align(size, self.info.layout.align()).ok();
+    }
+
+    /// Makes sure the slot after the last component, the spare slot, is allocated
+    fn reserve_one(&mut self) -> Result<(), Error>
+    {
+        if self.owners.len() < self.capacity
+        {
+            return Ok(());
+        }
+        let layout = self.capacity.checked_mul(2)
+            .map(|capacity| std::cmp::max(4, capacity))
+            .and_then(|capacity| Some((capacity, self.buffer_layout(capacity)?)));
+        let (capacity, layout) = layout.ok_or_else(|| Error::PoolOverflow(self.info.name.clone()))?;
+        let ptr = unsafe
+        {
+            match self.capacity
+            {
+                0 => std::alloc::alloc(layout),
+                // The current buffer was allocated with this layout, it was checked at that time
+                _ => std::alloc::realloc(self.data.as_ptr(), self.buffer_layout(self.capacity).unwrap(), layout.size())
+            }
+        };
+        self.data = NonNull::new(ptr).unwrap_or_else(|| std::alloc::handle_alloc_error(layout));
+        self.capacity = capacity;
+        return Ok(());
+    }
+
+    fn dense_index(&self, owner: ObjectRef) -> Option<usize>
+    {
+        let index = (*self.sparse.get(owner.index())?)? as usize;
+        if self.owners[index] != owner
+        {
+            return None;
+        }
+        return Some(index);
+    }
+
+    fn is<T: 'static>(&self) -> bool
+    {
+        return self.info.type_id == Some(TypeId::of::<T>());
+    }
+
+    unsafe fn drop_at(&self, index: usize)
+    {
+        if let Some(drop) = self.info.drop
+        {
+            drop(self.ptr_at(index));
+        }
+    }
+
+    /// Moves the component built in the spare slot to the given index and drops the component it replaces
+    ///
+    /// *the old component is dropped last, from the spare slot, so a panicking drop never leaves
+    /// a dropped component registered*
+    unsafe fn replace_with_spare(&mut self, index: usize)
+    {
+        let spare = self.owners.len();
+        std::ptr::swap_nonoverlapping(self.ptr_at(index), self.ptr_at(spare), self.info.layout.size());
+        self.drop_at(spare);
+    }
+
+    /// Removes the component left behind by a previous object at the same index
+    fn discard_stale(&mut self, owner: ObjectRef)
+    {
+        if let Some(index) = self.sparse.get(owner.index()).copied().flatten()
+        {
+            let stale = self.owners[index as usize];
+            if stale != owner
+            {
+                self.remove(stale);
+            }
+        }
+    }
+
+    /// Registers the component built in the spare slot for the given owner, replacing its previous component
+    unsafe fn commit_spare(&mut self, owner: ObjectRef)
+    {
+        match self.dense_index(owner)
+        {
+            Some(index) => self.replace_with_spare(index),
+            None =>
+            {
+                if self.sparse.len() <= owner.index()
+                {
+                    self.sparse.resize(owner.index() + 1, None);
+                }
+                self.sparse[owner.index()] = Some(self.owners.len() as u32);
+                self.owners.push(owner);
+            }
+        }
+    }
+
+    /// Attaches a component to the given object by moving the bytes at the given address into this pool,
+    /// the previous component, if any, is dropped
+    ///
+    /// *returns an error if the pool cannot grow, the source is then left untouched*
+    ///
+    /// # Safety
+    ///
+    /// The source must point to a valid component of the type of this pool; on success ownership is
+    /// transferred to the pool, so the source must not be dropped or used afterwards.
+    pub unsafe fn insert_raw(&mut self, owner: ObjectRef, src: *const u8) -> Result<(), Error>
+    {
+        self.reserve_one()?;
+        self.discard_stale(owner);
+        std::ptr::copy_nonoverlapping(src, self.ptr_at(self.owners.len()), self.info.layout.size());
+        self.commit_spare(owner);
+        return Ok(());
+    }
+
+    /// Attaches a component to the given object, the previous component, if any, is dropped
+    ///
+    /// *returns an error if T is not the type of this pool or if the pool cannot grow*
+    pub fn insert<T: 'static>(&mut self, owner: ObjectRef, comp: T) -> Result<(), Error>
+    {
+        if !self.is::<T>()
+        {
+            return Err(Error::ComponentTypeMismatch(type_name::<T>()));
+        }
+        let comp = std::mem::ManuallyDrop::new(comp);
+        if let Err(e) = unsafe { self.insert_raw(owner, &*comp as *const T as *const u8) }
+        {
+            drop(std::mem::ManuallyDrop::into_inner(comp));
+            return Err(e);
+        }
+        return Ok(());
+    }
+
+    /// Returns true if the given object owns a component in this pool
+    pub fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return self.dense_index(owner).is_some();
+    }
+
+    /// Returns the address of the component owned by the given object
+    pub fn get_raw(&self, owner: ObjectRef) -> Option<*const u8>
+    {
+        return self.dense_index(owner).map(|i| self.ptr_at(i) as *const u8);
+    }
+
+    /// Returns the address of the component owned by the given object
+    pub fn get_raw_mut(&mut self, owner: ObjectRef) -> Option<*mut u8>
+    {
+        return self.dense_index(owner).map(|i| self.ptr_at(i));
+    }
+
+    /// Returns the component owned by the given object, None if T is not the type of this pool
+    pub fn get<T: 'static>(&self, owner: ObjectRef) -> Option<&T>
+    {
+        if !self.is::<T>()
+        {
+            return None;
+        }
+        return self.get_raw(owner).map(|p| unsafe { &*(p as *const T) });
+    }
+
+    /// Returns the component owned by the given object, None if T is not the type of this pool
+    pub fn get_mut<T: 'static>(&mut self, owner: ObjectRef) -> Option<&mut T>
+    {
+        if !self.is::<T>()
+        {
+            return None;
+        }
+        return self.get_raw_mut(owner).map(|p| unsafe { &mut *(p as *mut T) });
+    }
+
+    // Swaps the component at the given index with the last one and unregisters it, the component
+    // then sits in the spare slot where the caller drops or moves it out
+    fn forget_at(&mut self, owner: ObjectRef, index: usize)
+    {
+        let last = self.owners.len() - 1;
+        if index != last
+        {
+            unsafe { std::ptr::swap_nonoverlapping(self.ptr_at(last), self.ptr_at(index), self.info.layout.size()) };
+        }
+        self.sparse[owner.index()] = None;
+        self.owners.swap_remove(index);
+        if let Some(moved) = self.owners.get(index)
+        {
+            self.sparse[moved.index()] = Some(index as u32);
+        }
+    }
+
+    /// Removes and drops the component owned by the given object, returns false if there was none
+    pub fn remove(&mut self, owner: ObjectRef) -> bool
+    {
+        let index = match self.dense_index(owner)
+        {
+            Some(index) => index,
+            None => return false
+        };
+        self.forget_at(owner, index);
+        unsafe { self.drop_at(self.owners.len()) };
+        return true;
+    }
+
+    /// Removes the component owned by the given object and returns it, None if T is not the type of this pool
+    pub fn take<T: 'static>(&mut self, owner: ObjectRef) -> Option<T>
+    {
+        if !self.is::<T>()
+        {
+            return None;
+        }
+        let index = self.dense_index(owner)?;
+        self.forget_at(owner, index);
+        return Some(unsafe { std::ptr::read(self.ptr_at(self.owners.len()) as *const T) });
+    }
+
+    /// Clones the component of the first object and attaches the copy to the second object
+    ///
+    /// *returns false if the source object owns no component or if the type cannot be cloned,
+    /// returns an error if the pool cannot grow*
+    pub fn clone_component(&mut self, from: ObjectRef, to: ObjectRef) -> Result<bool, Error>
+    {
+        let clone = match self.info.clone
+        {
+            Some(clone) => clone,
+            None => return Ok(false)
+        };
+        // A reference sharing the index of the source is either the source itself or stale
+        if !self.contains(from) || from.index() == to.index()
+        {
+            return Ok(from == to);
+        }
+        self.reserve_one()?;
+        self.discard_stale(to);
+        let src = self.ptr_at(self.dense_index(from).unwrap());
+        // The copy is built in the spare slot so that a panicking clone leaves the pool untouched
+        unsafe
+        {
+            clone(src, self.ptr_at(self.owners.len()));
+            self.commit_spare(to);
+        }
+        return Ok(true);
+    }
+
+    /// Clones the component of the given object and attaches the copy to an object of another pool of the same type
+    ///
+    /// *returns false if the source object owns no component, if the type cannot be cloned or if the pools
+    /// do not hold the same type, returns an error if the other pool cannot grow*
+    pub fn clone_into(&self, from: ObjectRef, other: &mut DynamicPool, to: ObjectRef) -> Result<bool, Error>
+    {
+        let clone = match self.info.clone
+        {
+            Some(clone) => clone,
+            None => return Ok(false)
+        };
+        if other.info.name != self.info.name || other.info.type_id != self.info.type_id || other.info.layout != self.info.layout
+        {
+            return Ok(false);
+        }
+        let index = match self.dense_index(from)
+        {
+            Some(index) => index,
+            None => return Ok(false)
+        };
+        other.reserve_one()?;
+        other.discard_stale(to);
+        unsafe
+        {
+            clone(self.ptr_at(index), other.ptr_at(other.owners.len()));
+            other.commit_spare(to);
+        }
+        return Ok(true);
+    }
+
+    /// Returns the number of components in this pool
+    pub fn len(&self) -> usize
+    {
+        return self.owners.len();
+    }
+
+    /// Returns true if this pool holds no component
+    pub fn is_empty(&self) -> bool
+    {
+        return self.owners.is_empty();
+    }
+
+    /// Returns the packed list of owners
+    pub fn owners(&self) -> &[ObjectRef]
+    {
+        return &self.owners;
+    }
+
+    /// Iterates over all components with their owning object, None if T is not the type of this pool
+    pub fn iter<T: 'static>(&self) -> Option<impl Iterator<Item = (ObjectRef, &T)>>
+    {
+        if !self.is::<T>()
+        {
+            return None;
+        }
+        return Some(self.owners.iter().enumerate().map(move |(i, o)| (*o, unsafe { &*(self.ptr_at(i) as *const T) })));
+    }
+
+    /// Iterates mutably over all components with their owning object, None if T is not the type of this pool
+    pub fn iter_mut<T: 'static>(&mut self) -> Option<impl Iterator<Item = (ObjectRef, &mut T)>>
+    {
+        if !self.is::<T>()
+        {
+            return None;
+        }
+        let data = self.data.as_ptr();
+        let size = self.info.layout.size();
+        // Each index is yielded once, so the references never alias
+        return Some(self.owners.iter().enumerate().map(move |(i, o)| (*o, unsafe { &mut *(data.add(i * size) as *mut T) })));
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Result<Option<SavedPool>, Error>
+    {
+        let encode = match self.info.codec
+        {
+            Some((encode, _)) => encode,
+            None => return Ok(None)
+        };
+        let mut entries = Vec::with_capacity(self.owners.len());
+        for (i, owner) in self.owners.iter().enumerate()
+        {
+            let data = unsafe { encode(self.ptr_at(i)) }.map_err(|e| Error::InvalidComponentData(self.info.name.clone(), e))?;
+            entries.push((*owner, data));
+        }
+        return Ok(Some(SavedPool
+        {
+            name: self.info.name.clone(),
+            entries
+        }));
+    }
+
+    fn load(&mut self, saved: &SavedPool) -> Result<(), Error>
+    {
+        let invalid = |reason: String| Error::InvalidComponentData(saved.name.clone(), reason);
+        let decode = match self.info.codec
+        {
+            Some((_, decode)) => decode,
+            None => return Err(invalid(String::from("the type has no codec")))
+        };
+        for (owner, data) in &saved.entries
+        {
+            if self.sparse.get(owner.index()).copied().flatten().is_some()
+            {
+                return Err(invalid(format!("duplicate owner {}", owner)));
+            }
+            self.reserve_one()?;
+            unsafe
+            {
+                decode(data, self.ptr_at(self.owners.len())).map_err(invalid)?;
+                self.commit_spare(*owner);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Returns a typed view of this pool, None if T is not the type of this pool
+    pub fn typed<T: 'static>(&mut self) -> Option<TypedPool<'_, T>>
+    {
+        if !self.is::<T>()
+        {
+            return None;
+        }
+        return Some(TypedPool
+        {
+            pool: self,
+            useless: PhantomData
+        });
+    }
+}
+
+impl Drop for DynamicPool
+{
+    fn drop(&mut self)
+    {
+        for i in 0..self.owners.len()
+        {
+            unsafe { self.drop_at(i) };
+        }
+        if self.info.layout.size() > 0 && self.capacity > 0
+        {
+            // The current buffer was allocated with this layout, it was checked at that time
+            let layout = self.buffer_layout(self.capacity).unwrap();
+            unsafe { std::alloc::dealloc(self.data.as_ptr(), layout) };
+        }
+    }
+}
+
+/// Typed view of a [DynamicPool](DynamicPool), lets runtime-defined components of a Rust type
+/// be used wherever a [Storage](Storage) is expected, queries included
+///
+/// *Storage::insert returns Error::PoolOverflow if the pool cannot grow, the previous component is then kept*
+pub struct TypedPool<'a, T>
+{
+    pool: &'a mut DynamicPool,
+    useless: PhantomData<&'a mut T>
+}
+
+unsafe impl <'a, T: 'static> Storage for TypedPool<'a, T>
+{
+    type Component = T;
+
+    fn insert(&mut self, owner: ObjectRef, comp: T) -> Result<Option<T>, Error>
+    {
+        // Room is made first so that the previous component is only taken once the insertion cannot fail
+        self.pool.reserve_one()?;
+        let old = self.pool.take::<T>(owner);
+        self.pool.insert(owner, comp)?;
+        return Ok(old);
+    }
+
+    fn remove(&mut self, owner: ObjectRef) -> Option<T>
+    {
+        return self.pool.take::<T>(owner);
+    }
+
+    fn get(&self, owner: ObjectRef) -> Option<&T>
+    {
+        return self.pool.get::<T>(owner);
+    }
+
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut T>
+    {
+        return self.pool.get_mut::<T>(owner);
+    }
+
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut T>
+    {
+        return self.pool.get_raw_mut(owner).map(|p| p as *mut T);
+    }
+
+    fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return self.pool.contains(owner);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &T)>
+    {
+        return self.pool.iter::<T>().into_iter().flatten();
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut T)>
+    {
+        return self.pool.iter_mut::<T>().into_iter().flatten();
+    }
+
+    fn len(&self) -> usize
+    {
+        return self.pool.len();
+    }
+}
+
+/// Handle to a dynamic component type registered in [DynamicComponents](DynamicComponents)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ComponentId(u32);
+
+impl ComponentId
+{
+    /// Returns the index of this component type in its registry
+    pub fn index(&self) -> usize
+    {
+        return self.0 as usize;
+    }
+}
+
+/// Registry of component types defined at runtime, each type owns one [DynamicPool](DynamicPool)
+///
+/// *types are looked up either by TypeId, for Rust types, or by their registered name*
+///
+/// *types are not serialized, only components of types with a codec are: a deserialized registry
+/// keeps the components of each type until the type is registered again; snapshots save them,
+/// deltas do not cover runtime-defined components*
+#[derive(Default)]
+pub struct DynamicComponents
+{
+    pools: Vec<DynamicPool>,
+    by_type: HashMap<TypeId, ComponentId>,
+    by_name: HashMap<String, ComponentId>,
+    pending: Vec<SavedPool>
+}
+
+/// Encoded components of a single dynamic component type
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct SavedPool
+{
+    name: String,
+    entries: Vec<(ObjectRef, Vec<u8>)>
+}
+
+impl DynamicComponents
+{
+    pub fn new() -> DynamicComponents
+    {
+        return DynamicComponents
+        {
+            pools: Vec::new(),
+            by_type: HashMap::new(),
+            by_name: HashMap::new(),
+            pending: Vec::new()
+        };
+    }
+
+    /// Registers a new component type and creates its pool, components of this type waiting
+    /// since deserialization are decoded into the new pool
+    ///
+    /// *returns an error if the name or the Rust type is already registered, or if the waiting
+    /// components cannot be decoded; the type is then left unregistered*
+    pub fn register(&mut self, info: ComponentInfo) -> Result<ComponentId, Error>
+    {
+        if self.by_name.contains_key(info.name()) || info.type_id.map(|t| self.by_type.contains_key(&t)).unwrap_or(false)
+        {
+            return Err(Error::DuplicateComponent(String::from(info.name())));
+        }
+        let mut pool = DynamicPool::new(info);
+        if let Some(pos) = self.pending.iter().position(|saved| saved.name == pool.info.name)
+        {
+            pool.load(&self.pending[pos])?;
+            self.pending.remove(pos);
+        }
+        let info = &pool.info;
+        let id = ComponentId(self.pools.len() as u32);
+        if let Some(t) = info.type_id
+        {
+            self.by_type.insert(t, id);
+        }
+        self.by_name.insert(String::from(info.name()), id);
+        self.pools.push(pool);
+        return Ok(id);
+    }
+
+    /// Iterates over the names of deserialized types which are not registered yet
+    pub fn unclaimed(&self) -> impl Iterator<Item = &str>
+    {
+        return self.pending.iter().map(|saved| &*saved.name);
+    }
+
+    /// Returns the identifier of the Rust type T
+    pub fn id_of<T: 'static>(&self) -> Option<ComponentId>
+    {
+        return self.by_type.get(&TypeId::of::<T>()).copied();
+    }
+
+    /// Returns the identifier of the type registered with the given name
+    pub fn id_by_name(&self, name: &str) -> Option<ComponentId>
+    {
+        return self.by_name.get(name).copied();
+    }
+
+    /// Returns the pool of the given type
+    pub fn pool(&self, id: ComponentId) -> Option<&DynamicPool>
+    {
+        return self.pools.get(id.index());
+    }
+
+    /// Returns the pool of the given type
+    pub fn pool_mut(&mut self, id: ComponentId) -> Option<&mut DynamicPool>
+    {
+        return self.pools.get_mut(id.index());
+    }
+
+    /// Returns the pool of the Rust type T
+    pub fn pool_of<T: 'static>(&self) -> Option<&DynamicPool>
+    {
+        return self.id_of::<T>().map(|id| &self.pools[id.index()]);
+    }
+
+    /// Returns the pool of the Rust type T
+    pub fn pool_of_mut<T: 'static>(&mut self) -> Option<&mut DynamicPool>
+    {
+        return self.id_of::<T>().map(move |id| &mut self.pools[id.index()]);
+    }
+
+    /// Returns the pool of the type registered with the given name
+    pub fn pool_by_name(&self, name: &str) -> Option<&DynamicPool>
+    {
+        return self.id_by_name(name).map(|id| &self.pools[id.index()]);
+    }
+
+    /// Returns the pool of the type registered with the given name
+    pub fn pool_by_name_mut(&mut self, name: &str) -> Option<&mut DynamicPool>
+    {
+        return self.id_by_name(name).map(move |id| &mut self.pools[id.index()]);
+    }
+
+    /// Iterates over all registered pools
+    pub fn pools(&self) -> impl Iterator<Item = (ComponentId, &DynamicPool)>
+    {
+        return self.pools.iter().enumerate().map(|(i, p)| (ComponentId(i as u32), p));
+    }
+
+    /// Removes all dynamic components attached to the given object
+    pub fn clear(&mut self, target: ObjectRef)
+    {
+        for pool in &mut self.pools
+        {
+            pool.remove(target);
+        }
+    }
+
+    /// Decodes deserialized components into new pools for each registered type with a codec,
+    /// without modifying this registry
+    #[cfg(feature = "snapshot")]
+    pub(crate) fn prepare_restore(&self, saved: DynamicComponents) -> Result<Restore, Error>
+    {
+        let mut pending = saved.pending;
+        let mut pools = Vec::new();
+        for (i, pool) in self.pools.iter().enumerate()
+        {
+            if !pool.info.is_serializable()
+            {
+                continue;
+            }
+            let mut restored = DynamicPool::new(pool.info.clone());
+            if let Some(pos) = pending.iter().position(|saved| saved.name == pool.info.name)
+            {
+                restored.load(&pending.remove(pos))?;
+            }
+            pools.push((ComponentId(i as u32), restored));
+        }
+        return Ok(Restore
+        {
+            pools,
+            pending
+        });
+    }
+
+    /// Replaces the pools of all types with a codec by the pools decoded in
+    /// [prepare_restore](DynamicComponents::prepare_restore)
+    #[cfg(feature = "snapshot")]
+    pub(crate) fn commit_restore(&mut self, restore: Restore)
+    {
+        for (id, pool) in restore.pools
+        {
+            self.pools[id.index()] = pool;
+        }
+        self.pending = restore.pending;
+    }
+}
+
+/// Decoded state of a registry, ready to replace the serializable pools of the registry it was decoded for
+#[cfg(feature = "snapshot")]
+pub(crate) struct Restore
+{
+    pools: Vec<(ComponentId, DynamicPool)>,
+    pending: Vec<SavedPool>
+}
+
+/// *components of types without a codec are not serialized, components of types which were not
+/// registered again since deserialization are serialized as is*
+#[cfg(feature = "serde")]
+impl serde::Serialize for DynamicComponents
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        use serde::ser::Error;
+        use serde::ser::SerializeSeq;
+        let mut saved = Vec::new();
+        for pool in &self.pools
+        {
+            if let Some(data) = pool.save().map_err(S::Error::custom)?
+            {
+                saved.push(data);
+            }
+        }
+        let mut seq = serializer.serialize_seq(Some(saved.len() + self.pending.len()))?;
+        for data in saved.iter().chain(&self.pending)
+        {
+            seq.serialize_element(data)?;
+        }
+        return seq.end();
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <'de> serde::Deserialize<'de> for DynamicComponents
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        use serde::de::Error;
+        let pending: Vec<SavedPool> = Vec::deserialize(deserializer)?;
+        for (i, saved) in pending.iter().enumerate()
+        {
+            if pending[..i].iter().any(|other| other.name == saved.name)
+            {
+                return Err(D::Error::custom(format!("duplicate component type {}", saved.name)));
+            }
+        }
+        let mut components = DynamicComponents::new();
+        components.pending = pending;
+        return Ok(components);
+    }
+}
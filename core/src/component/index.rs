@@ -0,0 +1,282 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::ops::RangeBounds;
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::error::Error;
+
+/// Map from index keys to the objects owning a component with that key
+///
+/// *implemented by HashMap for hash indexes and BTreeMap for ordered indexes*
+pub trait KeyMap: Default
+{
+    type Key: Clone;
+
+    fn owners(&self, key: &Self::Key) -> &[ObjectRef];
+
+    fn insert(&mut self, key: Self::Key, owner: ObjectRef);
+
+    fn remove(&mut self, key: &Self::Key, owner: ObjectRef);
+}
+
+impl <TKey: Clone + Eq + Hash> KeyMap for HashMap<TKey, Vec<ObjectRef>>
+{
+    type Key = TKey;
+
+    fn owners(&self, key: &TKey) -> &[ObjectRef]
+    {
+        return self.get(key).map(|v| v.as_slice()).unwrap_or(&[]);
+    }
+
+    fn insert(&mut self, key: TKey, owner: ObjectRef)
+    {
+        self.entry(key).or_default().push(owner);
+    }
+
+    fn remove(&mut self, key: &TKey, owner: ObjectRef)
+    {
+        if let Some(owners) = self.get_mut(key)
+        {
+            owners.retain(|o| *o != owner);
+            if owners.is_empty()
+            {
+                HashMap::remove(self, key);
+            }
+        }
+    }
+}
+
+impl <TKey: Clone + Ord> KeyMap for BTreeMap<TKey, Vec<ObjectRef>>
+{
+    type Key = TKey;
+
+    fn owners(&self, key: &TKey) -> &[ObjectRef]
+    {
+        return self.get(key).map(|v| v.as_slice()).unwrap_or(&[]);
+    }
+
+    fn insert(&mut self, key: TKey, owner: ObjectRef)
+    {
+        self.entry(key).or_default().push(owner);
+    }
+
+    fn remove(&mut self, key: &TKey, owner: ObjectRef)
+    {
+        if let Some(owners) = self.get_mut(key)
+        {
+            owners.retain(|o| *o != owner);
+            if owners.is_empty()
+            {
+                BTreeMap::remove(self, key);
+            }
+        }
+    }
+}
+
+/// Function extracting the key of a component
+pub type KeyFn<TComponent, TKey> = Box<dyn Fn(&TComponent) -> TKey + Send + Sync>;
+
+/// Secondary index over the components of a [ComponentPool](crate::component::ComponentPool),
+/// mapping a key extracted from each component to the objects owning it
+///
+/// *see [HashIndex](HashIndex) and [OrderedIndex](OrderedIndex)*
+pub struct Index<TComponent, TMap: KeyMap>
+{
+    name: &'static str,
+    key: KeyFn<TComponent, TMap::Key>,
+    unique: bool,
+    keys: HashMap<ObjectRef, TMap::Key>,
+    entries: TMap
+}
+
+/// Index looking up components by hashed key
+pub type HashIndex<TComponent, TKey> = Index<TComponent, HashMap<TKey, Vec<ObjectRef>>>;
+
+/// Index looking up components by key, which also supports range queries
+pub type OrderedIndex<TComponent, TKey> = Index<TComponent, BTreeMap<TKey, Vec<ObjectRef>>>;
+
+impl <TComponent, TMap: KeyMap> Index<TComponent, TMap>
+{
+    /// Creates an index given its name and the function extracting the key of a component
+    pub fn new<F: 'static + Send + Sync + Fn(&TComponent) -> TMap::Key>(name: &'static str, key: F) -> Index<TComponent, TMap>
+    {
+        return Index
+        {
+            name,
+            key: Box::new(key),
+            unique: false,
+            keys: HashMap::new(),
+            entries: TMap::default()
+        };
+    }
+
+    /// Rejects components whose key is already used by another object
+    pub fn unique(mut self) -> Self
+    {
+        self.unique = true;
+        return self;
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        return self.name;
+    }
+
+    pub fn is_unique(&self) -> bool
+    {
+        return self.unique;
+    }
+
+    /// Returns the objects owning a component with the given key
+    pub fn get(&self, key: &TMap::Key) -> &[ObjectRef]
+    {
+        return self.entries.owners(key);
+    }
+
+    /// Returns the first object owning a component with the given key
+    pub fn get_one(&self, key: &TMap::Key) -> Option<ObjectRef>
+    {
+        return self.entries.owners(key).first().copied();
+    }
+
+    /// Returns the key under which the component of the given object is indexed
+    pub fn key_of(&self, owner: ObjectRef) -> Option<&TMap::Key>
+    {
+        return self.keys.get(&owner);
+    }
+
+    /// Returns the number of indexed components
+    pub fn len(&self) -> usize
+    {
+        return self.keys.len();
+    }
+
+    /// Returns true if no component is indexed
+    pub fn is_empty(&self) -> bool
+    {
+        return self.keys.is_empty();
+    }
+}
+
+impl <TComponent, TKey: Clone + Ord> OrderedIndex<TComponent, TKey>
+{
+    /// Iterates in key order over the objects whose component key lies in the given range
+    pub fn range<R: RangeBounds<TKey>>(&self, range: R) -> impl Iterator<Item = (&TKey, ObjectRef)>
+    {
+        return self.entries.range(range).flat_map(|(k, owners)| owners.iter().map(move |o| (k, *o)));
+    }
+
+    /// Iterates in key order over all indexed objects
+    pub fn iter(&self) -> impl Iterator<Item = (&TKey, ObjectRef)>
+    {
+        return self.range(..);
+    }
+}
+
+/// Type-erased index stored in a pool
+pub(crate) trait AnyIndex<TComponent>
+{
+    fn name(&self) -> &'static str;
+
+    /// Returns an error if indexing the given component under the given object breaks a unique constraint
+    fn check(&self, owner: ObjectRef, comp: &TComponent) -> Result<(), Error>;
+
+    /// Indexes the given component, replacing the previous key of the object
+    fn insert(&mut self, owner: ObjectRef, comp: &TComponent);
+
+    fn remove(&mut self, owner: ObjectRef);
+
+    /// Removes all indexed components
+    fn clear(&mut self);
+
+    /// Returns the owners of the given key, None if the key is not of the type of this index
+    fn lookup(&self, key: &dyn Any) -> Option<&[ObjectRef]>;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl <TComponent: 'static, TMap: 'static + KeyMap> AnyIndex<TComponent> for Index<TComponent, TMap>
+    where TMap::Key: 'static
+{
+    fn name(&self) -> &'static str
+    {
+        return self.name;
+    }
+
+    fn check(&self, owner: ObjectRef, comp: &TComponent) -> Result<(), Error>
+    {
+        if !self.unique
+        {
+            return Ok(());
+        }
+        let key = (self.key)(comp);
+        return match self.entries.owners(&key).iter().find(|o| **o != owner)
+        {
+            Some(other) => Err(Error::UniqueViolation(String::from(self.name), *other)),
+            None => Ok(())
+        };
+    }
+
+    fn insert(&mut self, owner: ObjectRef, comp: &TComponent)
+    {
+        AnyIndex::remove(self, owner);
+        let key = (self.key)(comp);
+        self.entries.insert(key.clone(), owner);
+        self.keys.insert(owner, key);
+    }
+
+    fn remove(&mut self, owner: ObjectRef)
+    {
+        if let Some(key) = self.keys.remove(&owner)
+        {
+            self.entries.remove(&key, owner);
+        }
+    }
+
+    fn clear(&mut self)
+    {
+        self.keys.clear();
+        self.entries = TMap::default();
+    }
+
+    fn lookup(&self, key: &dyn Any) -> Option<&[ObjectRef]>
+    {
+        return key.downcast_ref().map(|k| self.entries.owners(k));
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        return self;
+    }
+}
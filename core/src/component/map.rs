@@ -0,0 +1,254 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::collections::HashMap;
+use std::collections::BTreeMap;
+
+use crate::object::ObjectRef;
+use crate::component::Storage;
+use crate::error::Error;
+
+/// Hash map allocation pool for a given type of component
+///
+/// *suited to rarely used components, memory only grows with the number of components; each
+/// component is boxed so that looking up an owner never touches the components of other owners*
+pub struct HashMapPool<TComponent: Sized>
+{
+    comps: HashMap<ObjectRef, Box<TComponent>>
+}
+
+impl <TComponent: Sized> Default for HashMapPool<TComponent>
+{
+    fn default() -> Self
+    {
+        return HashMapPool::new();
+    }
+}
+
+impl <TComponent: Sized> HashMapPool<TComponent>
+{
+    pub fn new() -> HashMapPool<TComponent>
+    {
+        return HashMapPool
+        {
+            comps: HashMap::new()
+        };
+    }
+}
+
+unsafe impl <TComponent: Sized> Storage for HashMapPool<TComponent>
+{
+    type Component = TComponent;
+
+    fn insert(&mut self, owner: ObjectRef, comp: TComponent) -> Result<Option<TComponent>, Error>
+    {
+        return Ok(self.comps.insert(owner, Box::new(comp)).map(|c| *c));
+    }
+
+    fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        return self.comps.remove(&owner).map(|c| *c);
+    }
+
+    fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        return self.comps.get(&owner).map(|c| &**c);
+    }
+
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        return self.comps.get_mut(&owner).map(|c| &mut **c);
+    }
+
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut TComponent>
+    {
+        return self.get_mut(owner).map(|comp| comp as *mut TComponent);
+    }
+
+    fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return self.comps.contains_key(&owner);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.comps.iter().map(|(o, c)| (*o, &**c));
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        return self.comps.iter_mut().map(|(o, c)| (*o, &mut **c));
+    }
+
+    fn len(&self) -> usize
+    {
+        return self.comps.len();
+    }
+}
+
+/// B-tree allocation pool for a given type of component
+///
+/// *suited to rarely used components which must be iterated in object order; each component is
+/// boxed so that looking up an owner never touches the components of other owners*
+pub struct BTreePool<TComponent: Sized>
+{
+    comps: BTreeMap<ObjectRef, Box<TComponent>>
+}
+
+impl <TComponent: Sized> Default for BTreePool<TComponent>
+{
+    fn default() -> Self
+    {
+        return BTreePool::new();
+    }
+}
+
+impl <TComponent: Sized> BTreePool<TComponent>
+{
+    pub fn new() -> BTreePool<TComponent>
+    {
+        return BTreePool
+        {
+            comps: BTreeMap::new()
+        };
+    }
+}
+
+unsafe impl <TComponent: Sized> Storage for BTreePool<TComponent>
+{
+    type Component = TComponent;
+
+    fn insert(&mut self, owner: ObjectRef, comp: TComponent) -> Result<Option<TComponent>, Error>
+    {
+        return Ok(self.comps.insert(owner, Box::new(comp)).map(|c| *c));
+    }
+
+    fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        return self.comps.remove(&owner).map(|c| *c);
+    }
+
+    fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        return self.comps.get(&owner).map(|c| &**c);
+    }
+
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        return self.comps.get_mut(&owner).map(|c| &mut **c);
+    }
+
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut TComponent>
+    {
+        return self.get_mut(owner).map(|comp| comp as *mut TComponent);
+    }
+
+    fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return self.comps.contains_key(&owner);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.comps.iter().map(|(o, c)| (*o, &**c));
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        return self.comps.iter_mut().map(|(o, c)| (*o, &mut **c));
+    }
+
+    fn len(&self) -> usize
+    {
+        return self.comps.len();
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize> serde::Serialize for HashMapPool<TComponent>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entries(self, serializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <'de, TComponent: serde::Deserialize<'de>> serde::Deserialize<'de> for HashMapPool<TComponent>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        return crate::component::deserialize_entries(deserializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize> serde::Serialize for BTreePool<TComponent>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entries(self, serializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <'de, TComponent: serde::Deserialize<'de>> serde::Deserialize<'de> for BTreePool<TComponent>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        return crate::component::deserialize_entries(deserializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize + serde::de::DeserializeOwned> crate::component::SerializeEntries for HashMapPool<TComponent>
+{
+    fn serialize_entry<S: serde::Serializer>(&self, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entry(self, owner, serializer);
+    }
+
+    fn deserialize_entry<'de, D: serde::Deserializer<'de>>(&mut self, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>
+    {
+        return crate::component::deserialize_entry(self, owner, deserializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize + serde::de::DeserializeOwned> crate::component::SerializeEntries for BTreePool<TComponent>
+{
+    fn serialize_entry<S: serde::Serializer>(&self, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entry(self, owner, serializer);
+    }
+
+    fn deserialize_entry<'de, D: serde::Deserializer<'de>>(&mut self, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>
+    {
+        return crate::component::deserialize_entry(self, owner, deserializer);
+    }
+}
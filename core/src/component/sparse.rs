@@ -0,0 +1,262 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::component::Storage;
+use crate::error::Error;
+
+/// Sparse-set allocation pool for a given type of component
+///
+/// *components are packed in a dense array for cache-friendly iteration; a sparse index keyed by
+/// object makes lookup, insertion and removal O(1), removal swaps the last component into the hole*
+pub struct SparseSetPool<TComponent: Sized>
+{
+    sparse: Vec<Option<u32>>,
+    owners: Vec<ObjectRef>,
+    comps: Vec<TComponent>
+}
+
+impl <TComponent: Sized> Default for SparseSetPool<TComponent>
+{
+    fn default() -> Self
+    {
+        return SparseSetPool::new();
+    }
+}
+
+impl <TComponent: Sized> SparseSetPool<TComponent>
+{
+    pub fn new() -> SparseSetPool<TComponent>
+    {
+        return SparseSetPool
+        {
+            sparse: Vec::new(),
+            owners: Vec::new(),
+            comps: Vec::new()
+        };
+    }
+
+    fn dense_index(&self, owner: ObjectRef) -> Option<usize>
+    {
+        let index = (*self.sparse.get(owner.index())?)? as usize;
+        if self.owners[index] != owner
+        {
+            return None;
+        }
+        return Some(index);
+    }
+
+    /// Attaches a component to the given object
+    ///
+    /// *returns the previous component if the object already owned one in this pool; a component left
+    /// behind by a previous object at the same index is discarded*
+    pub fn insert(&mut self, owner: ObjectRef, comp: TComponent) -> Option<TComponent>
+    {
+        if let Some(index) = self.sparse.get(owner.index()).copied().flatten()
+        {
+            let index = index as usize;
+            let old = std::mem::replace(&mut self.comps[index], comp);
+            if self.owners[index] == owner
+            {
+                return Some(old);
+            }
+            self.owners[index] = owner;
+            return None;
+        }
+        if self.sparse.len() <= owner.index()
+        {
+            self.sparse.resize(owner.index() + 1, None);
+        }
+        self.sparse[owner.index()] = Some(self.comps.len() as u32);
+        self.owners.push(owner);
+        self.comps.push(comp);
+        return None;
+    }
+
+    /// Returns true if the given object owns a component in this pool
+    pub fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return self.dense_index(owner).is_some();
+    }
+
+    /// Returns the component owned by the given object
+    pub fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        let index = self.dense_index(owner)?;
+        return Some(&self.comps[index]);
+    }
+
+    /// Returns the component owned by the given object
+    pub fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        let index = self.dense_index(owner)?;
+        return Some(&mut self.comps[index]);
+    }
+
+    /// Removes the component owned by the given object
+    pub fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        let index = self.dense_index(owner)?;
+        self.sparse[owner.index()] = None;
+        self.owners.swap_remove(index);
+        let comp = self.comps.swap_remove(index);
+        if let Some(moved) = self.owners.get(index)
+        {
+            self.sparse[moved.index()] = Some(index as u32);
+        }
+        return Some(comp);
+    }
+
+    /// Returns the number of components in this pool
+    pub fn len(&self) -> usize
+    {
+        return self.comps.len();
+    }
+
+    /// Returns true if this pool holds no component
+    pub fn is_empty(&self) -> bool
+    {
+        return self.comps.is_empty();
+    }
+
+    /// Returns the packed list of owners, in the same order as [components](SparseSetPool::components)
+    pub fn owners(&self) -> &[ObjectRef]
+    {
+        return &self.owners;
+    }
+
+    /// Returns the packed list of components
+    pub fn components(&self) -> &[TComponent]
+    {
+        return &self.comps;
+    }
+
+    /// Returns the packed list of components
+    pub fn components_mut(&mut self) -> &mut [TComponent]
+    {
+        return &mut self.comps;
+    }
+
+    /// Iterates over all components with their owning object
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.owners.iter().copied().zip(self.comps.iter());
+    }
+
+    /// Iterates mutably over all components with their owning object
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        return self.owners.iter().copied().zip(self.comps.iter_mut());
+    }
+}
+
+unsafe impl <TComponent: Sized> Storage for SparseSetPool<TComponent>
+{
+    type Component = TComponent;
+
+    fn insert(&mut self, owner: ObjectRef, comp: TComponent) -> Result<Option<TComponent>, Error>
+    {
+        return Ok(SparseSetPool::insert(self, owner, comp));
+    }
+
+    fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        return SparseSetPool::remove(self, owner);
+    }
+
+    fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        return SparseSetPool::get(self, owner);
+    }
+
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        return SparseSetPool::get_mut(self, owner);
+    }
+
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut TComponent>
+    {
+        let index = self.dense_index(owner)?;
+        // Owners are stored apart from components, only the component of the owner is accessed
+        return Some(unsafe { self.comps.as_mut_ptr().add(index) });
+    }
+
+    fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return SparseSetPool::contains(self, owner);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return SparseSetPool::iter(self);
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        return SparseSetPool::iter_mut(self);
+    }
+
+    fn len(&self) -> usize
+    {
+        return self.comps.len();
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize> serde::Serialize for SparseSetPool<TComponent>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entries(self, serializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <'de, TComponent: serde::Deserialize<'de>> serde::Deserialize<'de> for SparseSetPool<TComponent>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        return crate::component::deserialize_entries(deserializer);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <TComponent: serde::Serialize + serde::de::DeserializeOwned> crate::component::SerializeEntries for SparseSetPool<TComponent>
+{
+    fn serialize_entry<S: serde::Serializer>(&self, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return crate::component::serialize_entry(self, owner, serializer);
+    }
+
+    fn deserialize_entry<'de, D: serde::Deserializer<'de>>(&mut self, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>
+    {
+        return crate::component::deserialize_entry(self, owner, deserializer);
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::component::Storage;
+use crate::error::Error;
+
+/// Zero-sized marker component which can be stored in a [TagPool](TagPool)
+///
+/// *implemented by `#[derive(Component)]` for unit structs*
+pub trait Tag: Sized
+{
+    /// The value of this marker
+    const INSTANCE: Self;
+}
+
+const BITS: usize = u64::BITS as usize;
+
+/// Bitset allocation pool for marker components
+///
+/// *membership costs one bit per object, plus the generation of the tagged object so that stale
+/// references are rejected; generations are stored densely next to the bitset*
+pub struct TagPool<TComponent: Tag>
+{
+    bits: Vec<u64>,
+    generations: Vec<u32>,
+    len: usize,
+    instance: TComponent
+}
+
+impl <TComponent: Tag> Default for TagPool<TComponent>
+{
+    fn default() -> Self
+    {
+        return TagPool::new();
+    }
+}
+
+impl <TComponent: Tag> TagPool<TComponent>
+{
+    const ZERO_SIZED: () = assert!(std::mem::size_of::<TComponent>() == 0, "Tag components must be zero-sized");
+
+    pub fn new() -> TagPool<TComponent>
+    {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::ZERO_SIZED;
+        return TagPool
+        {
+            bits: Vec::new(),
+            generations: Vec::new(),
+            len: 0,
+            instance: TComponent::INSTANCE
+        };
+    }
+
+    fn is_set(&self, index: usize) -> bool
+    {
+        return self.bits.get(index / BITS).map(|w| w & (1 << (index % BITS)) != 0).unwrap_or(false);
+    }
+
+    fn generation(&self, index: usize) -> u32
+    {
+        return self.generations.get(index).copied().unwrap_or(0);
+    }
+
+    fn set_generation(&mut self, index: usize, generation: u32)
+    {
+        if self.generations.len() <= index
+        {
+            self.generations.resize(index + 1, 0);
+        }
+        self.generations[index] = generation;
+    }
+}
+
+unsafe impl <TComponent: Tag> Storage for TagPool<TComponent>
+{
+    type Component = TComponent;
+
+    fn insert(&mut self, owner: ObjectRef, _: TComponent) -> Result<Option<TComponent>, Error>
+    {
+        let index = owner.index();
+        if self.is_set(index)
+        {
+            let previous = self.generation(index);
+            self.set_generation(index, owner.generation());
+            // A tag left behind by a previous object at the same index is discarded
+            return Ok(if previous == owner.generation() { Some(TComponent::INSTANCE) } else { None });
+        }
+        if self.bits.len() <= index / BITS
+        {
+            self.bits.resize(index / BITS + 1, 0);
+        }
+        self.bits[index / BITS] |= 1 << (index % BITS);
+        self.set_generation(index, owner.generation());
+        self.len += 1;
+        return Ok(None);
+    }
+
+    fn remove(&mut self, owner: ObjectRef) -> Option<TComponent>
+    {
+        if !self.contains(owner)
+        {
+            return None;
+        }
+        let index = owner.index();
+        self.bits[index / BITS] &= !(1 << (index % BITS));
+        self.len -= 1;
+        return Some(TComponent::INSTANCE);
+    }
+
+    fn get(&self, owner: ObjectRef) -> Option<&TComponent>
+    {
+        if !self.contains(owner)
+        {
+            return None;
+        }
+        return Some(&self.instance);
+    }
+
+    fn get_mut(&mut self, owner: ObjectRef) -> Option<&mut TComponent>
+    {
+        if !self.contains(owner)
+        {
+            return None;
+        }
+        // The component is zero-sized, references to it never overlap any memory
+        return Some(&mut self.instance);
+    }
+
+    fn get_mut_ptr(&mut self, owner: ObjectRef) -> Option<*mut TComponent>
+    {
+        return self.get_mut(owner).map(|comp| comp as *mut TComponent);
+    }
+
+    fn contains(&self, owner: ObjectRef) -> bool
+    {
+        return self.is_set(owner.index()) && self.generation(owner.index()) == owner.generation();
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ObjectRef, &TComponent)>
+    {
+        return self.bits.iter().enumerate()
+            .flat_map(|(w, bits)| (0..BITS).filter(move |b| bits & (1 << b) != 0).map(move |b| w * BITS + b))
+            .map(move |i| (ObjectRef::new(i, self.generation(i)), &self.instance));
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (ObjectRef, &mut TComponent)>
+    {
+        let generations = &self.generations;
+        let instance: *mut TComponent = &mut self.instance;
+        return self.bits.iter().enumerate()
+            .flat_map(|(w, bits)| (0..BITS).filter(move |b| bits & (1 << b) != 0).map(move |b| w * BITS + b))
+            // The component is zero-sized, references to it never overlap any memory
+            .map(move |i| (ObjectRef::new(i, generations[i]), unsafe { &mut *instance }));
+    }
+
+    fn len(&self) -> usize
+    {
+        return self.len;
+    }
+}
+
+/// *only owners are serialized*
+#[cfg(feature = "serde")]
+impl <TComponent: Tag> serde::Serialize for TagPool<TComponent>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        use serde::ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(self.len))?;
+        for (owner, _) in self.iter()
+        {
+            seq.serialize_element(&owner)?;
+        }
+        return seq.end();
+    }
+}
+
+#[cfg(feature = "serde")]
+impl <'de, TComponent: Tag> serde::Deserialize<'de> for TagPool<TComponent>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        use serde::de::Error;
+        let owners: Vec<ObjectRef> = Vec::deserialize(deserializer)?;
+        let mut pool = TagPool::new();
+        for owner in owners
+        {
+            if pool.insert(owner, TComponent::INSTANCE).map_err(D::Error::custom)?.is_some()
+            {
+                return Err(D::Error::custom(format!("duplicate owner {}", owner)));
+            }
+        }
+        return Ok(pool);
+    }
+}
+
+/// *tags carry no data, entries are serialized as unit*
+#[cfg(feature = "serde")]
+impl <TComponent: Tag> crate::component::SerializeEntries for TagPool<TComponent>
+{
+    fn serialize_entry<S: serde::Serializer>(&self, owner: ObjectRef, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        use serde::ser::Error;
+        if !self.contains(owner)
+        {
+            return Err(S::Error::custom(format!("no component for {}", owner)));
+        }
+        return serializer.serialize_unit();
+    }
+
+    fn deserialize_entry<'de, D: serde::Deserializer<'de>>(&mut self, owner: ObjectRef, deserializer: D) -> Result<(), D::Error>
+    {
+        use serde::Deserialize;
+        use serde::de::Error;
+        <()>::deserialize(deserializer)?;
+        self.insert(owner, TComponent::INSTANCE).map_err(D::Error::custom)?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,408 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Delta encoding of component state
+//!
+//! A [Delta](Delta) lists, for each pool of a component manager, the components added, modified and
+//! removed between two states. Components are encoded one by one, so that applying a delta to the
+//! older state only touches the entries which differ.
+
+use std::any::Any;
+use std::collections::HashMap;
+
+use bincode::Options;
+use serde::Serialize;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::object::ObjectRef;
+use crate::component::ComponentManager;
+use crate::component::SerializePools;
+use crate::component::SerializeEntries;
+use crate::component::PoolVisitor;
+use crate::component::PoolLoader;
+use crate::component::Tick;
+use crate::snapshot::type_id_of;
+use crate::error::Error;
+
+/// Changes of a single pool
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PoolDelta
+{
+    /// Field name of the pool in the component manager
+    pub name: String,
+
+    /// Identifier of the stable component name, see [type_id_of](crate::snapshot::type_id_of)
+    pub type_id: u64,
+
+    /// True if the pool is sent in full, the pool is cleared before the delta is applied
+    pub full: bool,
+
+    /// Encoded components attached since the older state
+    pub added: Vec<(ObjectRef, Vec<u8>)>,
+
+    /// Encoded components whose value changed since the older state
+    pub modified: Vec<(ObjectRef, Vec<u8>)>,
+
+    /// Objects whose component was removed since the older state
+    pub removed: Vec<ObjectRef>
+}
+
+impl PoolDelta
+{
+    /// Returns true if this delta leaves the pool unchanged
+    pub fn is_empty(&self) -> bool
+    {
+        return !self.full && self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty();
+    }
+}
+
+/// Changes of all pools of a component manager between two states
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Delta
+{
+    pools: Vec<PoolDelta>
+}
+
+fn invalid<T: ToString>(err: T) -> Error
+{
+    return Error::InvalidDelta(err.to_string());
+}
+
+struct EntryRef<'a, TStorage>
+{
+    pool: &'a TStorage,
+    owner: ObjectRef
+}
+
+impl <'a, TStorage: SerializeEntries> Serialize for EntryRef<'a, TStorage>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        return self.pool.serialize_entry(self.owner, serializer);
+    }
+}
+
+fn encode<TStorage: SerializeEntries>(pool: &TStorage, owner: ObjectRef) -> Result<Vec<u8>, Error>
+{
+    return bincode::DefaultOptions::new().serialize(&EntryRef { pool, owner }).map_err(invalid);
+}
+
+fn decode<TStorage: SerializeEntries>(pool: &mut TStorage, owner: ObjectRef, data: &[u8]) -> Result<(), Error>
+{
+    let mut deserializer = bincode::Deserializer::from_slice(data, bincode::DefaultOptions::new());
+    return pool.deserialize_entry(owner, &mut deserializer).map_err(invalid);
+}
+
+type Entries = HashMap<ObjectRef, Vec<u8>>;
+
+struct Encoder
+{
+    pools: Vec<(&'static str, Entries)>
+}
+
+impl PoolVisitor for Encoder
+{
+    type Error = Error;
+
+    fn visit<TStorage: SerializeEntries + Serialize>(&mut self, name: &'static str, _: &'static str, _: usize, _: u32, pool: &TStorage) -> Result<(), Error>
+    {
+        let mut entries = HashMap::with_capacity(pool.len());
+        for (owner, _) in pool.iter()
+        {
+            entries.insert(owner, encode(pool, owner)?);
+        }
+        self.pools.push((name, entries));
+        return Ok(());
+    }
+}
+
+struct Differ
+{
+    old: Vec<(&'static str, Entries)>,
+    pools: Vec<PoolDelta>
+}
+
+impl PoolVisitor for Differ
+{
+    type Error = Error;
+
+    fn visit<TStorage: SerializeEntries + Serialize>(&mut self, name: &'static str, component: &'static str, _: usize, _: u32, pool: &TStorage) -> Result<(), Error>
+    {
+        let mut old = self.old.iter_mut().find(|(n, _)| *n == name).map(|(_, e)| std::mem::take(e)).unwrap_or_default();
+        let mut delta = PoolDelta
+        {
+            name: String::from(name),
+            type_id: type_id_of(component),
+            ..Default::default()
+        };
+        for (owner, _) in pool.iter()
+        {
+            let data = encode(pool, owner)?;
+            match old.remove(&owner)
+            {
+                None => delta.added.push((owner, data)),
+                Some(previous) if previous != data => delta.modified.push((owner, data)),
+                Some(_) => ()
+            }
+        }
+        delta.removed = old.into_keys().collect();
+        delta.removed.sort_by_key(|owner| (owner.index(), owner.generation()));
+        self.pools.push(delta);
+        return Ok(());
+    }
+}
+
+struct Tracker
+{
+    tick: Tick,
+    pools: Vec<PoolDelta>
+}
+
+impl PoolVisitor for Tracker
+{
+    type Error = Error;
+
+    fn visit<TStorage: SerializeEntries + Serialize>(&mut self, name: &'static str, component: &'static str, _: usize, _: u32, pool: &TStorage) -> Result<(), Error>
+    {
+        let mut delta = PoolDelta
+        {
+            name: String::from(name),
+            type_id: type_id_of(component),
+            ..Default::default()
+        };
+        match pool.changes_since(self.tick)
+        {
+            Some(changes) =>
+            {
+                for owner in changes.added
+                {
+                    delta.added.push((owner, encode(pool, owner)?));
+                }
+                for owner in changes.changed
+                {
+                    delta.modified.push((owner, encode(pool, owner)?));
+                }
+                delta.removed = changes.removed;
+            },
+            None =>
+            {
+                delta.full = true;
+                for (owner, _) in pool.iter()
+                {
+                    delta.added.push((owner, encode(pool, owner)?));
+                }
+            }
+        }
+        self.pools.push(delta);
+        return Ok(());
+    }
+}
+
+/// First applying pass, decodes every component of the delta into a temporary pool without touching
+/// the component manager
+struct Decoder<'a>
+{
+    pools: &'a [PoolDelta],
+    decoded: Vec<Option<Box<dyn Any>>>
+}
+
+impl <'a> PoolLoader for Decoder<'a>
+{
+    type Error = Error;
+
+    fn load<TStorage: 'static + SerializeEntries + DeserializeOwned + Default>(&mut self, name: &'static str, component: &'static str, _: usize, _: u32, _: &mut TStorage) -> Result<(), Error>
+    {
+        let delta = match self.pools.iter().find(|p| p.name == name)
+        {
+            Some(delta) => delta,
+            None =>
+            {
+                self.decoded.push(None);
+                return Ok(());
+            }
+        };
+        if delta.type_id != type_id_of(component)
+        {
+            return Err(Error::InvalidDelta(format!("pool {} does not store components of type {}", name, component)));
+        }
+        let mut decoded = TStorage::default();
+        for (owner, data) in delta.added.iter().chain(&delta.modified)
+        {
+            decode(&mut decoded, *owner, data)?;
+        }
+        self.decoded.push(Some(Box::new(decoded)));
+        return Ok(());
+    }
+}
+
+/// Second applying pass, moves the components decoded by the first pass into the component manager
+struct Applier<'a>
+{
+    pools: &'a [PoolDelta],
+    decoded: std::vec::IntoIter<Option<Box<dyn Any>>>,
+    rejected: Option<Error>
+}
+
+impl <'a> PoolLoader for Applier<'a>
+{
+    type Error = Error;
+
+    fn load<TStorage: 'static + SerializeEntries + DeserializeOwned + Default>(&mut self, name: &'static str, _: &'static str, _: usize, _: u32, pool: &mut TStorage) -> Result<(), Error>
+    {
+        // Pools are visited in declaration order in both passes
+        let mut decoded = match self.decoded.next()
+        {
+            Some(Some(decoded)) => *decoded.downcast::<TStorage>().unwrap(),
+            _ => return Ok(())
+        };
+        let delta = self.pools.iter().find(|p| p.name == name).unwrap();
+        if delta.full
+        {
+            let owners: Vec<ObjectRef> = pool.iter().map(|(owner, _)| owner).collect();
+            for owner in owners
+            {
+                pool.remove(owner);
+            }
+        }
+        for owner in &delta.removed
+        {
+            pool.remove(*owner);
+        }
+        for (owner, _) in delta.added.iter().chain(&delta.modified)
+        {
+            if let Some(comp) = decoded.remove(*owner)
+            {
+                if let Err(e) = pool.insert(*owner, comp)
+                {
+                    self.rejected.get_or_insert(e);
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Delta
+{
+    /// Computes the delta which turns the older component manager into the newer one
+    ///
+    /// *modifications are detected by comparing encoded components*
+    pub fn between<TComponentManager: SerializePools>(old: &TComponentManager, new: &TComponentManager) -> Result<Delta, Error>
+    {
+        let mut encoder = Encoder
+        {
+            pools: Vec::new()
+        };
+        old.visit_pools(&mut encoder)?;
+        let mut differ = Differ
+        {
+            old: encoder.pools,
+            pools: Vec::new()
+        };
+        new.visit_pools(&mut differ)?;
+        return Ok(Delta
+        {
+            pools: differ.pools
+        });
+    }
+
+    /// Computes the delta which turns the state at the given baseline tick into the current state,
+    /// using the change ticks recorded by the pools
+    ///
+    /// *pools which do not track changes are sent in full; removals are only known as long as they are
+    /// remembered by the pool, which forgets them at the end of each update, so a pool which forgot
+    /// removals made after the baseline is sent in full as well*
+    pub fn since<TComponentManager: SerializePools>(components: &TComponentManager, baseline: Tick) -> Result<Delta, Error>
+    {
+        let mut tracker = Tracker
+        {
+            tick: baseline,
+            pools: Vec::new()
+        };
+        components.visit_pools(&mut tracker)?;
+        return Ok(Delta
+        {
+            pools: tracker.pools
+        });
+    }
+
+    /// Applies this delta to the older state, pools missing from the delta are left untouched
+    ///
+    /// *lifecycle notifications are queued as for any other insertion or removal; every component is
+    /// decoded before the component manager is modified, so on a decoding error it is left unchanged*
+    ///
+    /// *components rejected by their storage are skipped, the first rejection is returned once every other
+    /// change has been applied*
+    pub fn apply<TComponentManager>(&self, components: &mut TComponentManager) -> Result<(), Error>
+        where TComponentManager: ComponentManager + SerializePools
+    {
+        let mut decoder = Decoder
+        {
+            pools: &self.pools,
+            decoded: Vec::new()
+        };
+        components.load_pools(&mut decoder)?;
+        let mut applier = Applier
+        {
+            pools: &self.pools,
+            decoded: decoder.decoded.into_iter(),
+            rejected: None
+        };
+        components.load_pools(&mut applier)?;
+        return match applier.rejected
+        {
+            Some(e) => Err(e),
+            None => Ok(())
+        };
+    }
+
+    /// Returns the changes of each pool
+    pub fn pools(&self) -> &[PoolDelta]
+    {
+        return &self.pools;
+    }
+
+    /// Returns true if applying this delta changes nothing
+    pub fn is_empty(&self) -> bool
+    {
+        return self.pools.iter().all(|p| p.is_empty());
+    }
+
+    /// Encodes this delta
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error>
+    {
+        return bincode::DefaultOptions::new().serialize(self).map_err(invalid);
+    }
+
+    /// Decodes a delta previously encoded with [to_bytes](Delta::to_bytes)
+    pub fn from_bytes(data: &[u8]) -> Result<Delta, Error>
+    {
+        return bincode::DefaultOptions::new().deserialize(data).map_err(invalid);
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! REGECS error types
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use crate::object::ObjectRef;
+
+/// Represents an error reported by REGECS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error
+{
+    /// The object reference does not point to a living object, it has been removed or belongs to another scene
+    StaleObject(ObjectRef),
+
+    /// No resource of the named type exists in the scene
+    MissingResource(&'static str),
+
+    /// Attaching the first object to the second one would create a cycle in the hierarchy
+    CyclicHierarchy(ObjectRef, ObjectRef),
+
+    /// A dynamic component type with the same name or Rust type is already registered
+    DuplicateComponent(String),
+
+    /// The pool of the named dynamic component type cannot grow any further
+    PoolOverflow(String),
+
+    /// Components of the named dynamic component type cannot be encoded or decoded
+    InvalidComponentData(String, String),
+
+    /// The named Rust type does not match the type of the dynamic component pool
+    ComponentTypeMismatch(&'static str),
+
+    /// The snapshot is corrupted or does not match the scene it is loaded into
+    InvalidSnapshot(String),
+
+    /// The delta is corrupted or does not match the component manager it is applied to
+    InvalidDelta(String),
+
+    /// No reflected field exists at the given path
+    UnknownField(String),
+
+    /// The reflected field at the given path is not of the named Rust type
+    FieldTypeMismatch(String, &'static str),
+
+    /// The reflected field at the given path cannot be converted to or from text
+    InvalidFieldValue(String),
+
+    /// No migration is registered to upgrade the named component type from the given schema version
+    MissingMigration(String, u32),
+
+    /// No secondary index with the given name and key type exists in the pool
+    UnknownIndex(String),
+
+    /// A secondary index with the given name already exists in the pool
+    DuplicateIndex(String),
+
+    /// The named secondary index is out of date, components were mutated since the pool was last re-indexed
+    StaleIndex(String),
+
+    /// The component breaks the unique constraint of the named index, its key is already used by the given object
+    UniqueViolation(String, ObjectRef)
+}
+
+impl Display for Error
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result
+    {
+        return match self
+        {
+            Error::StaleObject(obj) => write!(f, "stale object reference {}", obj),
+            Error::MissingResource(name) => write!(f, "missing resource {}", name),
+            Error::CyclicHierarchy(child, parent) => write!(f, "cannot attach {} to {}: cyclic hierarchy", child, parent),
+            Error::DuplicateComponent(name) => write!(f, "duplicate component type {}", name),
+            Error::PoolOverflow(name) => write!(f, "pool of dynamic component {} is too large", name),
+            Error::InvalidComponentData(name, reason) => write!(f, "invalid data for dynamic component {}: {}", name, reason),
+            Error::ComponentTypeMismatch(name) => write!(f, "component type mismatch for {}", name),
+            Error::InvalidSnapshot(reason) => write!(f, "invalid snapshot: {}", reason),
+            Error::InvalidDelta(reason) => write!(f, "invalid delta: {}", reason),
+            Error::UnknownField(path) => write!(f, "unknown field {}", path),
+            Error::FieldTypeMismatch(path, name) => write!(f, "field {} is not of type {}", path, name),
+            Error::InvalidFieldValue(path) => write!(f, "invalid text value for field {}", path),
+            Error::MissingMigration(name, version) => write!(f, "no migration for component {} from schema version {}", name, version),
+            Error::UnknownIndex(name) => write!(f, "unknown index {}", name),
+            Error::DuplicateIndex(name) => write!(f, "duplicate index {}", name),
+            Error::StaleIndex(name) => write!(f, "index {} is out of date, the pool must be re-indexed", name),
+            Error::UniqueViolation(name, other) => write!(f, "unique index {} violated: key already used by {}", name, other)
+        };
+    }
+}
+
+impl std::error::Error for Error {}
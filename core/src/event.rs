@@ -32,19 +32,52 @@ use std::any::Any;
 use std::boxed::Box;
 
 use crate::object::ObjectRef;
+use crate::resource::Resources;
 
+/// Context given to an object when it receives an event
+///
+/// *ptr is the receiving object, other is the object which sent the event (None when sent from the scene)*
 pub struct EventContext<'a, TState, TComponentManager>
 {
     pub ptr: ObjectRef,
     pub other: Option<ObjectRef>,
     pub state: &'a mut TState,
-    pub components: &'a mut TComponentManager
+    pub components: &'a mut TComponentManager,
+    pub resources: &'a mut Resources
 }
 
+/// Function duplicating a type-erased event, used to deliver a broadcast event to each recipient
+pub(crate) type CloneFn = fn(&dyn Any) -> Box<dyn Any>;
+
+pub(crate) fn clone_event<EventType: Any + Clone>(ev: &dyn Any) -> Box<dyn Any>
+{
+    return Box::new(ev.downcast_ref::<EventType>().unwrap().clone());
+}
+
+/// Event waiting in the queue of a scene
+///
+/// *target is None for a broadcast, in which case clone is always set*
+pub(crate) struct PendingEvent
+{
+    pub(crate) sender: Option<ObjectRef>,
+    pub(crate) target: Option<ObjectRef>,
+    pub(crate) data: Box<dyn Any>,
+    pub(crate) clone: Option<CloneFn>
+}
+
+/// Result of an event handler, used to send follow-up events and to request removal of the receiving object
 pub struct EventResult
 {
-    to_send: Vec<(Option<ObjectRef>, Box<dyn Any>)>,
-    remove_flag: bool
+    pub(crate) to_send: Vec<PendingEvent>,
+    pub(crate) remove_flag: bool
+}
+
+impl Default for EventResult
+{
+    fn default() -> Self
+    {
+        return EventResult::new();
+    }
 }
 
 impl EventResult
@@ -63,13 +96,32 @@ impl EventResult
         self.remove_flag = true;
     }
 
+    /// Sends an event to the given object
+    ///
+    /// *unlike Scene::send_event, the target is only validated when the event is dispatched: an event
+    /// sent to a stale reference, or to an object removed before dispatch, is silently dropped*
     pub fn send<EventType: Any>(&mut self, target: ObjectRef, ev: EventType)
     {
-        self.to_send.push((Some(target), Box::from(ev)));
+        self.to_send.push(PendingEvent
+        {
+            sender: None,
+            target: Some(target),
+            data: Box::from(ev),
+            clone: None
+        });
     }
 
-    pub fn broadcast<EventType: Any>(&mut self, ev: EventType)
+    /// Sends an event to all objects of the scene
+    ///
+    /// *each recipient receives its own copy of the event*
+    pub fn broadcast<EventType: Any + Clone>(&mut self, ev: EventType)
     {
-        self.to_send.push((None, Box::from(ev)));
+        self.to_send.push(PendingEvent
+        {
+            sender: None,
+            target: None,
+            data: Box::from(ev),
+            clone: Some(clone_event::<EventType>)
+        });
     }
 }
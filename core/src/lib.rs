@@ -26,8 +26,26 @@
 // NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
 // SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+#![allow(clippy::needless_return)]
+
 pub mod event;
 pub mod object;
 pub mod system;
 pub mod component;
 pub mod scene;
+pub mod error;
+pub mod query;
+pub mod archetype;
+pub mod resource;
+pub mod bundle;
+pub mod prefab;
+pub mod reflect;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+#[cfg(feature = "snapshot")]
+pub mod delta;
+#[cfg(feature = "snapshot")]
+pub mod migration;
+
+#[cfg(feature = "serde")]
+pub use serde;
@@ -0,0 +1,111 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Schema migrations of component pools
+//!
+//! Component types declare a schema version with `#[version(N)]`, which is recorded in snapshots.
+//! A [Migrations](Migrations) registry holds, for each component type, upgrade functions from version N
+//! to N + 1; [Snapshot](crate::snapshot::Snapshot) runs them when it loads a pool saved with an older version.
+//! Steps are keyed on the [stable name](Versioned::NAME) of the component type, so renaming or moving
+//! the Rust type does not invalidate them.
+//!
+//! *migrations apply to pools stored in a [ComponentPool](ComponentPool), a serializable derived component
+//! manager fails to compile if a component with a schema version greater than 1 is stored in another storage*
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::component::ComponentPool;
+use crate::component::Versioned;
+use crate::snapshot::type_id_of;
+use crate::error::Error;
+
+type MigrateFn = Box<dyn Fn(&[u8]) -> Result<Vec<u8>, Error>>;
+
+/// Registry of component schema migrations
+#[derive(Default)]
+pub struct Migrations
+{
+    steps: HashMap<(u64, u32), MigrateFn>
+}
+
+impl Migrations
+{
+    pub fn new() -> Migrations
+    {
+        return Migrations
+        {
+            steps: HashMap::new()
+        };
+    }
+
+    /// Registers the upgrade of a component type from the given schema version to the next one
+    ///
+    /// *TFrom is the layout of the component at version `from` and TTo its layout at version `from + 1`,
+    /// the last step of the chain produces TComponent itself; the pool is decoded as a ComponentPool*
+    pub fn register<TComponent, TFrom, TTo, F>(mut self, from: u32, upgrade: F) -> Self
+        where TComponent: Versioned,
+              TFrom: DeserializeOwned,
+              TTo: Serialize,
+              F: 'static + Fn(TFrom) -> TTo
+    {
+        let step = move |data: &[u8]|
+        {
+            let pool: ComponentPool<TFrom> = bincode::deserialize(data).map_err(|e| Error::InvalidSnapshot(e.to_string()))?;
+            let pool = pool.convert(&upgrade);
+            return bincode::serialize(&pool).map_err(|e| Error::InvalidSnapshot(e.to_string()));
+        };
+        self.steps.insert((type_id_of(TComponent::NAME), from), Box::new(step));
+        return self;
+    }
+
+    /// Returns true if an upgrade of the component type with the given stable name from the given schema version is registered
+    pub fn contains(&self, component: &str, from: u32) -> bool
+    {
+        return self.steps.contains_key(&(type_id_of(component), from));
+    }
+
+    /// Upgrades the serialized pool of the component type with the given stable name from one schema version to another,
+    /// running every intermediate step
+    ///
+    /// *returns an error if a step is missing*
+    pub fn upgrade(&self, component: &str, from: u32, to: u32, data: &[u8]) -> Result<Vec<u8>, Error>
+    {
+        let id = type_id_of(component);
+        let mut data = data.to_vec();
+        for version in from..to
+        {
+            let step = self.steps.get(&(id, version))
+                .ok_or_else(|| Error::MissingMigration(String::from(component), version))?;
+            data = step(&data)?;
+        }
+        return Ok(data);
+    }
+}
@@ -30,14 +30,84 @@
 
 use std::boxed::Box;
 use std::any::Any;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
 
 use crate::event::EventContext;
 use crate::event::EventResult;
 
-/// Type alias for object references
-/// 
+/// Integer type used to store object indices
+///
+/// *the `large-world` feature switches to 64 bit indices for very large worlds*
+#[cfg(not(feature = "large-world"))]
+pub type ObjectIndex = u32;
+
+/// Integer type used to store object indices
+///
+/// *the `large-world` feature switches to 64 bit indices for very large worlds*
+#[cfg(feature = "large-world")]
+pub type ObjectIndex = u64;
+
+/// Generational reference to an object
+///
 /// *serves also as entry point into REGECS entity layer*
-pub type ObjectRef = u32;
+///
+/// A reference is made of a slot index and a generation; once the object is removed,
+/// the reference is considered stale and is rejected by the scene even if the slot is reused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectRef
+{
+    index: ObjectIndex,
+    generation: u32
+}
+
+impl ObjectRef
+{
+    /// Creates a reference from raw parts
+    ///
+    /// *references are normally allocated by a scene, this is mostly useful to custom storages*
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index does not fit in [ObjectIndex](ObjectIndex), see the `large-world` feature.
+    pub fn new(index: usize, generation: u32) -> ObjectRef
+    {
+        let index = match ObjectIndex::try_from(index)
+        {
+            Ok(index) => index,
+            Err(_) => panic!("object index {} does not fit in {} bits, enable the large-world feature",
+                index, std::mem::size_of::<ObjectIndex>() * 8)
+        };
+        return ObjectRef
+        {
+            index,
+            generation
+        };
+    }
+
+    /// Returns the slot index of this reference
+    pub fn index(&self) -> usize
+    {
+        return self.index as usize;
+    }
+
+    /// Returns the generation of this reference
+    pub fn generation(&self) -> u32
+    {
+        return self.generation;
+    }
+}
+
+impl Display for ObjectRef
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result
+    {
+        return write!(f, "{}v{}", self.index, self.generation);
+    }
+}
 
 /// Low-level object interface to represent all dynamic objects managed by a scene
 pub trait LowObject<TState, TComponentManager>
@@ -45,6 +115,16 @@ pub trait LowObject<TState, TComponentManager>
     fn on_event(&mut self, event: Box<dyn Any>, context: EventContext<TState, TComponentManager>) -> Option<EventResult>;
     fn on_init(&mut self, ptr: ObjectRef, components: &mut TComponentManager);
     fn on_remove(&mut self, ptr: ObjectRef, components: &mut TComponentManager);
+
+    /// Returns a copy of this object, None if the object cannot be copied
+    ///
+    /// *used by prefab captures and [Scene::clone_object](crate::scene::Scene::clone_object); objects are not
+    /// copyable by default*
+    fn clone_object(&self) -> Option<Box<dyn LowObject<TState, TComponentManager>>>
+        where Self: 'static
+    {
+        return None;
+    }
 }
 
 /// High-level object interface
@@ -55,6 +135,15 @@ pub trait Object<TState, TComponentManager>
     fn event(&mut self, event: &Self::EventType, context: EventContext<TState, TComponentManager>) -> Option<EventResult>;
     fn init(&mut self, ptr: ObjectRef, components: &mut TComponentManager);
     fn remove(&mut self, ptr: ObjectRef, components: &mut TComponentManager);
+
+    /// Returns a copy of this object, None if the object cannot be copied
+    ///
+    /// *objects are not copied by default, implement this as `Some(self.clone())` to allow it*
+    fn duplicate(&self) -> Option<Self>
+        where Self: Sized
+    {
+        return None;
+    }
 }
 
 impl <TState, TComponentManager, EventType: Any, O: Object<TState, TComponentManager, EventType = EventType>> LowObject<TState, TComponentManager> for O
@@ -77,4 +166,10 @@ impl <TState, TComponentManager, EventType: Any, O: Object<TState, TComponentMan
     {
         self.remove(ptr, components);
     }
+
+    fn clone_object(&self) -> Option<Box<dyn LowObject<TState, TComponentManager>>>
+        where Self: 'static
+    {
+        return self.duplicate().map(|obj| Box::new(obj) as Box<dyn LowObject<TState, TComponentManager>>);
+    }
 }
@@ -0,0 +1,173 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! REGECS prefab layer
+
+use crate::object::ObjectRef;
+use crate::error::Error;
+use crate::object::LowObject;
+use crate::bundle::Bundle;
+use crate::component::ComponentManager;
+use crate::component::DynamicPool;
+
+/// Type-erased component stored in a prefab, inserts a copy of the component for the given owner
+pub type PrefabComponent<TComponentManager> = Box<dyn Fn(&mut TComponentManager, ObjectRef) -> Result<(), Error>>;
+
+/// Component managers able to copy all components of an object
+///
+/// *implemented by `#[derive(ComponentManager)]` when the struct is marked with `#[prefab]`,
+/// all components must then implement Clone; runtime-defined components are captured separately
+/// by [Prefab::capture](Prefab::capture)*
+pub trait Capture: Sized
+{
+    /// Copies all compile-time components owned by the given object
+    fn capture(&self, target: ObjectRef) -> Vec<PrefabComponent<Self>>;
+}
+
+type ObjectFactory<TState, TComponentManager> = Box<dyn Fn() -> Option<Box<dyn LowObject<TState, TComponentManager>>>>;
+
+/// Reusable template of components and object, instantiated through
+/// [Scene::instantiate](crate::scene::Scene::instantiate)
+pub struct Prefab<TState, TComponentManager>
+{
+    components: Vec<PrefabComponent<TComponentManager>>,
+    factory: Option<ObjectFactory<TState, TComponentManager>>
+}
+
+impl <TState, TComponentManager> Default for Prefab<TState, TComponentManager>
+{
+    fn default() -> Self
+    {
+        return Prefab::new();
+    }
+}
+
+impl <TState, TComponentManager> Prefab<TState, TComponentManager>
+{
+    /// Creates an empty prefab
+    pub fn new() -> Prefab<TState, TComponentManager>
+    {
+        return Prefab
+        {
+            components: Vec::new(),
+            factory: None
+        };
+    }
+
+    /// Creates a prefab from a copy of all components owned by the given object
+    ///
+    /// *runtime-defined components are captured only if their type can be cloned, they are inserted
+    /// into the pool registered with the same name when the prefab is instantiated*
+    ///
+    /// *returns Error::PoolOverflow if a runtime-defined component cannot be copied*
+    pub fn capture(components: &TComponentManager, target: ObjectRef) -> Result<Prefab<TState, TComponentManager>, Error>
+        where TComponentManager: 'static + Capture + ComponentManager
+    {
+        let mut list = components.capture(target);
+        let pools = components.dynamic().into_iter().flat_map(|dynamic| dynamic.pools());
+        for (_, pool) in pools.filter(|(_, pool)| pool.contains(target) && pool.info().is_clone())
+        {
+            // The copy is kept in a single-entry pool owned by the prefab
+            let key = ObjectRef::new(0, 0);
+            let mut copy = DynamicPool::new(pool.info().clone());
+            pool.clone_into(target, &mut copy, key)?;
+            list.push(Box::new(move |components: &mut TComponentManager, owner|
+            {
+                let name = copy.info().name();
+                if let Some(dst) = components.dynamic_mut().and_then(|dynamic| dynamic.pool_by_name_mut(name))
+                {
+                    copy.clone_into(key, dst, owner)?;
+                }
+                return Ok(());
+            }));
+        }
+        return Ok(Prefab
+        {
+            components: list,
+            factory: None
+        });
+    }
+
+    /// Adds a component or a bundle of components to this prefab
+    ///
+    /// *components are applied in the order they were added, so a later component replaces an earlier one of the same type*
+    pub fn with<TBundle: 'static + Bundle<TComponentManager> + Clone>(mut self, bundle: TBundle) -> Self
+    {
+        self.components.push(Box::new(move |components, owner| bundle.clone().insert(owner, components)));
+        return self;
+    }
+
+    /// Sets the factory used to create the object attached to each instance
+    pub fn object<TObject, TFactory>(mut self, factory: TFactory) -> Self
+        where TObject: 'static + LowObject<TState, TComponentManager>,
+              TFactory: 'static + Fn() -> TObject
+    {
+        self.factory = Some(Box::new(move || Some(Box::new(factory()))));
+        return self;
+    }
+
+    /// Uses copies of the given object, made through LowObject::clone_object, as the object of each instance
+    pub fn template(mut self, template: Box<dyn LowObject<TState, TComponentManager>>) -> Self
+        where TState: 'static,
+              TComponentManager: 'static
+    {
+        self.factory = Some(Box::new(move || template.clone_object()));
+        return self;
+    }
+
+    /// Inserts a copy of all components of this prefab for the given owner
+    ///
+    /// *stops at the first component rejected by its storage, components inserted before it are kept*
+    pub fn insert(&self, owner: ObjectRef, components: &mut TComponentManager) -> Result<(), Error>
+    {
+        for comp in &self.components
+        {
+            comp(components, owner)?;
+        }
+        return Ok(());
+    }
+
+    /// Creates a new object from the factory of this prefab, None if no factory was set
+    /// or if the template object cannot be copied
+    pub fn create_object(&self) -> Option<Box<dyn LowObject<TState, TComponentManager>>>
+    {
+        return self.factory.as_ref().and_then(|f| f());
+    }
+
+    /// Returns the number of components in this prefab
+    pub fn len(&self) -> usize
+    {
+        return self.components.len();
+    }
+
+    /// Returns true if this prefab holds no component
+    pub fn is_empty(&self) -> bool
+    {
+        return self.components.is_empty();
+    }
+}
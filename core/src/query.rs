@@ -0,0 +1,436 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! REGECS query layer
+//!
+//! Queries iterate all objects owning a given set of components across several pools in one pass.
+//!
+//! ```ignore
+//! for (obj, (pos, vel, _)) in Query::new((&mut mgr.positions, &mgr.velocities, Without(&mgr.frozen)))
+//! {
+//!     pos.x += vel.x;
+//! }
+//! ```
+
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::component::Storage;
+use crate::component::Tracked;
+use crate::component::Tick;
+
+/// A single term of a query
+///
+/// # Safety
+///
+/// [Query](Query) filters all candidates with `matches` before fetching the first item, then keeps
+/// items alive while fetching other objects, so implementors must guarantee that:
+/// - `collect_candidates` never appends the same object twice
+/// - items fetched for distinct objects never alias, and stay valid while other objects are fetched
+/// - `fetch` does not access any component other than the one of the given object
+pub unsafe trait Fetch
+{
+    /// Item produced by this term for each matching object
+    type Item;
+
+    /// Returns the number of objects this term can drive iteration with, None if this term cannot drive a query
+    fn candidates(&self) -> Option<usize>;
+
+    /// Appends to the given list all objects which may match this term
+    ///
+    /// *each object must be appended at most once*
+    fn collect_candidates(&self, out: &mut Vec<ObjectRef>);
+
+    /// Returns true if the given object matches this term
+    fn matches(&self, obj: ObjectRef) -> bool;
+
+    /// Fetches the item for the given object, None if the object does not own the component
+    ///
+    /// # Safety
+    ///
+    /// This function must be called at most once per object.
+    unsafe fn fetch(&mut self, obj: ObjectRef) -> Option<Self::Item>;
+}
+
+unsafe impl <'a, TStorage: Storage> Fetch for &'a TStorage
+{
+    type Item = &'a TStorage::Component;
+
+    fn candidates(&self) -> Option<usize>
+    {
+        return Some(self.len());
+    }
+
+    fn collect_candidates(&self, out: &mut Vec<ObjectRef>)
+    {
+        out.extend(self.iter().map(|(obj, _)| obj));
+    }
+
+    fn matches(&self, obj: ObjectRef) -> bool
+    {
+        return self.contains(obj);
+    }
+
+    unsafe fn fetch(&mut self, obj: ObjectRef) -> Option<Self::Item>
+    {
+        let storage = *self;
+        return storage.get(obj);
+    }
+}
+
+unsafe impl <'a, TStorage: Storage> Fetch for &'a mut TStorage
+{
+    type Item = &'a mut TStorage::Component;
+
+    fn candidates(&self) -> Option<usize>
+    {
+        return Some(self.len());
+    }
+
+    fn collect_candidates(&self, out: &mut Vec<ObjectRef>)
+    {
+        out.extend(self.iter().map(|(obj, _)| obj));
+    }
+
+    fn matches(&self, obj: ObjectRef) -> bool
+    {
+        return self.contains(obj);
+    }
+
+    unsafe fn fetch(&mut self, obj: ObjectRef) -> Option<Self::Item>
+    {
+        // Storage guarantees pointers of distinct owners never overlap and stay valid while other
+        // owners are accessed, the caller guarantees each object is fetched once
+        return self.get_mut_ptr(obj).map(|comp| &mut *comp);
+    }
+}
+
+/// Query term matching all objects, yields the component when the object owns one
+pub struct Optional<T>(pub T);
+
+unsafe impl <T: Fetch> Fetch for Optional<T>
+{
+    type Item = Option<T::Item>;
+
+    fn candidates(&self) -> Option<usize>
+    {
+        return None;
+    }
+
+    fn collect_candidates(&self, _: &mut Vec<ObjectRef>)
+    {
+    }
+
+    fn matches(&self, _: ObjectRef) -> bool
+    {
+        return true;
+    }
+
+    unsafe fn fetch(&mut self, obj: ObjectRef) -> Option<Self::Item>
+    {
+        return Some(self.0.fetch(obj));
+    }
+}
+
+/// Query filter matching objects which own a component in the given pool
+pub struct With<T>(pub T);
+
+unsafe impl <T: Fetch> Fetch for With<T>
+{
+    type Item = ();
+
+    fn candidates(&self) -> Option<usize>
+    {
+        return self.0.candidates();
+    }
+
+    fn collect_candidates(&self, out: &mut Vec<ObjectRef>)
+    {
+        self.0.collect_candidates(out);
+    }
+
+    fn matches(&self, obj: ObjectRef) -> bool
+    {
+        return self.0.matches(obj);
+    }
+
+    unsafe fn fetch(&mut self, _: ObjectRef) -> Option<Self::Item>
+    {
+        return Some(());
+    }
+}
+
+/// Query filter matching objects which do not own a component in the given pool
+pub struct Without<T>(pub T);
+
+unsafe impl <T: Fetch> Fetch for Without<T>
+{
+    type Item = ();
+
+    fn candidates(&self) -> Option<usize>
+    {
+        return None;
+    }
+
+    fn collect_candidates(&self, _: &mut Vec<ObjectRef>)
+    {
+    }
+
+    fn matches(&self, obj: ObjectRef) -> bool
+    {
+        return !self.0.matches(obj);
+    }
+
+    unsafe fn fetch(&mut self, _: ObjectRef) -> Option<Self::Item>
+    {
+        return Some(());
+    }
+}
+
+/// Reference to a storage which tracks changes
+pub trait TrackedRef
+{
+    /// Type of storage referenced
+    type Target: Tracked;
+
+    /// Returns the referenced storage
+    fn tracked(&self) -> &Self::Target;
+}
+
+impl <TStorage: Tracked> TrackedRef for &TStorage
+{
+    type Target = TStorage;
+
+    fn tracked(&self) -> &TStorage
+    {
+        return self;
+    }
+}
+
+impl <TStorage: Tracked> TrackedRef for &mut TStorage
+{
+    type Target = TStorage;
+
+    fn tracked(&self) -> &TStorage
+    {
+        return self;
+    }
+}
+
+/// Query term matching objects whose component was added after the given tick
+pub struct Added<T>
+{
+    term: T,
+    since: Tick
+}
+
+impl <T> Added<T>
+{
+    pub fn new(term: T, since: Tick) -> Added<T>
+    {
+        return Added
+        {
+            term,
+            since
+        };
+    }
+}
+
+unsafe impl <T: Fetch + TrackedRef> Fetch for Added<T>
+{
+    type Item = T::Item;
+
+    fn candidates(&self) -> Option<usize>
+    {
+        return self.term.candidates();
+    }
+
+    fn collect_candidates(&self, out: &mut Vec<ObjectRef>)
+    {
+        self.term.collect_candidates(out);
+    }
+
+    fn matches(&self, obj: ObjectRef) -> bool
+    {
+        return self.term.tracked().added_tick(obj).map(|t| t > self.since).unwrap_or(false);
+    }
+
+    unsafe fn fetch(&mut self, obj: ObjectRef) -> Option<Self::Item>
+    {
+        return self.term.fetch(obj);
+    }
+}
+
+/// Query term matching objects whose component was changed (or added) after the given tick
+pub struct Changed<T>
+{
+    term: T,
+    since: Tick
+}
+
+impl <T> Changed<T>
+{
+    pub fn new(term: T, since: Tick) -> Changed<T>
+    {
+        return Changed
+        {
+            term,
+            since
+        };
+    }
+}
+
+unsafe impl <T: Fetch + TrackedRef> Fetch for Changed<T>
+{
+    type Item = T::Item;
+
+    fn candidates(&self) -> Option<usize>
+    {
+        return self.term.candidates();
+    }
+
+    fn collect_candidates(&self, out: &mut Vec<ObjectRef>)
+    {
+        self.term.collect_candidates(out);
+    }
+
+    fn matches(&self, obj: ObjectRef) -> bool
+    {
+        return self.term.tracked().changed_tick(obj).map(|t| t > self.since).unwrap_or(false);
+    }
+
+    unsafe fn fetch(&mut self, obj: ObjectRef) -> Option<Self::Item>
+    {
+        return self.term.fetch(obj);
+    }
+}
+
+macro_rules! impl_fetch_tuple
+{
+    ($($name: ident $index: tt),*) =>
+    {
+        unsafe impl <$($name: Fetch),*> Fetch for ($($name,)*)
+        {
+            type Item = ($($name::Item,)*);
+
+            fn candidates(&self) -> Option<usize>
+            {
+                let mut min: Option<usize> = None;
+                $(
+                    if let Some(v) = self.$index.candidates()
+                    {
+                        min = Some(min.map_or(v, |m| m.min(v)));
+                    }
+                )*
+                return min;
+            }
+
+            fn collect_candidates(&self, out: &mut Vec<ObjectRef>)
+            {
+                let min = match self.candidates()
+                {
+                    Some(v) => v,
+                    None => return
+                };
+                $(
+                    if self.$index.candidates() == Some(min)
+                    {
+                        self.$index.collect_candidates(out);
+                        return;
+                    }
+                )*
+            }
+
+            fn matches(&self, obj: ObjectRef) -> bool
+            {
+                return $(self.$index.matches(obj))&&*;
+            }
+
+            unsafe fn fetch(&mut self, obj: ObjectRef) -> Option<Self::Item>
+            {
+                return Some(($(self.$index.fetch(obj)?,)*));
+            }
+        }
+    };
+}
+
+impl_fetch_tuple!(A 0);
+impl_fetch_tuple!(A 0, B 1);
+impl_fetch_tuple!(A 0, B 1, C 2);
+impl_fetch_tuple!(A 0, B 1, C 2, D 3);
+impl_fetch_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_fetch_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_fetch_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_fetch_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Iterator over all objects matching a set of query terms
+///
+/// *iteration is driven by the smallest required term, a query made only of Optional
+/// and Without terms matches nothing*
+pub struct Query<TTerms>
+{
+    terms: TTerms,
+    candidates: Vec<ObjectRef>,
+    pos: usize
+}
+
+impl <TTerms: Fetch> Query<TTerms>
+{
+    pub fn new(terms: TTerms) -> Query<TTerms>
+    {
+        let mut candidates = Vec::new();
+        terms.collect_candidates(&mut candidates);
+        // Matching borrows the storages, it must be done before any item is handed out
+        candidates.retain(|obj| terms.matches(*obj));
+        return Query
+        {
+            terms,
+            candidates,
+            pos: 0
+        };
+    }
+}
+
+impl <TTerms: Fetch> Iterator for Query<TTerms>
+{
+    type Item = (ObjectRef, TTerms::Item);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        while let Some(obj) = self.candidates.get(self.pos).copied()
+        {
+            self.pos += 1;
+            // Candidates are unique as required by Fetch, so each object is fetched once
+            if let Some(item) = unsafe { self.terms.fetch(obj) }
+            {
+                return Some((obj, item));
+            }
+        }
+        return None;
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Runtime reflection of components
+//!
+//! Fields of reflected values are addressed by path: field names separated by dots,
+//! tuple struct fields and vector elements are named by their index (`transform.position.0`).
+//! An optional value exposes the fields of its content when it is set.
+//!
+//! *enums are not supported by `#[derive(Reflect)]`, they can be exposed as leaves by implementing
+//! [Reflect](Reflect) by hand with [to_text](Reflect::to_text) and [parse_text](Reflect::parse_text)*
+
+use std::any::Any;
+use std::any::type_name;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::object::ObjectRef;
+use crate::error::Error;
+
+/// Describes a field of a reflected value
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FieldInfo
+{
+    pub name: &'static str,
+    pub type_name: &'static str
+}
+
+/// Value whose fields can be listed, read and written at runtime
+///
+/// *implemented by `#[derive(Reflect)]` for structs; primitive types, strings and object references
+/// are leaves which have no field but can be converted to and from text; `Option` and `Vec` forward
+/// to their content*
+pub trait Reflect: Any
+{
+    /// Returns the name of the type of this value
+    fn type_name(&self) -> &'static str;
+
+    /// Lists the fields of this value in declaration order
+    fn fields(&self) -> Vec<FieldInfo>;
+
+    /// Returns the field with the given name
+    fn field(&self, name: &str) -> Option<&dyn Reflect>;
+
+    /// Returns the field with the given name
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Formats this value as text, None if this value is not a leaf
+    fn to_text(&self) -> Option<String>
+    {
+        return None;
+    }
+
+    /// Replaces this value by the one parsed from the given text
+    ///
+    /// *returns false if the text is invalid or this value is not a leaf*
+    fn parse_text(&mut self, _text: &str) -> bool
+    {
+        return false;
+    }
+
+    /// Creates a new value parsed from the given text, None if the text is invalid or this type is not a leaf
+    fn from_text(_text: &str) -> Option<Self>
+        where Self: Sized
+    {
+        return None;
+    }
+}
+
+impl dyn Reflect
+{
+    /// Returns the value at the given path, the empty path designates this value
+    pub fn path(&self, path: &str) -> Result<&dyn Reflect, Error>
+    {
+        let mut value = self;
+        if path.is_empty()
+        {
+            return Ok(value);
+        }
+        for name in path.split('.')
+        {
+            value = value.field(name).ok_or_else(|| Error::UnknownField(String::from(path)))?;
+        }
+        return Ok(value);
+    }
+
+    /// Returns the value at the given path, the empty path designates this value
+    pub fn path_mut(&mut self, path: &str) -> Result<&mut dyn Reflect, Error>
+    {
+        let mut value = self;
+        if path.is_empty()
+        {
+            return Ok(value);
+        }
+        for name in path.split('.')
+        {
+            value = value.field_mut(name).ok_or_else(|| Error::UnknownField(String::from(path)))?;
+        }
+        return Ok(value);
+    }
+
+    /// Returns the value at the given path as a concrete type
+    pub fn get<T: Any>(&self, path: &str) -> Result<&T, Error>
+    {
+        return self.path(path)?.as_any().downcast_ref()
+            .ok_or_else(|| Error::FieldTypeMismatch(String::from(path), type_name::<T>()));
+    }
+
+    /// Returns the value at the given path as a concrete type
+    pub fn get_mut<T: Any>(&mut self, path: &str) -> Result<&mut T, Error>
+    {
+        return self.path_mut(path)?.as_any_mut().downcast_mut()
+            .ok_or_else(|| Error::FieldTypeMismatch(String::from(path), type_name::<T>()));
+    }
+
+    /// Replaces the value at the given path and returns the previous one
+    pub fn set<T: Any>(&mut self, path: &str, value: T) -> Result<T, Error>
+    {
+        let field = self.get_mut::<T>(path)?;
+        return Ok(std::mem::replace(field, value));
+    }
+
+    /// Formats the leaf value at the given path as text
+    pub fn text(&self, path: &str) -> Result<String, Error>
+    {
+        return self.path(path)?.to_text().ok_or_else(|| Error::InvalidFieldValue(String::from(path)));
+    }
+
+    /// Parses text into the leaf value at the given path
+    pub fn set_text(&mut self, path: &str, text: &str) -> Result<(), Error>
+    {
+        if !self.path_mut(path)?.parse_text(text)
+        {
+            return Err(Error::InvalidFieldValue(String::from(path)));
+        }
+        return Ok(());
+    }
+}
+
+macro_rules! impl_leaf
+{
+    ($t: ty, $parse: expr) =>
+    {
+        impl Reflect for $t
+        {
+            fn type_name(&self) -> &'static str
+            {
+                return type_name::<$t>();
+            }
+
+            fn fields(&self) -> Vec<FieldInfo>
+            {
+                return Vec::new();
+            }
+
+            fn field(&self, _: &str) -> Option<&dyn Reflect>
+            {
+                return None;
+            }
+
+            fn field_mut(&mut self, _: &str) -> Option<&mut dyn Reflect>
+            {
+                return None;
+            }
+
+            fn as_any(&self) -> &dyn Any
+            {
+                return self;
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn Any
+            {
+                return self;
+            }
+
+            fn to_text(&self) -> Option<String>
+            {
+                return Some(self.to_string());
+            }
+
+            fn parse_text(&mut self, text: &str) -> bool
+            {
+                return match Self::from_text(text)
+                {
+                    Some(v) =>
+                    {
+                        *self = v;
+                        true
+                    },
+                    None => false
+                };
+            }
+
+            fn from_text(text: &str) -> Option<$t>
+            {
+                let parse: fn(&str) -> Option<$t> = $parse;
+                return parse(text);
+            }
+        }
+    };
+    ($($t: ty)*) =>
+    {
+        $(
+            impl_leaf!($t, |text: &str| text.parse().ok());
+        )*
+    };
+}
+
+fn parse_object(text: &str) -> Option<ObjectRef>
+{
+    let (index, generation) = text.split_once('v')?;
+    return Some(ObjectRef::new(index.parse().ok()?, generation.parse().ok()?));
+}
+
+impl_leaf!(bool char i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64 String);
+impl_leaf!(ObjectRef, parse_object);
+
+/// *the text of an unset value is `none`, so `Some(String::from("none"))` reads back as None*
+impl <T: Reflect> Reflect for Option<T>
+{
+    fn type_name(&self) -> &'static str
+    {
+        return type_name::<Self>();
+    }
+
+    fn fields(&self) -> Vec<FieldInfo>
+    {
+        return self.as_ref().map(|v| v.fields()).unwrap_or_default();
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect>
+    {
+        return self.as_ref()?.field(name);
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect>
+    {
+        return self.as_mut()?.field_mut(name);
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        return self;
+    }
+
+    fn to_text(&self) -> Option<String>
+    {
+        return match self
+        {
+            Some(v) => v.to_text(),
+            None => Some(String::from("none"))
+        };
+    }
+
+    fn parse_text(&mut self, text: &str) -> bool
+    {
+        if text == "none"
+        {
+            *self = None;
+            return true;
+        }
+        return match self
+        {
+            Some(v) => v.parse_text(text),
+            None => match T::from_text(text)
+            {
+                Some(v) =>
+                {
+                    *self = Some(v);
+                    true
+                },
+                None => false
+            }
+        };
+    }
+
+    fn from_text(text: &str) -> Option<Self>
+    {
+        if text == "none"
+        {
+            return Some(None);
+        }
+        return T::from_text(text).map(Some);
+    }
+}
+
+/// *elements are addressed by index but not listed by [fields](Reflect::fields)*
+impl <T: Reflect> Reflect for Vec<T>
+{
+    fn type_name(&self) -> &'static str
+    {
+        return type_name::<Self>();
+    }
+
+    fn fields(&self) -> Vec<FieldInfo>
+    {
+        return Vec::new();
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect>
+    {
+        return self.get(name.parse::<usize>().ok()?).map(|v| v as &dyn Reflect);
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect>
+    {
+        return self.get_mut(name.parse::<usize>().ok()?).map(|v| v as &mut dyn Reflect);
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        return self;
+    }
+}
+
+/// Describes a reflected pool of a component manager
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PoolInfo
+{
+    /// Field name of the pool in the component manager
+    pub name: &'static str,
+
+    /// Name of the component type stored in the pool
+    pub component: &'static str
+}
+
+/// Component managers whose pools can be inspected and edited at runtime
+///
+/// *implemented by `#[derive(ComponentManager)]` when the struct is marked with `#[reflect]`*
+pub trait ReflectPools
+{
+    /// Lists the reflected pools in declaration order
+    fn reflected_pools(&self) -> Vec<PoolInfo>;
+
+    /// Lists the objects which have a component in the given pool, None if there is no such pool
+    fn owners(&self, pool: &str) -> Option<Vec<ObjectRef>>;
+
+    /// Returns the component attached to the given object in the given pool
+    fn reflect(&self, pool: &str, owner: ObjectRef) -> Option<&dyn Reflect>;
+
+    /// Mutates the component attached to the given object in the given pool through the given function
+    ///
+    /// *returns None if there is no such component; the edit goes through [Storage::edit](crate::component::Storage::edit)
+    /// so indexes of the pool are kept up to date*
+    fn reflect_mut(&mut self, pool: &str, owner: ObjectRef, f: &mut dyn FnMut(&mut dyn Reflect) -> Result<(), Error>) -> Option<Result<(), Error>>;
+
+    /// Parses text into the leaf value at the given path of the component attached to the given object
+    ///
+    /// *returns None if there is no such component*
+    fn set_text(&mut self, pool: &str, owner: ObjectRef, path: &str, text: &str) -> Option<Result<(), Error>>
+    {
+        return self.reflect_mut(pool, owner, &mut |c| c.set_text(path, text));
+    }
+
+    /// Lists all reflected components attached to the given object, along with the name of their pool
+    fn reflect_all(&self, owner: ObjectRef) -> Vec<(&'static str, &dyn Reflect)>
+    {
+        return self.reflected_pools().iter()
+            .filter_map(|p| self.reflect(p.name, owner).map(|c| (p.name, c)))
+            .collect();
+    }
+}
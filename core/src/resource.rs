@@ -0,0 +1,102 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! REGECS resource layer
+
+use std::any::Any;
+use std::any::TypeId;
+use std::any::type_name;
+use std::collections::HashMap;
+
+use crate::error::Error;
+
+/// Typed map of scene-wide resources (clock, random generator, configuration, asset tables...)
+///
+/// *at most one resource of each type can be stored*
+#[derive(Default)]
+pub struct Resources
+{
+    map: HashMap<TypeId, Box<dyn Any>>
+}
+
+impl Resources
+{
+    pub fn new() -> Resources
+    {
+        return Resources
+        {
+            map: HashMap::new()
+        };
+    }
+
+    /// Inserts a resource, returns the previous resource of the same type if any
+    pub fn insert<T: Any>(&mut self, resource: T) -> Option<T>
+    {
+        let old = self.map.insert(TypeId::of::<T>(), Box::new(resource))?;
+        return old.downcast::<T>().ok().map(|b| *b);
+    }
+
+    /// Removes a resource, returns an error if no resource of the given type exists
+    pub fn remove<T: Any>(&mut self) -> Result<T, Error>
+    {
+        let res = self.map.remove(&TypeId::of::<T>()).ok_or(Error::MissingResource(type_name::<T>()))?;
+        return Ok(*res.downcast::<T>().unwrap());
+    }
+
+    /// Returns true if a resource of the given type exists
+    pub fn contains<T: Any>(&self) -> bool
+    {
+        return self.map.contains_key(&TypeId::of::<T>());
+    }
+
+    /// Returns a resource, returns an error if no resource of the given type exists
+    pub fn get<T: Any>(&self) -> Result<&T, Error>
+    {
+        let res = self.map.get(&TypeId::of::<T>()).ok_or(Error::MissingResource(type_name::<T>()))?;
+        return Ok(res.downcast_ref::<T>().unwrap());
+    }
+
+    /// Returns a resource, returns an error if no resource of the given type exists
+    pub fn get_mut<T: Any>(&mut self) -> Result<&mut T, Error>
+    {
+        let res = self.map.get_mut(&TypeId::of::<T>()).ok_or(Error::MissingResource(type_name::<T>()))?;
+        return Ok(res.downcast_mut::<T>().unwrap());
+    }
+
+    /// Returns the number of resources
+    pub fn len(&self) -> usize
+    {
+        return self.map.len();
+    }
+
+    /// Returns true if there are no resources
+    pub fn is_empty(&self) -> bool
+    {
+        return self.map.is_empty();
+    }
+}
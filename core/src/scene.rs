@@ -28,41 +28,596 @@
 
 //! REGECS scene object
 
+use std::any::Any;
+use std::collections::VecDeque;
+
 use crate::object::ObjectRef;
 use crate::system::System;
+use crate::system::SystemContext;
 use crate::object::LowObject;
+use crate::event::EventContext;
+use crate::event::EventResult;
+use crate::event::PendingEvent;
+use crate::event::clone_event;
+use crate::error::Error;
+use crate::component::ComponentManager;
+use crate::component::Tick;
+use crate::resource::Resources;
+use crate::bundle::Bundle;
+use crate::prefab::Prefab;
+use crate::prefab::Capture;
+
+pub(crate) struct ObjectSlot<TState, TComponentManager>
+{
+    pub(crate) generation: u32,
+    pub(crate) alive: bool,
+    pub(crate) parent: Option<ObjectRef>,
+    pub(crate) children: Vec<ObjectRef>,
+    pub(crate) object: Option<Box<dyn LowObject<TState, TComponentManager>>>
+}
 
 /// Represents a scene, provides storage for systems and objects
+///
+/// Events are dispatched in a well-defined order:
+/// - the event queue is first-in first-out, events are dispatched one at a time
+/// - a targeted event is delivered to its target, it is dropped if the target has been removed in the meantime;
+///   follow-up events are not validated when sent, so a follow-up sent to a stale reference is dropped as well
+/// - a broadcast event is delivered to every living object in ascending ObjectRef order
+/// - follow-up events returned by a handler are appended to the queue in the order they were sent
+/// - an object which requested removal is removed, together with its descendants, as soon as
+///   its handler returns, before the event reaches the next recipient
 pub struct Scene<TState, TComponentManager>
 {
-    component_manager: TComponentManager,
-    systems: Vec<Box<dyn System<TState, TComponentManager>>>,
-    objects: Vec<Box<dyn LowObject<TState, TComponentManager>>>
+    pub(crate) component_manager: TComponentManager,
+    resources: Resources,
+    systems: Vec<(Box<dyn System<TState, TComponentManager>>, Tick)>,
+    pub(crate) objects: Vec<ObjectSlot<TState, TComponentManager>>,
+    pub(crate) free_objects: Vec<usize>,
+    pub(crate) object_count: usize,
+    pub(crate) events: VecDeque<PendingEvent>,
+    pub(crate) tick: Tick,
+    pub(crate) update_start: Tick
 }
 
-impl <TState, TComponentManager> Scene<TState, TComponentManager>
+impl <TState, TComponentManager: ComponentManager> Scene<TState, TComponentManager>
 {
-    pub fn new(component_manager: TComponentManager) -> Scene<TState, TComponentManager>
+    pub fn new(mut component_manager: TComponentManager) -> Scene<TState, TComponentManager>
     {
+        component_manager.set_tick(1);
         return Scene
         {
-            component_manager: component_manager,
+            component_manager,
+            resources: Resources::new(),
             systems: Vec::new(),
-            objects: Vec::new()
+            objects: Vec::new(),
+            free_objects: Vec::new(),
+            object_count: 0,
+            events: VecDeque::new(),
+            tick: 1,
+            update_start: 1
         };
     }
 
+    /// Runs all systems then dispatches all pending events
+    ///
+    /// *the tick is advanced before each system and before dispatching events, so that a system
+    /// querying for changes after the tick it last ran at sees the changes made by every other system;
+    /// removals are remembered until the end of the next update*
     pub fn update(&mut self, ctx: &mut TState)
     {
+        self.component_manager.clear_removed(self.update_start);
+        self.update_start = self.tick + 1;
         for i in 0..self.systems.len()
         {
-            self.systems[i].update(ctx, &mut self.component_manager);
+            self.advance_tick();
+            let (system, last_run) = &mut self.systems[i];
+            system.update(SystemContext
+            {
+                state: ctx,
+                components: &mut self.component_manager,
+                resources: &mut self.resources,
+                last_run: *last_run,
+                tick: self.tick
+            });
+            *last_run = self.tick;
+            self.component_manager.run_hooks();
+            self.component_manager.reindex();
         }
+        self.advance_tick();
+        self.process_events(ctx);
+    }
+
+    fn advance_tick(&mut self)
+    {
+        self.tick += 1;
+        self.component_manager.set_tick(self.tick);
+    }
+
+    /// Returns the current tick of this scene
+    pub fn tick(&self) -> Tick
+    {
+        return self.tick;
     }
 
     pub fn add_system<TSystem: 'static + System<TState, TComponentManager>>(&mut self, system: TSystem)
     {
         let b = Box::new(system);
-        self.systems.push(b);
+        self.systems.push((b, 0));
+    }
+
+    /// Returns the resources of this scene
+    pub fn resources(&self) -> &Resources
+    {
+        return &self.resources;
+    }
+
+    /// Returns the resources of this scene
+    pub fn resources_mut(&mut self) -> &mut Resources
+    {
+        return &mut self.resources;
+    }
+
+    /// Returns the component manager of this scene
+    pub fn components(&self) -> &TComponentManager
+    {
+        return &self.component_manager;
+    }
+
+    /// Returns the component manager of this scene
+    pub fn components_mut(&mut self) -> &mut TComponentManager
+    {
+        return &mut self.component_manager;
+    }
+
+    fn reserve_object(&mut self) -> ObjectRef
+    {
+        let index = match self.free_objects.pop()
+        {
+            Some(index) => index,
+            None =>
+            {
+                self.objects.push(ObjectSlot
+                {
+                    generation: 0,
+                    alive: false,
+                    parent: None,
+                    children: Vec::new(),
+                    object: None
+                });
+                self.objects.len() - 1
+            }
+        };
+        return ObjectRef::new(index, self.objects[index].generation);
+    }
+
+    fn insert_object(&mut self, ptr: ObjectRef, object: Option<Box<dyn LowObject<TState, TComponentManager>>>)
+    {
+        let slot = &mut self.objects[ptr.index()];
+        slot.alive = true;
+        slot.object = object;
+        self.object_count += 1;
+    }
+
+    /// Spawns a new object in this scene
+    ///
+    /// *the object is initialized through LowObject::on_init before being stored*
+    pub fn spawn_object<TObject: 'static + LowObject<TState, TComponentManager>>(&mut self, object: TObject) -> ObjectRef
+    {
+        let ptr = self.reserve_object();
+        let mut b: Box<dyn LowObject<TState, TComponentManager>> = Box::new(object);
+        b.on_init(ptr, &mut self.component_manager);
+        self.component_manager.run_hooks();
+        self.component_manager.reindex();
+        self.insert_object(ptr, Some(b));
+        return ptr;
+    }
+
+    /// Starts building a new entity in this scene
+    ///
+    /// *nothing is spawned until EntityBuilder::id is called, all components are then inserted at once*
+    pub fn spawn(&mut self) -> EntityBuilder<'_, TState, TComponentManager, ()>
+    {
+        return EntityBuilder
+        {
+            scene: self,
+            prefab: None,
+            object: None,
+            bundle: ()
+        };
+    }
+
+    /// Starts building a new entity from a prefab
+    ///
+    /// *components added to the builder are inserted after the prefab components and override them;
+    /// the object is created from the prefab factory unless one is given to the builder*
+    pub fn instantiate<'a>(&'a mut self, prefab: &'a Prefab<TState, TComponentManager>) -> EntityBuilder<'a, TState, TComponentManager, ()>
+    {
+        return EntityBuilder
+        {
+            scene: self,
+            prefab: Some(prefab),
+            object: prefab.create_object(),
+            bundle: ()
+        };
+    }
+
+    /// Captures a copy of all components and of the object at the given reference into a new prefab
+    ///
+    /// *the object is captured only if it can be copied, see Object::duplicate*
+    ///
+    /// *returns an error if the reference is stale*
+    pub fn capture(&self, target: ObjectRef) -> Result<Prefab<TState, TComponentManager>, Error>
+        where TState: 'static,
+              TComponentManager: 'static + Capture
+    {
+        let slot = self.slot(target).ok_or(Error::StaleObject(target))?;
+        let prefab = Prefab::capture(&self.component_manager, target)?;
+        return match slot.object.as_ref().and_then(|obj| obj.clone_object())
+        {
+            Some(template) => Ok(prefab.template(template)),
+            None => Ok(prefab)
+        };
+    }
+
+    /// Spawns a copy of the object at the given reference and of all its components, returns the new reference
+    ///
+    /// *the copy is a root and children are not copied; if the object cannot be copied, see Object::duplicate,
+    /// the new entity only receives the components*
+    ///
+    /// *returns an error if the reference is stale*
+    pub fn clone_object(&mut self, target: ObjectRef) -> Result<ObjectRef, Error>
+        where TState: 'static,
+              TComponentManager: 'static + Capture
+    {
+        let prefab = self.capture(target)?;
+        return self.instantiate(&prefab).try_id();
+    }
+
+    fn slot(&self, target: ObjectRef) -> Option<&ObjectSlot<TState, TComponentManager>>
+    {
+        return self.objects.get(target.index())
+            .filter(|s| s.generation == target.generation() && s.alive);
+    }
+
+    fn slot_mut(&mut self, target: ObjectRef) -> Option<&mut ObjectSlot<TState, TComponentManager>>
+    {
+        return self.objects.get_mut(target.index())
+            .filter(|s| s.generation == target.generation() && s.alive);
+    }
+
+    /// Removes an object and all its descendants from this scene
+    ///
+    /// *descendants are removed depth-first, children before their parent; each object is notified
+    /// through LowObject::on_remove, then all its components are cleared*
+    ///
+    /// *returns an error if the reference is stale*
+    pub fn remove_object(&mut self, target: ObjectRef) -> Result<(), Error>
+    {
+        let slot = self.slot(target).ok_or(Error::StaleObject(target))?;
+        if let Some(parent) = slot.parent
+        {
+            self.objects[parent.index()].children.retain(|c| *c != target);
+        }
+        let mut order = Vec::new();
+        let mut stack = vec![(target, false)];
+        while let Some((obj, visited)) = stack.pop()
+        {
+            if visited
+            {
+                order.push(obj);
+                continue;
+            }
+            stack.push((obj, true));
+            stack.extend(self.objects[obj.index()].children.iter().rev().map(|c| (*c, false)));
+        }
+        for obj in order
+        {
+            self.remove_single(obj);
+        }
+        return Ok(());
+    }
+
+    fn remove_single(&mut self, target: ObjectRef)
+    {
+        let slot = &mut self.objects[target.index()];
+        let obj = slot.object.take();
+        slot.alive = false;
+        slot.parent = None;
+        slot.children.clear();
+        slot.generation = slot.generation.wrapping_add(1);
+        if let Some(mut obj) = obj
+        {
+            obj.on_remove(target, &mut self.component_manager);
+        }
+        self.component_manager.clear_components(target);
+        self.free_objects.push(target.index());
+        self.object_count -= 1;
+    }
+
+    /// Returns true if the given reference points to a living object
+    pub fn contains_object(&self, target: ObjectRef) -> bool
+    {
+        return self.slot(target).is_some();
+    }
+
+    /// Attaches an object to a new parent, detaching it from its previous parent if any
+    ///
+    /// *returns an error if either reference is stale or if the parent is the object itself or one of its descendants*
+    pub fn set_parent(&mut self, child: ObjectRef, parent: ObjectRef) -> Result<(), Error>
+    {
+        let old = self.slot(child).ok_or(Error::StaleObject(child))?.parent;
+        if !self.contains_object(parent)
+        {
+            return Err(Error::StaleObject(parent));
+        }
+        let mut cur = Some(parent);
+        while let Some(obj) = cur
+        {
+            if obj == child
+            {
+                return Err(Error::CyclicHierarchy(child, parent));
+            }
+            cur = self.objects[obj.index()].parent;
+        }
+        if old == Some(parent)
+        {
+            return Ok(());
+        }
+        if let Some(old) = old
+        {
+            self.objects[old.index()].children.retain(|c| *c != child);
+        }
+        self.objects[child.index()].parent = Some(parent);
+        self.objects[parent.index()].children.push(child);
+        return Ok(());
+    }
+
+    /// Detaches an object from its parent, the object becomes a root
+    ///
+    /// *returns an error if the reference is stale*
+    pub fn clear_parent(&mut self, child: ObjectRef) -> Result<(), Error>
+    {
+        let slot = self.slot_mut(child).ok_or(Error::StaleObject(child))?;
+        if let Some(parent) = slot.parent.take()
+        {
+            self.objects[parent.index()].children.retain(|c| *c != child);
+        }
+        return Ok(());
+    }
+
+    /// Returns the parent of the given object, None if the object is a root or if the reference is stale
+    pub fn parent(&self, target: ObjectRef) -> Option<ObjectRef>
+    {
+        return self.slot(target)?.parent;
+    }
+
+    /// Iterates over the direct children of the given object, in the order they were attached
+    pub fn children(&self, target: ObjectRef) -> impl Iterator<Item = ObjectRef> + '_
+    {
+        return self.slot(target).into_iter().flat_map(|s| s.children.iter().copied());
+    }
+
+    /// Iterates over all descendants of the given object, depth-first with parents before their children
+    pub fn descendants(&self, target: ObjectRef) -> impl Iterator<Item = ObjectRef> + '_
+    {
+        let mut stack: Vec<ObjectRef> = self.children(target).collect();
+        stack.reverse();
+        return std::iter::from_fn(move ||
+        {
+            let obj = stack.pop()?;
+            stack.extend(self.objects[obj.index()].children.iter().rev());
+            return Some(obj);
+        });
+    }
+
+    /// Returns the object at the given reference, None if the reference is stale
+    /// or if the entity was spawned without an object
+    pub fn get_object(&self, target: ObjectRef) -> Option<&dyn LowObject<TState, TComponentManager>>
+    {
+        return self.slot(target).and_then(|s| s.object.as_deref());
+    }
+
+    /// Returns the object at the given reference, None if the reference is stale
+    /// or if the entity was spawned without an object
+    pub fn get_object_mut(&mut self, target: ObjectRef) -> Option<&mut (dyn LowObject<TState, TComponentManager> + 'static)>
+    {
+        return self.slot_mut(target).and_then(|s| s.object.as_deref_mut());
+    }
+
+    /// Returns the number of objects currently living in this scene
+    pub fn object_count(&self) -> usize
+    {
+        return self.object_count;
+    }
+
+    /// Iterates over the references of all objects living in this scene
+    pub fn objects(&self) -> impl Iterator<Item = ObjectRef> + '_
+    {
+        return self.objects.iter().enumerate()
+            .filter(|(_, s)| s.alive)
+            .map(|(i, s)| ObjectRef::new(i, s.generation));
+    }
+
+    /// Queues an event for the given target object
+    ///
+    /// *returns an error if the reference is stale*
+    pub fn send_event<EventType: Any>(&mut self, target: ObjectRef, ev: EventType) -> Result<(), Error>
+    {
+        if !self.contains_object(target)
+        {
+            return Err(Error::StaleObject(target));
+        }
+        self.events.push_back(PendingEvent
+        {
+            sender: None,
+            target: Some(target),
+            data: Box::new(ev),
+            clone: None
+        });
+        return Ok(());
+    }
+
+    /// Queues an event for all objects of this scene
+    ///
+    /// *each recipient receives its own copy of the event*
+    pub fn broadcast_event<EventType: Any + Clone>(&mut self, ev: EventType)
+    {
+        self.events.push_back(PendingEvent
+        {
+            sender: None,
+            target: None,
+            data: Box::new(ev),
+            clone: Some(clone_event::<EventType>)
+        });
+    }
+
+    /// Returns the number of events waiting to be dispatched
+    pub fn pending_events(&self) -> usize
+    {
+        return self.events.len();
+    }
+
+    /// Dispatches all pending events, including follow-up events sent by handlers, until the queue is empty
+    pub fn process_events(&mut self, ctx: &mut TState)
+    {
+        while let Some(ev) = self.events.pop_front()
+        {
+            match (ev.target, ev.clone)
+            {
+                (Some(target), _) => self.dispatch(ctx, target, ev.sender, ev.data),
+                (None, Some(clone)) =>
+                {
+                    for i in 0..self.objects.len()
+                    {
+                        let target = ObjectRef::new(i, self.objects[i].generation);
+                        if self.objects[i].object.is_some()
+                        {
+                            self.dispatch(ctx, target, ev.sender, clone(&*ev.data));
+                        }
+                    }
+                }
+                (None, None) => ()
+            }
+        }
+    }
+
+    fn dispatch(&mut self, ctx: &mut TState, target: ObjectRef, sender: Option<ObjectRef>, data: Box<dyn Any>)
+    {
+        let obj = match self.objects.get_mut(target.index())
+            .filter(|s| s.generation == target.generation() && s.alive)
+            .and_then(|s| s.object.as_mut())
+        {
+            Some(obj) => obj,
+            None => return
+        };
+        let context = EventContext
+        {
+            ptr: target,
+            other: sender,
+            state: ctx,
+            components: &mut self.component_manager,
+            resources: &mut self.resources
+        };
+        let res = obj.on_event(data, context);
+        self.component_manager.run_hooks();
+        self.component_manager.reindex();
+        if let Some(res) = res
+        {
+            self.handle_result(target, res);
+        }
+    }
+
+    fn handle_result(&mut self, sender: ObjectRef, res: EventResult)
+    {
+        for mut ev in res.to_send
+        {
+            ev.sender = Some(sender);
+            self.events.push_back(ev);
+        }
+        if res.remove_flag
+        {
+            // The sender is the object currently being dispatched, it cannot be stale
+            self.remove_object(sender).unwrap();
+        }
+    }
+}
+
+/// Fluent builder for a new entity, created by Scene::spawn
+#[must_use = "the entity is only spawned when calling id"]
+pub struct EntityBuilder<'a, TState, TComponentManager, TBundle>
+{
+    scene: &'a mut Scene<TState, TComponentManager>,
+    prefab: Option<&'a Prefab<TState, TComponentManager>>,
+    object: Option<Box<dyn LowObject<TState, TComponentManager>>>,
+    bundle: TBundle
+}
+
+impl <'a, TState, TComponentManager: ComponentManager, TBundle: Bundle<TComponentManager>> EntityBuilder<'a, TState, TComponentManager, TBundle>
+{
+    /// Adds a component or a bundle of components to the entity
+    pub fn with<TOther: Bundle<TComponentManager>>(self, bundle: TOther) -> EntityBuilder<'a, TState, TComponentManager, (TBundle, TOther)>
+    {
+        return EntityBuilder
+        {
+            scene: self.scene,
+            prefab: self.prefab,
+            object: self.object,
+            bundle: (self.bundle, bundle)
+        };
+    }
+
+    /// Attaches an object to the entity, the object is initialized after all components have been inserted
+    pub fn object<TObject: 'static + LowObject<TState, TComponentManager>>(mut self, object: TObject) -> Self
+    {
+        self.object = Some(Box::new(object));
+        return self;
+    }
+
+    /// Spawns the entity and returns its reference
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component is rejected by its storage, see [try_id](EntityBuilder::try_id).
+    pub fn id(self) -> ObjectRef
+    {
+        return match self.try_id()
+        {
+            Ok(ptr) => ptr,
+            Err(e) => panic!("{}", e)
+        };
+    }
+
+    /// Spawns the entity and returns its reference
+    ///
+    /// *returns an error if a component is rejected by its storage, components inserted before it are then
+    /// cleared and nothing is spawned*
+    pub fn try_id(self) -> Result<ObjectRef, Error>
+    {
+        let EntityBuilder { scene, prefab, mut object, bundle } = self;
+        let ptr = scene.reserve_object();
+        let res = match prefab
+        {
+            Some(prefab) => prefab.insert(ptr, &mut scene.component_manager),
+            None => Ok(())
+        };
+        if let Err(e) = res.and_then(|_| bundle.insert(ptr, &mut scene.component_manager))
+        {
+            // Hooks of the inserted components run before they are cleared so that every on_add has its on_remove
+            scene.component_manager.run_hooks();
+            scene.component_manager.clear_components(ptr);
+            // The reference may have been seen by hooks, it must not be handed out again
+            let slot = &mut scene.objects[ptr.index()];
+            slot.generation = slot.generation.wrapping_add(1);
+            scene.free_objects.push(ptr.index());
+            return Err(e);
+        }
+        if let Some(obj) = &mut object
+        {
+            obj.on_init(ptr, &mut scene.component_manager);
+        }
+        scene.component_manager.run_hooks();
+        scene.component_manager.reindex();
+        scene.insert_object(ptr, object);
+        return Ok(ptr);
     }
 }
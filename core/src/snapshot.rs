@@ -0,0 +1,800 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! REGECS binary snapshot format
+//!
+//! A snapshot is laid out as follows, all integers are little-endian:
+//! - magic `RGSN`, format version (u16) and number of sections (u32)
+//! - one header entry per section: kind (u8), name and stable component name (u16 length + UTF-8),
+//!   component type id (u64), component size (u32), component schema version (u32),
+//!   payload length (u64) and payload CRC32 (u32)
+//! - the payload of each section, in header order
+//! - a CRC32 of everything before it
+//!
+//! The object table, the pending events, the runtime-defined components and each component pool are stored
+//! in their own section, so that a reader can skip the sections it does not need. Pools saved with an older
+//! schema version of their component are upgraded through the [Migrations](crate::migration::Migrations)
+//! given to the snapshot.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::VecDeque;
+use std::convert::TryInto;
+
+use serde::Serialize;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::object::ObjectRef;
+use crate::component::ComponentManager;
+use crate::component::SerializePools;
+use crate::component::SerializeEntries;
+use crate::component::PoolVisitor;
+use crate::component::PoolLoader;
+use crate::component::Tick;
+use crate::component::DynamicComponents;
+use crate::component::ComponentId;
+use crate::scene::Scene;
+use crate::scene::ObjectSlot;
+use crate::event::PendingEvent;
+use crate::event::CloneFn;
+use crate::event::clone_event;
+use crate::migration::Migrations;
+use crate::error::Error;
+
+const MAGIC: [u8; 4] = *b"RGSN";
+
+/// Current version of the snapshot format
+pub const FORMAT_VERSION: u16 = 4;
+
+/// Oldest version of the snapshot format which can be read
+///
+/// *type ids were computed from Rust type names before format version 4*
+pub const MIN_FORMAT_VERSION: u16 = 4;
+
+const OBJECTS_SECTION: &str = "objects";
+const EVENTS_SECTION: &str = "events";
+const DYNAMIC_SECTION: &str = "dynamic";
+
+/// Returns the identifier of a stable component or event name, as stored in snapshot headers (64 bits FNV-1a)
+pub fn type_id_of(name: &str) -> u64
+{
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in name.bytes()
+    {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
+
+/// Kind of data stored in a snapshot section
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SectionKind
+{
+    Objects,
+    Events,
+    Pool,
+    Dynamic
+}
+
+/// Describes a section of a snapshot
+#[derive(Clone, Debug)]
+pub struct SectionInfo
+{
+    pub kind: SectionKind,
+    pub name: String,
+    pub type_name: String,
+    pub type_id: u64,
+
+    /// In-memory size of a component when the snapshot was written, informational only
+    pub component_size: u32,
+
+    pub schema_version: u32,
+    pub checksum: u32,
+    offset: usize,
+    len: usize
+}
+
+impl SectionInfo
+{
+    /// Returns the length in bytes of the payload of this section
+    pub fn len(&self) -> usize
+    {
+        return self.len;
+    }
+
+    /// Returns true if the payload of this section is empty
+    pub fn is_empty(&self) -> bool
+    {
+        return self.len == 0;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ObjectEntry
+{
+    generation: u32,
+    alive: bool,
+    parent: Option<ObjectRef>,
+    children: Vec<ObjectRef>
+}
+
+#[derive(Serialize, Deserialize)]
+struct ObjectTable
+{
+    tick: Tick,
+    update_start: Tick,
+    slots: Vec<ObjectEntry>
+}
+
+#[derive(Serialize, Deserialize)]
+struct EventEntry
+{
+    sender: Option<ObjectRef>,
+    target: Option<ObjectRef>,
+    type_id: u64,
+    data: Vec<u8>
+}
+
+fn invalid<T: ToString>(err: T) -> Error
+{
+    return Error::InvalidSnapshot(err.to_string());
+}
+
+fn encode_event<T: 'static + Serialize>(ev: &dyn Any) -> Result<Vec<u8>, Error>
+{
+    return bincode::serialize(ev.downcast_ref::<T>().unwrap()).map_err(invalid);
+}
+
+fn decode_event<T: 'static + DeserializeOwned>(data: &[u8]) -> Result<Box<dyn Any>, Error>
+{
+    let ev: T = bincode::deserialize(data).map_err(invalid)?;
+    return Ok(Box::new(ev));
+}
+
+type EncodeFn = fn(&dyn Any) -> Result<Vec<u8>, Error>;
+type DecodeFn = fn(&[u8]) -> Result<Box<dyn Any>, Error>;
+
+struct EventCodec
+{
+    id: u64,
+    type_id: TypeId,
+    encode: EncodeFn,
+    decode: DecodeFn,
+    clone: CloneFn
+}
+
+struct Section
+{
+    kind: SectionKind,
+    name: String,
+    type_name: String,
+    component_size: u32,
+    schema_version: u32,
+    payload: Vec<u8>
+}
+
+struct Writer
+{
+    sections: Vec<Section>
+}
+
+impl PoolVisitor for Writer
+{
+    type Error = Error;
+
+    fn visit<TStorage: SerializeEntries + Serialize>(&mut self, name: &'static str, component: &'static str, size: usize, version: u32, pool: &TStorage) -> Result<(), Error>
+    {
+        self.sections.push(Section
+        {
+            kind: SectionKind::Pool,
+            name: String::from(name),
+            type_name: String::from(component),
+            component_size: size as u32,
+            schema_version: version,
+            payload: bincode::serialize(pool).map_err(invalid)?
+        });
+        return Ok(());
+    }
+}
+
+/// First loading pass, decodes every pool into a temporary without touching the component manager
+struct Decoder<'a, 'b, TFilter: Fn(&SectionInfo) -> bool>
+{
+    reader: &'b SnapshotReader<'a>,
+    migrations: &'b Migrations,
+    filter: &'b TFilter,
+    pools: Vec<Option<Box<dyn Any>>>
+}
+
+impl <'a, 'b, TFilter: Fn(&SectionInfo) -> bool> PoolLoader for Decoder<'a, 'b, TFilter>
+{
+    type Error = Error;
+
+    fn load<TStorage: 'static + SerializeEntries + DeserializeOwned + Default>(&mut self, name: &'static str, component: &'static str, _: usize, version: u32, _: &mut TStorage) -> Result<(), Error>
+    {
+        let section = match self.reader.find(SectionKind::Pool, name)
+        {
+            Some(section) if (self.filter)(section) => section,
+            _ =>
+            {
+                self.pools.push(None);
+                return Ok(());
+            }
+        };
+        // The in-memory size depends on the compiler and the platform, only the stable name identifies the component
+        if section.type_id != type_id_of(component)
+        {
+            return Err(Error::InvalidSnapshot(format!("pool {} was saved with component {}", name, section.type_name)));
+        }
+        if section.schema_version > version
+        {
+            return Err(Error::InvalidSnapshot(format!("pool {} was saved with schema version {} of {}, newer than {}",
+                name, section.schema_version, component, version)));
+        }
+        let payload = self.reader.payload(section)?;
+        let pool: TStorage = if section.schema_version < version
+        {
+            let data = self.migrations.upgrade(component, section.schema_version, version, payload)?;
+            bincode::deserialize(&data).map_err(invalid)?
+        }
+        else
+        {
+            bincode::deserialize(payload).map_err(invalid)?
+        };
+        self.pools.push(Some(Box::new(pool)));
+        return Ok(());
+    }
+}
+
+/// Second loading pass, moves the pools decoded by the first pass into the component manager
+struct Swapper
+{
+    pools: std::vec::IntoIter<Option<Box<dyn Any>>>
+}
+
+impl PoolLoader for Swapper
+{
+    type Error = Error;
+
+    fn load<TStorage: 'static + SerializeEntries + DeserializeOwned + Default>(&mut self, _: &'static str, _: &'static str, _: usize, _: u32, pool: &mut TStorage) -> Result<(), Error>
+    {
+        // Pools are visited in declaration order in both passes
+        if let Some(Some(decoded)) = self.pools.next()
+        {
+            pool.restore(*decoded.downcast::<TStorage>().unwrap());
+        }
+        return Ok(());
+    }
+}
+
+/// Removes, without running hooks, all components whose owner is not a live object
+struct Purger
+{
+    /// Generation of each live object, None for free slots
+    live: Vec<Option<u32>>
+}
+
+impl Purger
+{
+    fn is_live(&self, owner: ObjectRef) -> bool
+    {
+        return self.live.get(owner.index()).copied().flatten() == Some(owner.generation());
+    }
+}
+
+impl PoolLoader for Purger
+{
+    type Error = Error;
+
+    fn load<TStorage: 'static + SerializeEntries + DeserializeOwned + Default>(&mut self, _: &'static str, _: &'static str, _: usize, _: u32, pool: &mut TStorage) -> Result<(), Error>
+    {
+        let dead: Vec<ObjectRef> = pool.iter().map(|(owner, _)| owner).filter(|owner| !self.is_live(*owner)).collect();
+        for owner in dead
+        {
+            pool.remove_silent(owner);
+        }
+        return Ok(());
+    }
+}
+
+/// Checks that the hierarchy of an object table only links live objects, both ways and without cycles
+fn validate_table(table: &ObjectTable) -> Result<(), Error>
+{
+    let slots = &table.slots;
+    let live = |obj: ObjectRef| slots.get(obj.index()).filter(|s| s.alive && s.generation == obj.generation());
+    let mut links = 0;
+    for (i, slot) in slots.iter().enumerate()
+    {
+        let this = ObjectRef::new(i, slot.generation);
+        if !slot.alive
+        {
+            if slot.parent.is_some() || !slot.children.is_empty()
+            {
+                return Err(Error::InvalidSnapshot(format!("removed object {} is part of the hierarchy", this)));
+            }
+            continue;
+        }
+        if let Some(parent) = slot.parent
+        {
+            if !live(parent).map(|p| p.children.contains(&this)).unwrap_or(false)
+            {
+                return Err(Error::InvalidSnapshot(format!("invalid parent {} of object {}", parent, this)));
+            }
+            links += 1;
+        }
+        for child in &slot.children
+        {
+            if live(*child).map(|c| c.parent != Some(this)).unwrap_or(true)
+            {
+                return Err(Error::InvalidSnapshot(format!("invalid child {} of object {}", child, this)));
+            }
+        }
+        // Each parent link is checked above, so walking up must reach a root within slots.len() steps
+        let mut cur = slot.parent;
+        let mut depth = 0;
+        while let Some(parent) = cur
+        {
+            depth += 1;
+            if depth > slots.len()
+            {
+                return Err(Error::InvalidSnapshot(format!("cyclic hierarchy at object {}", this)));
+            }
+            cur = slots[parent.index()].parent;
+        }
+    }
+    // Every child entry points to an object whose parent links back, so equal counts rule out duplicates
+    if slots.iter().map(|s| s.children.len()).sum::<usize>() != links
+    {
+        return Err(invalid("duplicate children in the hierarchy"));
+    }
+    return Ok(());
+}
+
+/// Parsed snapshot, gives access to the header and to individual sections
+pub struct SnapshotReader<'a>
+{
+    version: u16,
+    sections: Vec<SectionInfo>,
+    data: &'a [u8]
+}
+
+struct Cursor<'a>
+{
+    data: &'a [u8],
+    pos: usize
+}
+
+impl <'a> Cursor<'a>
+{
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error>
+    {
+        let end = self.pos.checked_add(len).filter(|e| *e <= self.data.len()).ok_or_else(|| invalid("truncated header"))?;
+        let b = &self.data[self.pos..end];
+        self.pos = end;
+        return Ok(b);
+    }
+
+    fn u8(&mut self) -> Result<u8, Error>
+    {
+        return Ok(self.bytes(1)?[0]);
+    }
+
+    fn u16(&mut self) -> Result<u16, Error>
+    {
+        return Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()));
+    }
+
+    fn u32(&mut self) -> Result<u32, Error>
+    {
+        return Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()));
+    }
+
+    fn u64(&mut self) -> Result<u64, Error>
+    {
+        return Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()));
+    }
+
+    fn string(&mut self) -> Result<String, Error>
+    {
+        let len = self.u16()? as usize;
+        return String::from_utf8(self.bytes(len)?.to_vec()).map_err(invalid);
+    }
+}
+
+impl <'a> SnapshotReader<'a>
+{
+    /// Parses the header of a snapshot
+    ///
+    /// *returns an error if the magic, the version or the checksum is invalid*
+    pub fn new(data: &'a [u8]) -> Result<SnapshotReader<'a>, Error>
+    {
+        if data.len() < 4 + 2 + 4 + 4
+        {
+            return Err(invalid("truncated snapshot"));
+        }
+        let (body, trailer) = data.split_at(data.len() - 4);
+        if crc32fast::hash(body) != u32::from_le_bytes(trailer.try_into().unwrap())
+        {
+            return Err(invalid("checksum mismatch"));
+        }
+        let mut cursor = Cursor { data: body, pos: 0 };
+        if cursor.bytes(4)? != MAGIC
+        {
+            return Err(invalid("not a snapshot"));
+        }
+        let version = cursor.u16()?;
+        if !(MIN_FORMAT_VERSION..=FORMAT_VERSION).contains(&version)
+        {
+            return Err(Error::InvalidSnapshot(format!("unsupported format version {}", version)));
+        }
+        let count = cursor.u32()?;
+        let mut sections = Vec::new();
+        for _ in 0..count
+        {
+            let kind = match cursor.u8()?
+            {
+                0 => SectionKind::Objects,
+                1 => SectionKind::Events,
+                2 => SectionKind::Pool,
+                3 => SectionKind::Dynamic,
+                k => return Err(Error::InvalidSnapshot(format!("unknown section kind {}", k)))
+            };
+            sections.push(SectionInfo
+            {
+                kind,
+                name: cursor.string()?,
+                type_name: cursor.string()?,
+                type_id: cursor.u64()?,
+                component_size: cursor.u32()?,
+                schema_version: cursor.u32()?,
+                len: cursor.u64()? as usize,
+                checksum: cursor.u32()?,
+                offset: 0
+            });
+        }
+        let mut offset = cursor.pos;
+        for section in &mut sections
+        {
+            section.offset = offset;
+            offset = offset.checked_add(section.len).filter(|o| *o <= body.len()).ok_or_else(|| invalid("truncated section"))?;
+        }
+        return Ok(SnapshotReader
+        {
+            version,
+            sections,
+            data: body
+        });
+    }
+
+    /// Returns the format version this snapshot was written with
+    pub fn version(&self) -> u16
+    {
+        return self.version;
+    }
+
+    /// Returns the header entries of all sections
+    pub fn sections(&self) -> &[SectionInfo]
+    {
+        return &self.sections;
+    }
+
+    fn find(&self, kind: SectionKind, name: &str) -> Option<&SectionInfo>
+    {
+        return self.sections.iter().find(|s| s.kind == kind && s.name == name);
+    }
+
+    /// Returns the payload of a section
+    ///
+    /// *returns an error if the section checksum does not match*
+    pub fn payload(&self, section: &SectionInfo) -> Result<&'a [u8], Error>
+    {
+        let data = &self.data[section.offset..section.offset + section.len];
+        if crc32fast::hash(data) != section.checksum
+        {
+            return Err(Error::InvalidSnapshot(format!("checksum mismatch in section {}", section.name)));
+        }
+        return Ok(data);
+    }
+}
+
+/// Writer and reader of binary scene snapshots
+///
+/// *event types which may be pending when a snapshot is taken must be registered with
+/// [event](Snapshot::event); objects cannot be serialized, entities are restored without object*
+#[derive(Default)]
+pub struct Snapshot
+{
+    events: Vec<EventCodec>,
+    migrations: Migrations
+}
+
+impl Snapshot
+{
+    pub fn new() -> Snapshot
+    {
+        return Snapshot
+        {
+            events: Vec::new(),
+            migrations: Migrations::new()
+        };
+    }
+
+    /// Sets the migrations used to upgrade pools saved with an older schema version
+    pub fn migrations(mut self, migrations: Migrations) -> Self
+    {
+        self.migrations = migrations;
+        return self;
+    }
+
+    /// Registers an event type under a stable name, which must not change between builds
+    ///
+    /// *events must be cloneable, as a pending broadcast is copied for each recipient once restored*
+    pub fn event<T: 'static + Serialize + DeserializeOwned + Clone>(mut self, name: &str) -> Self
+    {
+        self.events.push(EventCodec
+        {
+            id: type_id_of(name),
+            type_id: TypeId::of::<T>(),
+            encode: encode_event::<T>,
+            decode: decode_event::<T>,
+            clone: clone_event::<T>
+        });
+        return self;
+    }
+
+    /// Writes a snapshot of the given scene: object table, pending events, runtime-defined components
+    /// with a codec and all component pools
+    ///
+    /// *returns an error if a pending event is of an unregistered type*
+    pub fn write<TState, TComponentManager>(&self, scene: &Scene<TState, TComponentManager>) -> Result<Vec<u8>, Error>
+        where TComponentManager: ComponentManager + SerializePools
+    {
+        let table = ObjectTable
+        {
+            tick: scene.tick,
+            update_start: scene.update_start,
+            slots: scene.objects.iter().map(|s| ObjectEntry
+            {
+                generation: s.generation,
+                alive: s.alive,
+                parent: s.parent,
+                children: s.children.clone()
+            }).collect()
+        };
+        let mut events = Vec::new();
+        for ev in &scene.events
+        {
+            let codec = self.events.iter().find(|c| c.type_id == (*ev.data).type_id())
+                .ok_or_else(|| invalid("pending event of an unregistered type"))?;
+            events.push(EventEntry
+            {
+                sender: ev.sender,
+                target: ev.target,
+                type_id: codec.id,
+                data: (codec.encode)(&*ev.data)?
+            });
+        }
+        let mut writer = Writer
+        {
+            sections: vec![
+                Section
+                {
+                    kind: SectionKind::Objects,
+                    name: String::from(OBJECTS_SECTION),
+                    type_name: String::new(),
+                    component_size: 0,
+                    schema_version: 0,
+                    payload: bincode::serialize(&table).map_err(invalid)?
+                },
+                Section
+                {
+                    kind: SectionKind::Events,
+                    name: String::from(EVENTS_SECTION),
+                    type_name: String::new(),
+                    component_size: 0,
+                    schema_version: 0,
+                    payload: bincode::serialize(&events).map_err(invalid)?
+                }
+            ]
+        };
+        if let Some(dynamic) = scene.component_manager.dynamic()
+        {
+            writer.sections.push(Section
+            {
+                kind: SectionKind::Dynamic,
+                name: String::from(DYNAMIC_SECTION),
+                type_name: String::new(),
+                component_size: 0,
+                schema_version: 0,
+                payload: bincode::serialize(dynamic).map_err(invalid)?
+            });
+        }
+        scene.component_manager.visit_pools(&mut writer)?;
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&(writer.sections.len() as u32).to_le_bytes());
+        for section in &writer.sections
+        {
+            out.push(section.kind as u8);
+            for s in [&section.name, &section.type_name]
+            {
+                out.extend_from_slice(&(s.len() as u16).to_le_bytes());
+                out.extend_from_slice(s.as_bytes());
+            }
+            let type_id = match section.kind
+            {
+                SectionKind::Pool => type_id_of(&section.type_name),
+                _ => 0
+            };
+            out.extend_from_slice(&type_id.to_le_bytes());
+            out.extend_from_slice(&section.component_size.to_le_bytes());
+            out.extend_from_slice(&section.schema_version.to_le_bytes());
+            out.extend_from_slice(&(section.payload.len() as u64).to_le_bytes());
+            out.extend_from_slice(&crc32fast::hash(&section.payload).to_le_bytes());
+        }
+        for section in &writer.sections
+        {
+            out.extend_from_slice(&section.payload);
+        }
+        let checksum = crc32fast::hash(&out);
+        out.extend_from_slice(&checksum.to_le_bytes());
+        return Ok(out);
+    }
+
+    /// Restores a snapshot into the given scene
+    ///
+    /// *objects of the scene are notified through LowObject::on_remove and discarded, entities are
+    /// restored without object*
+    pub fn read<TState, TComponentManager>(&self, data: &[u8], scene: &mut Scene<TState, TComponentManager>) -> Result<(), Error>
+        where TComponentManager: ComponentManager + SerializePools
+    {
+        return self.read_with(data, scene, |_| true);
+    }
+
+    /// Restores the sections of a snapshot accepted by the given filter into the given scene
+    ///
+    /// *pools whose section is skipped or missing are left untouched, except for components whose owner is not
+    /// a live object once the snapshot is restored, which are cleared without running hooks; every section is
+    /// decoded before the scene is modified, so on error the scene is left unchanged*
+    pub fn read_with<TState, TComponentManager, TFilter>(&self, data: &[u8], scene: &mut Scene<TState, TComponentManager>, filter: TFilter) -> Result<(), Error>
+        where TComponentManager: ComponentManager + SerializePools,
+              TFilter: Fn(&SectionInfo) -> bool
+    {
+        let reader = SnapshotReader::new(data)?;
+        let mut events = None;
+        if let Some(section) = reader.find(SectionKind::Events, EVENTS_SECTION).filter(|s| filter(s))
+        {
+            let entries: Vec<EventEntry> = bincode::deserialize(reader.payload(section)?).map_err(invalid)?;
+            let mut queue = VecDeque::new();
+            for entry in entries
+            {
+                let codec = self.events.iter().find(|c| c.id == entry.type_id)
+                    .ok_or_else(|| invalid("pending event of an unregistered type"))?;
+                queue.push_back(PendingEvent
+                {
+                    sender: entry.sender,
+                    target: entry.target,
+                    data: (codec.decode)(&entry.data)?,
+                    clone: Some(codec.clone)
+                });
+            }
+            events = Some(queue);
+        }
+        let mut table = None;
+        if let Some(section) = reader.find(SectionKind::Objects, OBJECTS_SECTION).filter(|s| filter(s))
+        {
+            let t: ObjectTable = bincode::deserialize(reader.payload(section)?).map_err(invalid)?;
+            validate_table(&t)?;
+            table = Some(t);
+        }
+        let mut dynamic = None;
+        if let Some(section) = reader.find(SectionKind::Dynamic, DYNAMIC_SECTION).filter(|s| filter(s))
+        {
+            if let Some(current) = scene.component_manager.dynamic()
+            {
+                let saved: DynamicComponents = bincode::deserialize(reader.payload(section)?).map_err(invalid)?;
+                dynamic = Some(current.prepare_restore(saved)?);
+            }
+        }
+        let mut decoder = Decoder
+        {
+            reader: &reader,
+            migrations: &self.migrations,
+            filter: &filter,
+            pools: Vec::new()
+        };
+        scene.component_manager.load_pools(&mut decoder)?;
+        // Everything is decoded, nothing below can fail; objects are notified while their components still exist
+        if table.is_some()
+        {
+            for i in 0..scene.objects.len()
+            {
+                let slot = &mut scene.objects[i];
+                if let (true, Some(mut obj)) = (slot.alive, slot.object.take())
+                {
+                    obj.on_remove(ObjectRef::new(i, slot.generation), &mut scene.component_manager);
+                }
+            }
+        }
+        let mut swapper = Swapper
+        {
+            pools: decoder.pools.into_iter()
+        };
+        scene.component_manager.load_pools(&mut swapper)?;
+        if let (Some(restore), Some(current)) = (dynamic, scene.component_manager.dynamic_mut())
+        {
+            current.commit_restore(restore);
+        }
+        if let Some(table) = table
+        {
+            scene.objects = table.slots.into_iter().map(|e| ObjectSlot
+            {
+                generation: e.generation,
+                alive: e.alive,
+                parent: e.parent,
+                children: e.children,
+                object: None
+            }).collect();
+            scene.free_objects = scene.objects.iter().enumerate().rev()
+                .filter(|(_, s)| !s.alive)
+                .map(|(i, _)| i)
+                .collect();
+            scene.object_count = scene.objects.len() - scene.free_objects.len();
+            scene.tick = table.tick;
+            scene.update_start = table.update_start;
+            scene.component_manager.set_tick(table.tick);
+        }
+        if let Some(events) = events
+        {
+            scene.events = events;
+        }
+        // Whichever of the object table and the pools the filter skipped, no component may outlive its object
+        let mut purger = Purger
+        {
+            live: scene.objects.iter().map(|s| Some(s.generation).filter(|_| s.alive)).collect()
+        };
+        scene.component_manager.load_pools(&mut purger)?;
+        if let Some(dynamic) = scene.component_manager.dynamic_mut()
+        {
+            let ids: Vec<ComponentId> = dynamic.pools().map(|(id, _)| id).collect();
+            for id in ids
+            {
+                let pool = dynamic.pool_mut(id).unwrap();
+                let dead: Vec<ObjectRef> = pool.owners().iter().copied().filter(|owner| !purger.is_live(*owner)).collect();
+                for owner in dead
+                {
+                    pool.remove(owner);
+                }
+            }
+        }
+        return Ok(());
+    }
+}
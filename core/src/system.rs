@@ -28,8 +28,24 @@
 
 //! REGECS system layer
 
+use crate::resource::Resources;
+use crate::component::Tick;
+
+/// Context given to a system when it runs
+///
+/// *last_run is the tick the system last ran at (0 on its first run), tick is the current tick;
+/// a system sees the changes of every other system by querying for changes after last_run*
+pub struct SystemContext<'a, TState, TComponentManager>
+{
+    pub state: &'a mut TState,
+    pub components: &'a mut TComponentManager,
+    pub resources: &'a mut Resources,
+    pub last_run: Tick,
+    pub tick: Tick
+}
+
 /// System interface
 pub trait System<TState, TComponentManager>
 {
-    fn update(&mut self, ctx: &mut TState, components: &mut TComponentManager);
+    fn update(&mut self, context: SystemContext<TState, TComponentManager>);
 }
@@ -0,0 +1,227 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::archetype::ArchetypeManager;
+use regecs::component::ComponentManager;
+use regecs::component::Hooks;
+use regecs::object::ObjectRef;
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+#[test]
+fn adding_components_moves_rows()
+{
+    let mut mgr = ArchetypeManager::new();
+    mgr.insert(obj(0), 1u32);
+    mgr.insert(obj(1), 2u32);
+    mgr.insert(obj(0), String::from("a"));
+    assert_eq!(mgr.archetype_count(), 3);
+    assert_eq!(mgr.get::<u32>(obj(0)), Some(&1));
+    assert_eq!(mgr.get::<String>(obj(0)).map(|s| s.as_str()), Some("a"));
+    assert_eq!(mgr.get::<u32>(obj(1)), Some(&2));
+    assert_eq!(mgr.get::<String>(obj(1)), None);
+    assert_eq!(mgr.insert(obj(0), 5u32), Some(1));
+}
+
+#[test]
+fn removing_components_updates_swapped_rows()
+{
+    let mut mgr = ArchetypeManager::new();
+    for i in 0..3
+    {
+        mgr.insert(obj(i), i as u32);
+        mgr.insert(obj(i), i as u64 * 10);
+    }
+    assert_eq!(mgr.remove::<u64>(obj(0)), Some(0));
+    assert_eq!(mgr.get::<u32>(obj(0)), Some(&0));
+    assert_eq!(mgr.get::<u64>(obj(0)), None);
+    assert_eq!(mgr.get::<u64>(obj(2)), Some(&20));
+    assert_eq!(mgr.get::<u32>(obj(2)), Some(&2));
+    assert_eq!(mgr.remove::<u64>(obj(0)), None);
+    let mut owners: Vec<ObjectRef> = mgr.iter::<u64>().map(|(o, _)| o).collect();
+    owners.sort();
+    assert_eq!(owners, vec![obj(1), obj(2)]);
+}
+
+#[test]
+fn query_visits_every_matching_table()
+{
+    let mut mgr = ArchetypeManager::new();
+    for i in 0..4
+    {
+        mgr.insert(obj(i), i as u32);
+        if i % 2 == 0
+        {
+            mgr.insert(obj(i), 1.5f32);
+        }
+    }
+    mgr.insert(obj(2), 'x');
+    let mut seen = Vec::new();
+    for (o, (a, b)) in mgr.query::<(&mut u32, &f32)>()
+    {
+        *a += *b as u32;
+        seen.push(o);
+    }
+    seen.sort();
+    assert_eq!(seen, vec![obj(0), obj(2)]);
+    assert_eq!(mgr.get::<u32>(obj(2)), Some(&3));
+    assert_eq!(mgr.get::<u32>(obj(1)), Some(&1));
+    assert_eq!(mgr.query::<&u32>().count(), 4);
+}
+
+#[test]
+#[should_panic]
+fn query_rejects_aliasing_terms()
+{
+    let mut mgr = ArchetypeManager::new();
+    mgr.insert(obj(0), 1u32);
+    let _ = mgr.query::<(&mut u32, &u32)>();
+}
+
+#[test]
+fn clear_components_removes_row()
+{
+    let mut mgr = ArchetypeManager::new();
+    mgr.insert(obj(0), 1u32);
+    mgr.insert(obj(1), 2u32);
+    mgr.clear_components(obj(0));
+    assert!(!mgr.contains::<u32>(obj(0)));
+    assert_eq!(mgr.get::<u32>(obj(1)), Some(&2));
+    assert_eq!(mgr.query::<&u32>().count(), 1);
+}
+
+#[test]
+fn newer_generation_discards_left_behind_row()
+{
+    let mut mgr = ArchetypeManager::new();
+    mgr.insert(ObjectRef::new(0, 0), 1u32);
+    mgr.insert(ObjectRef::new(0, 1), 2u32);
+    assert_eq!(mgr.get::<u32>(ObjectRef::new(0, 0)), None);
+    assert_eq!(mgr.iter::<u32>().collect::<Vec<_>>(), vec![(ObjectRef::new(0, 1), &2)]);
+}
+
+#[test]
+fn removing_last_component_returns_to_empty_archetype()
+{
+    let mut mgr = ArchetypeManager::new();
+    mgr.insert(obj(0), 1u32);
+    mgr.insert(obj(0), 2u64);
+    assert_eq!(mgr.archetype_of(obj(0)), Some(2));
+    mgr.remove::<u32>(obj(0));
+    mgr.remove::<u64>(obj(0));
+    assert_eq!(mgr.archetype_of(obj(0)), Some(0));
+    assert_eq!(mgr.archetype_count(), 4);
+    mgr.insert(obj(0), 3u32);
+    assert_eq!(mgr.archetype_of(obj(0)), Some(1));
+    assert_eq!(mgr.get::<u32>(obj(0)), Some(&3));
+}
+
+#[test]
+fn ticks_follow_rows_across_archetypes()
+{
+    let mut mgr = ArchetypeManager::new();
+    mgr.set_tick(1);
+    mgr.insert(obj(0), 1u32);
+    mgr.insert(obj(1), 2u32);
+    mgr.set_tick(2);
+    mgr.insert(obj(0), 1.5f32);
+    assert_eq!(mgr.added_tick::<u32>(obj(0)), Some(1));
+    assert_eq!(mgr.added_tick::<f32>(obj(0)), Some(2));
+    mgr.set_tick(3);
+    for (_, v) in mgr.query::<&mut u32>()
+    {
+        *v += 1;
+    }
+    assert_eq!(mgr.changed_tick::<u32>(obj(0)), Some(3));
+    assert_eq!(mgr.changed_tick::<f32>(obj(0)), Some(2));
+    mgr.set_tick(4);
+    *mgr.get_mut::<f32>(obj(0)).unwrap() = 2.0;
+    assert_eq!(mgr.changed_tick::<f32>(obj(0)), Some(4));
+    assert_eq!(mgr.changed_tick::<u32>(obj(0)), Some(3));
+    assert_eq!(mgr.added_tick::<u32>(obj(0)), Some(1));
+}
+
+#[test]
+fn removals_are_remembered_until_cleared()
+{
+    let mut mgr = ArchetypeManager::new();
+    mgr.insert(obj(0), 1u32);
+    mgr.insert(obj(1), 2u32);
+    mgr.insert(obj(1), 'x');
+    mgr.set_tick(1);
+    mgr.remove::<u32>(obj(0));
+    mgr.set_tick(2);
+    mgr.clear_components(obj(1));
+    let mut removed: Vec<ObjectRef> = mgr.removed_since::<u32>(0).collect();
+    removed.sort();
+    assert_eq!(removed, vec![obj(0), obj(1)]);
+    assert_eq!(mgr.removed_since::<u32>(1).collect::<Vec<_>>(), vec![obj(1)]);
+    assert_eq!(mgr.removed_since::<char>(0).collect::<Vec<_>>(), vec![obj(1)]);
+    mgr.clear_removed(2);
+    assert_eq!(mgr.removed_since::<u32>(0).collect::<Vec<_>>(), vec![obj(1)]);
+    mgr.clear_removed(3);
+    assert_eq!(mgr.removed_since::<u32>(0).count(), 0);
+}
+
+fn count_added(mgr: &mut ArchetypeManager, _: ObjectRef)
+{
+    *mgr.get_mut::<u64>(obj(100)).unwrap() += 1;
+}
+
+fn clear_label(mgr: &mut ArchetypeManager, owner: ObjectRef)
+{
+    assert!(mgr.contains::<u32>(owner));
+    mgr.remove::<char>(owner);
+}
+
+#[test]
+fn hooks_run_on_add_and_remove()
+{
+    let mut mgr = ArchetypeManager::new();
+    mgr.insert(obj(100), 0u64);
+    mgr.set_hooks::<u32>(Hooks::new().on_add(count_added).on_remove(clear_label));
+    mgr.insert(obj(0), 1u32);
+    mgr.insert(obj(0), 'a');
+    mgr.insert(obj(1), 2u32);
+    mgr.insert(obj(1), 3u32);
+    assert_eq!(mgr.get::<u64>(obj(100)), Some(&0));
+    mgr.run_hooks();
+    assert_eq!(mgr.get::<u64>(obj(100)), Some(&2));
+    mgr.clear_components(obj(0));
+    assert!(!mgr.contains::<char>(obj(0)));
+    assert_eq!(mgr.archetype_of(obj(0)), None);
+    mgr.clear_hooks::<u32>();
+    mgr.insert(obj(2), 4u32);
+    mgr.run_hooks();
+    assert_eq!(mgr.get::<u64>(obj(100)), Some(&2));
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ChunkedPool;
+use regecs::object::ObjectRef;
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+#[test]
+fn chunks_are_allocated_on_demand()
+{
+    let mut pool = ChunkedPool::<u32, 4>::new();
+    assert_eq!(pool.chunk_count(), 0);
+    for i in 0..5
+    {
+        assert_eq!(pool.insert(obj(i), i as u32), None);
+    }
+    assert_eq!(pool.len(), 5);
+    assert_eq!(pool.chunk_count(), 2);
+    assert_eq!(pool.capacity(), 8);
+    assert_eq!(pool.iter().map(|(o, v)| (o.index(), *v)).collect::<Vec<_>>(), vec![(0, 0), (1, 1), (2, 2), (3, 3), (4, 4)]);
+}
+
+#[test]
+fn components_never_move()
+{
+    let mut pool = ChunkedPool::<u64, 2>::new();
+    pool.insert(obj(0), 10);
+    let addr = pool.get(obj(0)).unwrap() as *const u64;
+    for i in 1..32
+    {
+        pool.insert(obj(i), i as u64);
+    }
+    for i in (1..32).step_by(2)
+    {
+        pool.remove(obj(i));
+    }
+    assert_eq!(pool.get(obj(0)).unwrap() as *const u64, addr);
+    assert_eq!(pool.get(obj(0)), Some(&10));
+}
+
+#[test]
+fn freed_slots_are_recycled()
+{
+    let mut pool = ChunkedPool::<u32, 4>::new();
+    for i in 0..4
+    {
+        pool.insert(obj(i), i as u32);
+    }
+    assert_eq!(pool.remove(obj(1)), Some(1));
+    assert_eq!(pool.remove(obj(1)), None);
+    assert!(!pool.contains(obj(1)));
+    pool.insert(obj(9), 9);
+    assert_eq!(pool.chunk_count(), 1);
+    assert_eq!(pool.len(), 4);
+    assert_eq!(pool.get(obj(9)), Some(&9));
+}
+
+#[test]
+fn insert_replaces_in_place()
+{
+    let mut pool = ChunkedPool::<u32, 4>::new();
+    pool.insert(obj(0), 1);
+    let addr = pool.get(obj(0)).unwrap() as *const u32;
+    assert_eq!(pool.insert(obj(0), 2), Some(1));
+    *pool.get_mut(obj(0)).unwrap() += 1;
+    assert_eq!(pool.get(obj(0)).unwrap() as *const u32, addr);
+    assert_eq!(pool.get(obj(0)), Some(&3));
+    assert_eq!(pool.len(), 1);
+}
+
+#[test]
+fn newer_owner_generation_discards_left_behind_component()
+{
+    let mut pool = ChunkedPool::<u32, 4>::new();
+    pool.insert(ObjectRef::new(0, 0), 1);
+    assert_eq!(pool.insert(ObjectRef::new(0, 1), 2), None);
+    assert_eq!(pool.len(), 1);
+    assert_eq!(pool.get(ObjectRef::new(0, 0)), None);
+    assert_eq!(pool.get(ObjectRef::new(0, 1)), Some(&2));
+    assert_eq!(pool.remove(ObjectRef::new(0, 0)), None);
+    assert_eq!(pool.iter_mut().map(|(o, v)| (o, *v)).collect::<Vec<_>>(), vec![(ObjectRef::new(0, 1), 2)]);
+}
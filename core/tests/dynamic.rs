@@ -0,0 +1,240 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::panic::AssertUnwindSafe;
+use std::rc::Rc;
+
+use regecs::component::ComponentInfo;
+use regecs::component::DynamicComponents;
+use regecs::component::DynamicPool;
+use regecs::component::Storage;
+use regecs::error::Error;
+use regecs::object::ObjectRef;
+use regecs::query::Query;
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+/// Counts drops in a shared counter, cloning panics when asked to
+struct Counted
+{
+    value: u32,
+    drops: Rc<Cell<usize>>,
+    panic_on_clone: bool
+}
+
+impl Clone for Counted
+{
+    fn clone(&self) -> Self
+    {
+        if self.panic_on_clone
+        {
+            panic!("clone failed");
+        }
+        return Counted
+        {
+            value: self.value,
+            drops: self.drops.clone(),
+            panic_on_clone: false
+        };
+    }
+}
+
+impl Drop for Counted
+{
+    fn drop(&mut self)
+    {
+        self.drops.set(self.drops.get() + 1);
+    }
+}
+
+fn counted(value: u32, drops: &Rc<Cell<usize>>) -> Counted
+{
+    return Counted
+    {
+        value,
+        drops: drops.clone(),
+        panic_on_clone: false
+    };
+}
+
+#[test]
+fn components_are_dropped_once()
+{
+    let drops = Rc::new(Cell::new(0));
+    {
+        let mut pool = DynamicPool::new(ComponentInfo::of_clone::<Counted>("counted"));
+        for i in 0..10
+        {
+            pool.insert(obj(i), counted(i as u32, &drops)).unwrap();
+        }
+        pool.insert(obj(3), counted(33, &drops)).unwrap();
+        assert_eq!(drops.get(), 1);
+        assert!(pool.remove(obj(0)));
+        assert_eq!(drops.get(), 2);
+        let taken = pool.take::<Counted>(obj(5)).unwrap();
+        assert_eq!(taken.value, 5);
+        drop(taken);
+        assert_eq!(drops.get(), 3);
+        assert!(pool.clone_component(obj(3), obj(20)).unwrap());
+        assert!(pool.clone_component(obj(3), obj(1)).unwrap());
+        assert_eq!(drops.get(), 4);
+        assert_eq!(pool.get::<Counted>(obj(1)).unwrap().value, 33);
+        assert_eq!(pool.len(), 9);
+    }
+    // 10 inserted, 1 replacement and 2 clones, every one dropped exactly once
+    assert_eq!(drops.get(), 13);
+}
+
+#[test]
+fn growth_keeps_components()
+{
+    let mut pool = DynamicPool::new(ComponentInfo::of::<String>("name"));
+    for i in 0..1000
+    {
+        pool.insert(obj(i), format!("object {}", i)).unwrap();
+    }
+    for i in (0..1000).step_by(3)
+    {
+        assert!(pool.remove(obj(i)));
+    }
+    assert_eq!(pool.len(), 666);
+    for i in 0..1000
+    {
+        let expected = if i % 3 == 0 { None } else { Some(format!("object {}", i)) };
+        assert_eq!(pool.get::<String>(obj(i)).cloned(), expected);
+    }
+    assert!(pool.get::<u32>(obj(1)).is_none());
+    assert_eq!(pool.insert(obj(1), 1u32), Err(Error::ComponentTypeMismatch("u32")));
+}
+
+struct Empty;
+
+#[test]
+fn zero_sized_components()
+{
+    let mut pool = DynamicPool::new(ComponentInfo::of::<Empty>("empty"));
+    for i in 0..100
+    {
+        pool.insert(obj(i), Empty).unwrap();
+    }
+    assert!(pool.remove(obj(50)));
+    assert!(pool.take::<Empty>(obj(51)).is_some());
+    assert_eq!(pool.len(), 98);
+    assert!(pool.get::<Empty>(obj(99)).is_some());
+    assert!(pool.get::<Empty>(obj(50)).is_none());
+}
+
+#[repr(align(64))]
+#[derive(Clone, Debug, PartialEq)]
+struct Aligned([u8; 3]);
+
+#[test]
+fn over_aligned_components()
+{
+    let mut pool = DynamicPool::new(ComponentInfo::of_clone::<Aligned>("aligned"));
+    for i in 0..33
+    {
+        pool.insert(obj(i), Aligned([i as u8; 3])).unwrap();
+    }
+    assert!(pool.clone_component(obj(0), obj(40)).unwrap());
+    for i in 0..33
+    {
+        let comp = pool.get::<Aligned>(obj(i)).unwrap();
+        assert_eq!(comp as *const Aligned as usize % 64, 0);
+        assert_eq!(comp, &Aligned([i as u8; 3]));
+    }
+    assert_eq!(pool.get::<Aligned>(obj(40)), Some(&Aligned([0; 3])));
+}
+
+#[test]
+fn panicking_clone_leaves_pool_untouched()
+{
+    let drops = Rc::new(Cell::new(0));
+    let mut pool = DynamicPool::new(ComponentInfo::of_clone::<Counted>("counted"));
+    pool.insert(obj(0), Counted { value: 1, drops: drops.clone(), panic_on_clone: true }).unwrap();
+    pool.insert(obj(1), counted(2, &drops)).unwrap();
+    let res = std::panic::catch_unwind(AssertUnwindSafe(|| pool.clone_component(obj(0), obj(1))));
+    assert!(res.is_err());
+    assert_eq!(drops.get(), 0);
+    assert_eq!(pool.get::<Counted>(obj(1)).unwrap().value, 2);
+    assert_eq!(pool.len(), 2);
+    drop(pool);
+    assert_eq!(drops.get(), 2);
+}
+
+#[test]
+fn oversized_pool_reports_an_error()
+{
+    let layout = Layout::from_size_align(usize::MAX / 4, 1).unwrap();
+    let info = unsafe { ComponentInfo::new("huge", layout, None, None) };
+    let mut pool = DynamicPool::new(info);
+    let res = unsafe { pool.insert_raw(obj(0), std::ptr::NonNull::dangling().as_ptr()) };
+    assert_eq!(res, Err(Error::PoolOverflow(String::from("huge"))));
+    assert!(pool.is_empty());
+}
+
+#[test]
+fn newer_owner_generation_discards_left_behind_component()
+{
+    let mut pool = DynamicPool::new(ComponentInfo::of::<u32>("value"));
+    pool.insert(ObjectRef::new(0, 0), 1u32).unwrap();
+    pool.insert(obj(1), 2u32).unwrap();
+    pool.insert(ObjectRef::new(0, 1), 3u32).unwrap();
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.get::<u32>(ObjectRef::new(0, 0)), None);
+    assert_eq!(pool.get::<u32>(ObjectRef::new(0, 1)), Some(&3));
+    assert!(pool.remove(obj(1)));
+    assert_eq!(pool.owners(), &[ObjectRef::new(0, 1)]);
+}
+
+#[test]
+fn typed_pools_are_storages()
+{
+    let mut registry = DynamicComponents::new();
+    let id = registry.register(ComponentInfo::of::<u32>("value")).unwrap();
+    let mut pool = registry.pool_mut(id).unwrap().typed::<u32>().unwrap();
+    assert_eq!(pool.insert(obj(0), 1), Ok(None));
+    assert_eq!(pool.insert(obj(1), 2), Ok(None));
+    assert_eq!(pool.insert(obj(1), 3), Ok(Some(2)));
+    for (_, value) in Query::new((&mut pool,))
+    {
+        *value.0 *= 10;
+    }
+    assert_eq!(pool.iter().map(|(o, v)| (o, *v)).collect::<Vec<_>>(), vec![(obj(0), 10), (obj(1), 30)]);
+    assert_eq!(pool.remove(obj(0)), Some(10));
+    assert_eq!(Storage::len(&pool), 1);
+    assert!(registry.pool_mut(id).unwrap().typed::<u64>().is_none());
+    assert_eq!(registry.pool(id).unwrap().get::<u32>(obj(1)), Some(&30));
+}
@@ -0,0 +1,152 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentManager;
+use regecs::component::ComponentPool;
+use regecs::error::Error;
+use regecs::event::EventContext;
+use regecs::event::EventResult;
+use regecs::object::Object;
+use regecs::object::ObjectRef;
+use regecs::scene::Scene;
+
+#[derive(Default)]
+struct Components
+{
+    values: ComponentPool<u32>,
+    removed: Vec<ObjectRef>
+}
+
+impl ComponentManager for Components
+{
+    fn clear_components(&mut self, target: ObjectRef)
+    {
+        self.values.remove(target);
+    }
+}
+
+struct Node;
+
+impl Object<(), Components> for Node
+{
+    type EventType = ();
+
+    fn event(&mut self, _: &(), _: EventContext<(), Components>) -> Option<EventResult>
+    {
+        return None;
+    }
+
+    fn init(&mut self, ptr: ObjectRef, components: &mut Components)
+    {
+        components.values.add(ptr, ptr.index() as u32);
+    }
+
+    fn remove(&mut self, ptr: ObjectRef, components: &mut Components)
+    {
+        // Components are still attached while the object is notified
+        assert!(components.values.contains(ptr));
+        components.removed.push(ptr);
+    }
+}
+
+/// Builds root -> (a -> (a1, a2), b) and a separate object
+fn tree(scene: &mut Scene<(), Components>) -> [ObjectRef; 6]
+{
+    let root = scene.spawn_object(Node);
+    let a = scene.spawn_object(Node);
+    let a1 = scene.spawn_object(Node);
+    let a2 = scene.spawn_object(Node);
+    let b = scene.spawn_object(Node);
+    let other = scene.spawn_object(Node);
+    scene.set_parent(a, root).unwrap();
+    scene.set_parent(b, root).unwrap();
+    scene.set_parent(a1, a).unwrap();
+    scene.set_parent(a2, a).unwrap();
+    return [root, a, a1, a2, b, other];
+}
+
+#[test]
+fn hierarchy_is_ordered()
+{
+    let mut scene = Scene::new(Components::default());
+    let [root, a, a1, a2, b, other] = tree(&mut scene);
+    assert_eq!(scene.parent(a1), Some(a));
+    assert_eq!(scene.parent(root), None);
+    assert_eq!(scene.children(root).collect::<Vec<_>>(), vec![a, b]);
+    assert_eq!(scene.descendants(root).collect::<Vec<_>>(), vec![a, a1, a2, b]);
+    assert_eq!(scene.descendants(other).count(), 0);
+}
+
+#[test]
+fn remove_object_cascades_to_descendants()
+{
+    let mut scene = Scene::new(Components::default());
+    let [root, a, a1, a2, b, other] = tree(&mut scene);
+    scene.remove_object(a).unwrap();
+    assert_eq!(scene.components().removed, vec![a1, a2, a]);
+    for obj in [a, a1, a2]
+    {
+        assert!(!scene.contains_object(obj));
+        assert!(!scene.components().values.contains(obj));
+    }
+    assert_eq!(scene.children(root).collect::<Vec<_>>(), vec![b]);
+    scene.remove_object(root).unwrap();
+    assert_eq!(scene.components().removed, vec![a1, a2, a, b, root]);
+    assert_eq!(scene.object_count(), 1);
+    assert!(scene.contains_object(other));
+    assert!(scene.components().values.contains(other));
+}
+
+#[test]
+fn reused_slots_start_detached()
+{
+    let mut scene = Scene::new(Components::default());
+    let [root, a, ..] = tree(&mut scene);
+    scene.remove_object(root).unwrap();
+    let c = scene.spawn_object(Node);
+    assert_eq!(scene.parent(c), None);
+    assert_eq!(scene.children(c).count(), 0);
+    assert_eq!(scene.parent(a), None);
+}
+
+#[test]
+fn set_parent_rejects_cycles()
+{
+    let mut scene = Scene::new(Components::default());
+    let [root, a, a1, a2, b, _] = tree(&mut scene);
+    assert!(matches!(scene.set_parent(root, a1), Err(Error::CyclicHierarchy(c, p)) if c == root && p == a1));
+    assert!(matches!(scene.set_parent(a, a), Err(Error::CyclicHierarchy(_, _))));
+    assert!(matches!(scene.set_parent(a, a1), Err(Error::CyclicHierarchy(_, _))));
+    assert_eq!(scene.parent(root), None);
+    assert_eq!(scene.parent(a), Some(root));
+    scene.set_parent(a, b).unwrap();
+    assert_eq!(scene.children(root).collect::<Vec<_>>(), vec![b]);
+    assert_eq!(scene.descendants(root).collect::<Vec<_>>(), vec![b, a, a1, a2]);
+}
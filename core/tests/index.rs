@@ -0,0 +1,175 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentPool;
+use regecs::component::HashIndex;
+use regecs::component::OrderedIndex;
+use regecs::component::Storage;
+use regecs::error::Error;
+use regecs::object::ObjectRef;
+use regecs::query::Query;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Player
+{
+    name: &'static str,
+    team: u32
+}
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+fn player(name: &'static str, team: u32) -> Player
+{
+    return Player
+    {
+        name,
+        team
+    };
+}
+
+fn pool() -> ComponentPool<Player>
+{
+    let mut pool = ComponentPool::new();
+    pool.add_index(HashIndex::new("name", |p: &Player| p.name).unique()).unwrap();
+    pool.add_index(OrderedIndex::new("team", |p: &Player| p.team)).unwrap();
+    pool.add(obj(0), player("ann", 1));
+    pool.add(obj(1), player("bob", 2));
+    pool.add(obj(2), player("cid", 1));
+    return pool;
+}
+
+fn violation(res: Result<(), Error>) -> Option<ObjectRef>
+{
+    return match res
+    {
+        Err(Error::UniqueViolation(name, other)) if name == "name" => Some(other),
+        _ => None
+    };
+}
+
+#[test]
+fn unique_violations_are_rejected()
+{
+    let mut pool = pool();
+    assert_eq!(violation(pool.try_add(obj(3), player("ann", 3)).map(|_| ())), Some(obj(0)));
+    assert!(!pool.contains(obj(3)));
+    assert_eq!(violation(pool.try_insert(obj(1), player("ann", 3)).map(|_| ())), Some(obj(0)));
+    assert_eq!(pool.get(obj(1)), Some(&player("bob", 2)));
+    assert_eq!(pool.try_insert(obj(1), player("bo", 2)), Ok(Some(player("bob", 2))));
+    assert_eq!(pool.lookup("name", &"bo").unwrap(), &[obj(1)]);
+    assert!(pool.lookup("name", &"bob").unwrap().is_empty());
+    // Replacing a component by itself does not conflict with the previous key
+    assert_eq!(pool.try_insert(obj(1), player("bo", 3)), Ok(Some(player("bo", 2))));
+    assert_eq!(pool.index::<OrderedIndex<Player, u32>>("team").unwrap().iter().map(|(_, o)| o).collect::<Vec<_>>(),
+        vec![obj(0), obj(2), obj(1)]);
+}
+
+#[test]
+fn storage_insert_rejects_violations()
+{
+    let mut pool = pool();
+    assert_eq!(violation(Storage::insert(&mut pool, obj(2), player("ann", 4)).map(|_| ())), Some(obj(0)));
+    assert_eq!(pool.get(obj(2)), Some(&player("cid", 1)));
+    assert!(!pool.is_dirty());
+    assert_eq!(pool.lookup("name", &"cid").unwrap(), &[obj(2)]);
+    assert_eq!(violation(Storage::insert(&mut pool, obj(3), player("bob", 4)).map(|_| ())), Some(obj(1)));
+    assert!(!pool.contains(obj(3)));
+    assert_eq!(Storage::insert(&mut pool, obj(2), player("dan", 4)), Ok(Some(player("cid", 1))));
+    assert_eq!(pool.lookup("team", &4u32).unwrap(), &[obj(2)]);
+    assert_eq!(Storage::insert(&mut pool, obj(5), player("eve", 1)), Ok(None));
+    assert_eq!(pool.lookup("team", &1u32).unwrap(), &[obj(0), obj(5)]);
+}
+
+#[test]
+fn lookups_fail_until_mutations_are_reindexed()
+{
+    let mut pool = pool();
+    pool.get_mut(obj(0)).unwrap().team = 5;
+    assert!(matches!(pool.lookup("team", &1u32), Err(Error::StaleIndex(_))));
+    assert!(pool.index::<OrderedIndex<Player, u32>>("team").is_none());
+    pool.reindex().unwrap();
+    assert!(!pool.is_dirty());
+    assert_eq!(pool.lookup("team", &1u32).unwrap(), &[obj(2)]);
+    assert_eq!(pool.lookup("team", &5u32).unwrap(), &[obj(0)]);
+    for (_, p) in pool.iter_mut()
+    {
+        p.team += 10;
+    }
+    assert!(pool.lookup("team", &15u32).is_err());
+    pool.reindex().unwrap();
+    assert_eq!(pool.lookup("team", &15u32).unwrap(), &[obj(0)]);
+    for (_, p) in Query::new(&mut pool)
+    {
+        p.team = 0;
+    }
+    assert!(pool.is_dirty());
+    pool.reindex().unwrap();
+    assert_eq!(pool.lookup("team", &0u32).unwrap().len(), 3);
+}
+
+#[test]
+fn modify_reindexes_immediately()
+{
+    let mut pool = pool();
+    assert_eq!(pool.modify(obj(1), |p| p.team = 1), Some(Ok(())));
+    assert_eq!(pool.lookup("team", &1u32).unwrap(), &[obj(0), obj(2), obj(1)]);
+    let res = pool.modify(obj(1), |p| p.name = "cid").unwrap();
+    assert_eq!(violation(res), Some(obj(2)));
+    assert!(pool.is_dirty());
+    assert!(matches!(pool.lookup("team", &1u32), Err(Error::StaleIndex(_))));
+    assert_eq!(violation(pool.reindex()), Some(obj(2)));
+    pool.remove_index("name");
+    pool.reindex().unwrap();
+    assert_eq!(pool.lookup("team", &1u32).unwrap(), &[obj(0), obj(2), obj(1)]);
+    assert_eq!(pool.modify(obj(9), |p| p.team), None);
+}
+
+#[test]
+fn removal_unindexes_components()
+{
+    let mut pool = pool();
+    pool.remove(obj(0));
+    assert!(pool.lookup("name", &"ann").unwrap().is_empty());
+    assert_eq!(pool.lookup("team", &1u32).unwrap(), &[obj(2)]);
+    pool.add(obj(3), player("ann", 1));
+    assert_eq!(pool.lookup("name", &"ann").unwrap(), &[obj(3)]);
+    // Removing a dirty component clears its dirtiness
+    pool.get_mut(obj(2)).unwrap().team = 7;
+    assert!(pool.is_dirty());
+    pool.remove(obj(2));
+    assert!(!pool.is_dirty());
+    assert_eq!(pool.lookup("team", &1u32).unwrap(), &[obj(3)]);
+    assert!(pool.lookup("team", &7u32).unwrap().is_empty());
+    assert!(matches!(pool.lookup("missing", &1), Err(Error::UnknownIndex(_))));
+    assert!(matches!(pool.lookup("team", &"1"), Err(Error::UnknownIndex(_))));
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentPool;
+use regecs::component::Hooks;
+use regecs::component::Lifecycle;
+use regecs::component::Storage;
+use regecs::component::Tag;
+use regecs::component::TagPool;
+use regecs::object::ObjectRef;
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+#[test]
+fn handles_resolve_by_ref_and_owner()
+{
+    let mut pool = ComponentPool::new();
+    let a = pool.add(obj(0), 1);
+    let b = pool.add(obj(3), 2);
+    assert_ne!(a, b);
+    assert_eq!(pool.get(a), Some(&1));
+    assert_eq!(pool.get(obj(3)), Some(&2));
+    assert_eq!(pool.find(obj(3)), Some(b));
+    assert_eq!(pool.owner(a), Some(obj(0)));
+    assert_eq!(pool.len(), 2);
+}
+
+#[test]
+fn removed_handle_is_stale()
+{
+    let mut pool = ComponentPool::new();
+    let a = pool.add(obj(0), 1);
+    assert_eq!(pool.remove(a), Some(1));
+    assert!(!pool.contains(a));
+    assert!(!pool.contains(obj(0)));
+    assert_eq!(pool.get(a), None);
+    assert_eq!(pool.remove(a), None);
+    assert!(pool.is_empty());
+}
+
+#[test]
+fn reused_slot_rejects_old_handle()
+{
+    let mut pool = ComponentPool::new();
+    let a = pool.add(obj(0), 1);
+    pool.remove(a);
+    let b = pool.add(obj(1), 2);
+    assert_eq!(a.index(), b.index());
+    assert_ne!(a.generation(), b.generation());
+    assert_eq!(pool.get(a), None);
+    assert_eq!(pool.get_mut(a), None);
+    assert_eq!(pool.get(b), Some(&2));
+}
+
+#[test]
+fn stale_owner_is_rejected()
+{
+    let mut pool = ComponentPool::new();
+    pool.add(ObjectRef::new(0, 1), 1);
+    assert_eq!(pool.get(ObjectRef::new(0, 0)), None);
+    assert_eq!(pool.remove(ObjectRef::new(0, 0)), None);
+    assert_eq!(pool.get(ObjectRef::new(0, 1)), Some(&1));
+}
+
+#[test]
+fn replacing_keeps_handle()
+{
+    let mut pool = ComponentPool::new();
+    let a = pool.add(obj(0), 1);
+    let b = pool.add(obj(0), 2);
+    assert_eq!(a, b);
+    assert_eq!(pool.get(a), Some(&2));
+    assert_eq!(pool.len(), 1);
+}
+
+#[test]
+fn newer_owner_generation_removes_left_behind_component()
+{
+    let mut pool = ComponentPool::new();
+    let a = pool.add(ObjectRef::new(0, 0), 1);
+    let b = pool.add(ObjectRef::new(0, 1), 2);
+    assert_eq!(pool.len(), 1);
+    assert_eq!(pool.get(a), None);
+    assert_eq!(pool.get(b), Some(&2));
+    assert_eq!(pool.iter_owned().collect::<Vec<_>>(), vec![(ObjectRef::new(0, 1), &2)]);
+}
+
+#[test]
+fn compact_remaps_moved_handles()
+{
+    let mut pool = ComponentPool::new();
+    let a = pool.add(obj(0), 1);
+    let b = pool.add(obj(1), 2);
+    let c = pool.add(obj(2), 3);
+    let d = pool.add(obj(3), 4);
+    pool.remove(b);
+    let remap = pool.compact();
+    assert_eq!(pool.len(), 3);
+    assert_eq!(remap.get(a), Some(a));
+    assert_eq!(remap.get(b), None);
+    let c2 = remap.get(c).unwrap();
+    let d2 = remap.get(d).unwrap();
+    assert_eq!((c2.index(), d2.index()), (1, 2));
+    assert_eq!(remap.moved().collect::<Vec<_>>(), vec![(c, c2), (d, d2)]);
+    assert_eq!(pool.get(c), None);
+    assert_eq!(pool.get(c2), Some(&3));
+    assert_eq!(pool.get(d2), Some(&4));
+    assert_eq!(pool.get(obj(2)), Some(&3));
+    assert_eq!(pool.find(obj(3)), Some(d2));
+    assert_eq!(pool.iter().map(|(_, v)| *v).collect::<Vec<_>>(), vec![1, 3, 4]);
+}
+
+#[test]
+fn compact_keeps_released_handles_stale()
+{
+    let mut pool = ComponentPool::new();
+    pool.add(obj(0), 1);
+    let b = pool.add(obj(1), 2);
+    let c = pool.add(obj(2), 3);
+    pool.remove(b);
+    let remap = pool.compact();
+    assert!(remap.get(c).is_some());
+    let e = pool.add(obj(4), 5);
+    let f = pool.add(obj(5), 6);
+    assert_eq!(e.index(), c.index());
+    assert_eq!(pool.get(c), None);
+    assert_eq!(pool.get(b), None);
+    assert_eq!(pool.get(e), Some(&5));
+    assert_eq!(pool.get(f), Some(&6));
+    // Handles from before the compaction are never translated by a later table
+    let remap = pool.compact();
+    assert_eq!(remap.get(c), None);
+    assert_eq!(remap.moved().count(), 0);
+}
+
+struct Marker;
+
+impl Tag for Marker
+{
+    const INSTANCE: Self = Marker;
+}
+
+#[test]
+fn tags_reject_stale_generations()
+{
+    let mut pool = TagPool::<Marker>::new();
+    assert!(pool.insert(ObjectRef::new(3, 0), Marker).unwrap().is_none());
+    assert!(pool.insert(ObjectRef::new(70, 2), Marker).unwrap().is_none());
+    assert!(pool.contains(ObjectRef::new(3, 0)));
+    assert!(pool.contains(ObjectRef::new(70, 2)));
+    assert!(!pool.contains(ObjectRef::new(70, 1)));
+    assert!(pool.insert(ObjectRef::new(3, 1), Marker).unwrap().is_none());
+    assert!(!pool.contains(ObjectRef::new(3, 0)));
+    assert_eq!(pool.len(), 2);
+    assert!(pool.remove(ObjectRef::new(70, 2)).is_some());
+    assert!(pool.insert(ObjectRef::new(70, 0), Marker).unwrap().is_none());
+    let owners: Vec<ObjectRef> = pool.iter().map(|(owner, _)| owner).collect();
+    assert_eq!(owners, vec![ObjectRef::new(3, 1), ObjectRef::new(70, 0)]);
+}
+
+#[test]
+fn restore_keeps_hooks()
+{
+    let mut pool = ComponentPool::new();
+    pool.set_hooks(Hooks::<()>::new());
+    let mut other = ComponentPool::new();
+    other.add(obj(1), 2u32);
+    pool.restore(other);
+    assert!(pool.lifecycle_hooks().is_some());
+    assert_eq!(pool.pop_lifecycle(), None);
+    pool.add(obj(2), 3);
+    assert_eq!(pool.pop_lifecycle(), Some(Lifecycle::Added(obj(2))));
+}
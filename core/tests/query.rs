@@ -0,0 +1,171 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentPool;
+use regecs::component::SparseSetPool;
+use regecs::component::Storage;
+use regecs::component::DenseVecPool;
+use regecs::component::HashMapPool;
+use regecs::component::BTreePool;
+use regecs::component::ChunkedPool;
+use regecs::object::ObjectRef;
+use regecs::query::Query;
+use regecs::query::Optional;
+use regecs::query::With;
+use regecs::query::Without;
+use regecs::query::Changed;
+
+fn obj(index: usize) -> ObjectRef
+{
+    return ObjectRef::new(index, 0);
+}
+
+fn pools() -> (ComponentPool<i32>, SparseSetPool<i32>, ComponentPool<bool>)
+{
+    let mut positions = ComponentPool::new();
+    let mut velocities = SparseSetPool::new();
+    let mut frozen = ComponentPool::new();
+    for i in 0..6
+    {
+        positions.add(obj(i), 0);
+    }
+    for i in (0..6).step_by(2)
+    {
+        velocities.insert(obj(i), i as i32);
+    }
+    frozen.add(obj(4), true);
+    return (positions, velocities, frozen);
+}
+
+#[test]
+fn disjoint_mutable_join()
+{
+    let (mut positions, mut velocities, _) = pools();
+    let mut seen = Vec::new();
+    for (obj, (pos, vel)) in Query::new((&mut positions, &mut velocities))
+    {
+        *pos += *vel;
+        *vel = -1;
+        seen.push(obj);
+    }
+    seen.sort();
+    assert_eq!(seen, vec![obj(0), obj(2), obj(4)]);
+    assert_eq!(positions.get(obj(4)), Some(&4));
+    assert_eq!(positions.get(obj(1)), Some(&0));
+    assert!(velocities.iter().all(|(_, v)| *v == -1));
+}
+
+#[test]
+fn items_are_held_across_iterations()
+{
+    let (mut positions, velocities, _) = pools();
+    let items: Vec<(ObjectRef, (&mut i32, &i32))> = Query::new((&mut positions, &velocities)).collect();
+    assert_eq!(items.len(), 3);
+    for (_, (pos, vel)) in items
+    {
+        *pos = *vel * 10;
+    }
+    assert_eq!(positions.get(obj(2)), Some(&20));
+}
+
+#[test]
+fn optional_term_matches_everything()
+{
+    let (positions, velocities, _) = pools();
+    let mut items: Vec<(ObjectRef, Option<i32>)> = Query::new((&positions, Optional(&velocities)))
+        .map(|(obj, (_, vel))| (obj, vel.copied()))
+        .collect();
+    items.sort();
+    assert_eq!(items.len(), 6);
+    assert_eq!(items[1], (obj(1), None));
+    assert_eq!(items[2], (obj(2), Some(2)));
+}
+
+#[test]
+fn filter_terms()
+{
+    let (positions, velocities, frozen) = pools();
+    let mut with: Vec<ObjectRef> = Query::new((&positions, With(&velocities), Without(&frozen)))
+        .map(|(obj, _)| obj)
+        .collect();
+    with.sort();
+    assert_eq!(with, vec![obj(0), obj(2)]);
+    let without = Query::new((&positions, Without(&velocities))).count();
+    assert_eq!(without, 3);
+}
+
+#[test]
+fn changed_term_filters_by_tick()
+{
+    let (mut positions, velocities, _) = pools();
+    positions.set_tick(5);
+    *positions.get_mut(obj(2)).unwrap() = 1;
+    let changed: Vec<ObjectRef> = Query::new((Changed::new(&positions, 1), &velocities))
+        .map(|(obj, _)| obj)
+        .collect();
+    assert_eq!(changed, vec![obj(2)]);
+}
+
+#[test]
+fn empty_pool_yields_nothing()
+{
+    let (mut positions, _, _) = pools();
+    let mut empty: ComponentPool<u8> = ComponentPool::new();
+    assert_eq!(Query::new((&mut positions, &mut empty)).count(), 0);
+    assert_eq!(Query::new(&empty).count(), 0);
+    assert_eq!(Query::new((Optional(&positions), Without(&empty))).count(), 0);
+}
+
+fn hold_all<TStorage: Storage<Component = i32> + Default>()
+{
+    let mut pool = TStorage::default();
+    for i in 0..8
+    {
+        pool.insert(obj(i), i as i32).unwrap();
+    }
+    let items: Vec<(ObjectRef, &mut i32)> = Query::new(&mut pool).collect();
+    assert_eq!(items.len(), 8);
+    for (obj, comp) in items
+    {
+        *comp += obj.index() as i32;
+    }
+    assert!(pool.iter().all(|(obj, comp)| *comp == 2 * obj.index() as i32));
+}
+
+#[test]
+fn every_storage_hands_out_disjoint_items()
+{
+    hold_all::<ComponentPool<i32>>();
+    hold_all::<SparseSetPool<i32>>();
+    hold_all::<DenseVecPool<i32>>();
+    hold_all::<HashMapPool<i32>>();
+    hold_all::<BTreePool<i32>>();
+    hold_all::<ChunkedPool<i32, 4>>();
+}
@@ -0,0 +1,131 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentManager;
+use regecs::error::Error;
+use regecs::event::EventContext;
+use regecs::event::EventResult;
+use regecs::object::Object;
+use regecs::object::ObjectRef;
+use regecs::resource::Resources;
+use regecs::scene::Scene;
+use regecs::system::System;
+use regecs::system::SystemContext;
+
+#[derive(Debug, PartialEq)]
+struct Clock(u64);
+
+#[derive(Debug, PartialEq)]
+struct Seed(u32);
+
+#[derive(Default)]
+struct Components;
+
+impl ComponentManager for Components
+{
+    fn clear_components(&mut self, _: ObjectRef)
+    {
+    }
+}
+
+#[test]
+fn resources_are_keyed_by_type()
+{
+    let mut resources = Resources::new();
+    assert!(resources.is_empty());
+    assert_eq!(resources.insert(Clock(1)), None);
+    assert_eq!(resources.insert(Seed(42)), None);
+    assert_eq!(resources.insert(Clock(2)), Some(Clock(1)));
+    assert_eq!(resources.len(), 2);
+    assert!(resources.contains::<Seed>());
+    assert_eq!(resources.get::<Clock>().unwrap(), &Clock(2));
+    resources.get_mut::<Seed>().unwrap().0 += 1;
+    assert_eq!(resources.remove::<Seed>().unwrap(), Seed(43));
+    assert!(!resources.contains::<Seed>());
+    assert_eq!(resources.len(), 1);
+}
+
+#[test]
+fn missing_resource_names_the_type()
+{
+    let mut resources = Resources::new();
+    let err = resources.get::<Clock>().unwrap_err();
+    assert!(matches!(err, Error::MissingResource(name) if name.ends_with("Clock")));
+    assert!(err.to_string().contains("Clock"));
+    assert!(matches!(resources.get_mut::<Seed>(), Err(Error::MissingResource(name)) if name.ends_with("Seed")));
+    assert!(matches!(resources.remove::<Seed>(), Err(Error::MissingResource(name)) if name.ends_with("Seed")));
+}
+
+struct Ticker;
+
+impl System<(), Components> for Ticker
+{
+    fn update(&mut self, context: SystemContext<(), Components>)
+    {
+        context.resources.get_mut::<Clock>().unwrap().0 += 1;
+    }
+}
+
+struct Sampler;
+
+impl Object<(), Components> for Sampler
+{
+    type EventType = ();
+
+    fn event(&mut self, _: &(), context: EventContext<(), Components>) -> Option<EventResult>
+    {
+        let now = context.resources.get::<Clock>().unwrap().0;
+        context.resources.insert(Seed(now as u32));
+        return None;
+    }
+
+    fn init(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+
+    fn remove(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+}
+
+#[test]
+fn systems_and_events_share_resources()
+{
+    let mut scene: Scene<(), Components> = Scene::new(Components);
+    scene.resources_mut().insert(Clock(0));
+    scene.add_system(Ticker);
+    let obj = scene.spawn_object(Sampler);
+    scene.update(&mut ());
+    scene.update(&mut ());
+    scene.send_event(obj, ()).unwrap();
+    scene.process_events(&mut ());
+    assert_eq!(scene.resources().get::<Clock>().unwrap(), &Clock(2));
+    assert_eq!(scene.resources().get::<Seed>().unwrap(), &Seed(2));
+}
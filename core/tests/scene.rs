@@ -0,0 +1,350 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::ComponentManager;
+use regecs::component::ComponentPool;
+use regecs::component::Storage;
+use regecs::event::EventContext;
+use regecs::event::EventResult;
+use regecs::object::LowObject;
+use regecs::object::Object;
+use regecs::object::ObjectRef;
+use regecs::scene::Scene;
+use regecs::system::System;
+use regecs::system::SystemContext;
+use regecs::component::Tick;
+use std::any::Any;
+
+#[derive(Default)]
+struct Components
+{
+    values: ComponentPool<u32>,
+    removed: Vec<ObjectRef>,
+    received: Vec<(ObjectRef, u32)>
+}
+
+impl ComponentManager for Components
+{
+    fn clear_components(&mut self, target: ObjectRef)
+    {
+        self.values.remove(target);
+    }
+
+    fn set_tick(&mut self, tick: Tick)
+    {
+        Storage::set_tick(&mut self.values, tick);
+    }
+}
+
+struct Counter(u32);
+
+impl Object<(), Components> for Counter
+{
+    type EventType = u32;
+
+    fn event(&mut self, _: &u32, _: EventContext<(), Components>) -> Option<EventResult>
+    {
+        return None;
+    }
+
+    fn init(&mut self, ptr: ObjectRef, components: &mut Components)
+    {
+        components.values.add(ptr, self.0);
+    }
+
+    fn remove(&mut self, ptr: ObjectRef, components: &mut Components)
+    {
+        components.removed.push(ptr);
+    }
+}
+
+fn scene() -> Scene<(), Components>
+{
+    return Scene::new(Components::default());
+}
+
+#[test]
+fn spawn_initializes_object()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Counter(1));
+    let b = scene.spawn_object(Counter(2));
+    assert_ne!(a, b);
+    assert_eq!(scene.object_count(), 2);
+    assert!(scene.contains_object(a));
+    assert!(scene.get_object(b).is_some());
+    assert_eq!(scene.components().values.get(a), Some(&1));
+    assert_eq!(scene.objects().collect::<Vec<_>>(), vec![a, b]);
+}
+
+#[test]
+fn remove_invalidates_reference()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Counter(1));
+    scene.remove_object(a).unwrap();
+    assert!(!scene.contains_object(a));
+    assert!(scene.get_object(a).is_none());
+    assert!(scene.get_object_mut(a).is_none());
+    assert_eq!(scene.object_count(), 0);
+    assert_eq!(scene.components().removed, vec![a]);
+    assert_eq!(scene.components().values.get(a), None);
+    assert!(scene.remove_object(a).is_err());
+}
+
+#[test]
+fn reused_slot_rejects_old_reference()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Counter(1));
+    scene.remove_object(a).unwrap();
+    let b = scene.spawn_object(Counter(2));
+    assert_eq!(a.index(), b.index());
+    assert_ne!(a.generation(), b.generation());
+    assert!(!scene.contains_object(a));
+    assert!(scene.get_object(a).is_none());
+    assert!(scene.remove_object(a).is_err());
+    assert!(scene.contains_object(b));
+}
+
+#[derive(Clone)]
+struct Ping
+{
+    value: u32,
+    forward: Option<ObjectRef>,
+    remove: bool
+}
+
+struct Receiver;
+
+impl Object<(), Components> for Receiver
+{
+    type EventType = Ping;
+
+    fn event(&mut self, ev: &Ping, context: EventContext<(), Components>) -> Option<EventResult>
+    {
+        context.components.received.push((context.ptr, ev.value));
+        let mut res = EventResult::new();
+        if let Some(target) = ev.forward
+        {
+            res.send(target, Ping { value: ev.value + 1, forward: None, remove: false });
+        }
+        if ev.remove
+        {
+            res.remove();
+        }
+        return Some(res);
+    }
+
+    fn init(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+
+    fn remove(&mut self, ptr: ObjectRef, components: &mut Components)
+    {
+        components.removed.push(ptr);
+    }
+}
+
+fn ping(value: u32) -> Ping
+{
+    return Ping
+    {
+        value,
+        forward: None,
+        remove: false
+    };
+}
+
+#[test]
+fn targeted_event_is_delivered()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Receiver);
+    let b = scene.spawn_object(Receiver);
+    scene.send_event(b, ping(1)).unwrap();
+    scene.send_event(a, 2u64).unwrap();
+    assert_eq!(scene.pending_events(), 2);
+    scene.process_events(&mut ());
+    assert_eq!(scene.pending_events(), 0);
+    assert_eq!(scene.components().received, vec![(b, 1)]);
+}
+
+#[test]
+fn broadcast_reaches_every_object_in_order()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Receiver);
+    let b = scene.spawn_object(Receiver);
+    let c = scene.spawn_object(Receiver);
+    scene.remove_object(b).unwrap();
+    scene.broadcast_event(ping(7));
+    scene.process_events(&mut ());
+    assert_eq!(scene.components().received, vec![(a, 7), (c, 7)]);
+}
+
+#[test]
+fn follow_up_events_are_queued_in_order()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Receiver);
+    let b = scene.spawn_object(Receiver);
+    scene.send_event(a, Ping { value: 1, forward: Some(b), remove: false }).unwrap();
+    scene.send_event(b, ping(10)).unwrap();
+    scene.process_events(&mut ());
+    assert_eq!(scene.components().received, vec![(a, 1), (b, 10), (b, 2)]);
+}
+
+#[test]
+fn removal_request_removes_receiver()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Receiver);
+    let b = scene.spawn_object(Receiver);
+    scene.broadcast_event(Ping { value: 1, forward: None, remove: true });
+    scene.send_event(a, ping(2)).unwrap();
+    scene.process_events(&mut ());
+    assert!(!scene.contains_object(a));
+    assert!(!scene.contains_object(b));
+    assert_eq!(scene.components().removed, vec![a, b]);
+    assert_eq!(scene.components().received, vec![(a, 1), (b, 1)]);
+}
+
+#[test]
+fn stale_references_are_rejected()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Receiver);
+    scene.remove_object(a).unwrap();
+    let b = scene.spawn_object(Receiver);
+    assert_eq!(a.index(), b.index());
+    assert!(scene.send_event(a, ping(1)).is_err());
+    assert!(scene.set_parent(b, a).is_err());
+    assert_eq!(scene.pending_events(), 0);
+}
+
+#[test]
+fn follow_up_to_stale_reference_is_dropped()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Receiver);
+    let b = scene.spawn_object(Receiver);
+    scene.remove_object(b).unwrap();
+    let c = scene.spawn_object(Receiver);
+    scene.send_event(a, Ping { value: 1, forward: Some(b), remove: false }).unwrap();
+    scene.process_events(&mut ());
+    assert_eq!(scene.components().received, vec![(a, 1)]);
+    assert!(scene.contains_object(c));
+}
+
+struct Writer;
+
+impl System<Vec<Vec<ObjectRef>>, Components> for Writer
+{
+    fn update(&mut self, context: SystemContext<Vec<Vec<ObjectRef>>, Components>)
+    {
+        if let Some(value) = context.components.values.get_mut(ObjectRef::new(0, 0))
+        {
+            *value += 1;
+        }
+    }
+}
+
+struct Reader(Vec<Tick>);
+
+impl System<Vec<Vec<ObjectRef>>, Components> for Reader
+{
+    fn update(&mut self, context: SystemContext<Vec<Vec<ObjectRef>>, Components>)
+    {
+        self.0.push(context.last_run);
+        let changed = context.components.values.iter_changed(context.last_run).map(|(o, _)| o).collect();
+        context.state.push(changed);
+    }
+}
+
+#[test]
+fn systems_receive_last_run_tick()
+{
+    let mut scene: Scene<Vec<Vec<ObjectRef>>, Components> = Scene::new(Components::default());
+    scene.components_mut().values.add(ObjectRef::new(0, 0), 0);
+    scene.components_mut().values.add(ObjectRef::new(1, 0), 0);
+    scene.add_system(Writer);
+    scene.add_system(Reader(Vec::new()));
+    let mut seen = Vec::new();
+    scene.update(&mut seen);
+    scene.update(&mut seen);
+    assert_eq!(seen[0].len(), 2);
+    assert_eq!(seen[1], vec![ObjectRef::new(0, 0)]);
+    assert!(scene.tick() > 4);
+}
+
+#[test]
+#[cfg(all(not(feature = "large-world"), target_pointer_width = "64"))]
+#[should_panic(expected = "does not fit")]
+fn oversized_object_index_is_rejected()
+{
+    ObjectRef::new(u32::MAX as usize + 1, 0);
+}
+
+#[test]
+fn largest_object_index_is_kept()
+{
+    let obj = ObjectRef::new(u32::MAX as usize, 3);
+    assert_eq!(obj.index(), u32::MAX as usize);
+    assert_eq!(obj.generation(), 3);
+}
+
+struct Raw;
+
+impl LowObject<(), Components> for Raw
+{
+    fn on_event(&mut self, _: Box<dyn Any>, _: EventContext<(), Components>) -> Option<EventResult>
+    {
+        return None;
+    }
+
+    fn on_init(&mut self, ptr: ObjectRef, components: &mut Components)
+    {
+        components.values.add(ptr, 7);
+    }
+
+    fn on_remove(&mut self, _: ObjectRef, _: &mut Components)
+    {
+    }
+}
+
+#[test]
+fn low_objects_are_not_copyable_by_default()
+{
+    let mut scene = scene();
+    let a = scene.spawn_object(Raw);
+    assert_eq!(scene.components().values.get(a), Some(&7));
+    assert!(LowObject::<(), Components>::clone_object(&Raw).is_none());
+}
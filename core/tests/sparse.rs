@@ -0,0 +1,71 @@
+// Copyright (c) 2021, BlockProject 3D
+//
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without modification,
+// are permitted provided that the following conditions are met:
+//
+//     * Redistributions of source code must retain the above copyright notice,
+//       this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above copyright notice,
+//       this list of conditions and the following disclaimer in the documentation
+//       and/or other materials provided with the distribution.
+//     * Neither the name of BlockProject 3D nor the names of its contributors
+//       may be used to endorse or promote products derived from this software
+//       without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT OWNER OR
+// CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL,
+// EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT LIMITED TO,
+// PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE, DATA, OR
+// PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY THEORY OF
+// LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT (INCLUDING
+// NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE OF THIS
+// SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#![allow(clippy::needless_return)]
+
+use regecs::component::SparseSetPool;
+use regecs::object::ObjectRef;
+
+#[test]
+fn removal_keeps_packing()
+{
+    let mut pool = SparseSetPool::new();
+    for i in 0..4
+    {
+        pool.insert(ObjectRef::new(i, 0), i);
+    }
+    assert_eq!(pool.remove(ObjectRef::new(1, 0)), Some(1));
+    assert_eq!(pool.len(), 3);
+    assert_eq!(pool.owners(), &[ObjectRef::new(0, 0), ObjectRef::new(3, 0), ObjectRef::new(2, 0)]);
+    assert_eq!(pool.get(ObjectRef::new(3, 0)), Some(&3));
+    assert_eq!(pool.get(ObjectRef::new(1, 0)), None);
+}
+
+#[test]
+fn newer_generation_replaces_in_place()
+{
+    let mut pool = SparseSetPool::new();
+    pool.insert(ObjectRef::new(0, 0), 1);
+    pool.insert(ObjectRef::new(1, 0), 2);
+    assert_eq!(pool.insert(ObjectRef::new(0, 1), 3), None);
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.get(ObjectRef::new(0, 0)), None);
+    assert_eq!(pool.get(ObjectRef::new(0, 1)), Some(&3));
+    assert_eq!(pool.remove(ObjectRef::new(0, 1)), Some(3));
+    assert_eq!(pool.len(), 1);
+    assert_eq!(pool.iter().collect::<Vec<_>>(), vec![(ObjectRef::new(1, 0), &2)]);
+}
+
+#[test]
+fn same_owner_returns_previous()
+{
+    let mut pool = SparseSetPool::new();
+    pool.insert(ObjectRef::new(2, 4), 1);
+    assert_eq!(pool.insert(ObjectRef::new(2, 4), 2), Some(1));
+    assert_eq!(pool.len(), 1);
+}